@@ -0,0 +1,226 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+
+use crate::{metrics::AllocationMetrics, nomad};
+
+/// Client-Statuses of Allocations that are no longer running
+const STOPPED_CLIENT_STATUSES: [&str; 3] = ["complete", "failed", "lost"];
+/// Desired-Statuses of Allocations that are being stopped
+const STOPPED_DESIRED_STATUSES: [&str; 2] = ["stop", "evict"];
+
+/// The Payload of an Allocation Event
+#[derive(Debug, Deserialize)]
+pub struct AllocationPayload {
+    #[serde(rename = "Allocation")]
+    pub allocation: Allocation,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Allocation {
+    #[serde(rename = "ID")]
+    pub id: String,
+    #[serde(rename = "TaskGroup")]
+    pub task_group: String,
+    #[serde(rename = "ClientStatus")]
+    pub client_status: String,
+    #[serde(rename = "DesiredStatus")]
+    pub desired_status: String,
+    #[serde(rename = "Job")]
+    pub job: nomad::ReadJobResponse,
+}
+
+/// A Task of an Allocation together with the Image it runs
+#[derive(Debug, Clone, PartialEq)]
+struct AllocationTask {
+    job: String,
+    group: String,
+    task: String,
+    image: String,
+}
+
+/// Tracks which Image every running Allocation uses, to see the state of a Rollout where some
+/// Allocations still run the old Version
+#[derive(Debug)]
+pub struct AllocationTracker {
+    metrics: AllocationMetrics,
+    running: HashMap<String, Vec<AllocationTask>>,
+}
+
+/// Whether an Allocation with these Statuses has stopped or is being stopped
+fn is_stopped(client_status: &str, desired_status: &str) -> bool {
+    STOPPED_CLIENT_STATUSES.contains(&client_status)
+        || STOPPED_DESIRED_STATUSES.contains(&desired_status)
+}
+
+impl Allocation {
+    /// Whether the Allocation has stopped or is being stopped
+    pub fn is_stopped(&self) -> bool {
+        is_stopped(&self.client_status, &self.desired_status)
+    }
+
+    fn tasks(&self) -> Vec<AllocationTask> {
+        self.job
+            .task_groups
+            .iter()
+            .filter(|g| g.name == self.task_group)
+            .flat_map(|g| g.tasks.iter())
+            .filter_map(|task| {
+                Some(AllocationTask {
+                    job: self.job.name.clone(),
+                    group: self.task_group.clone(),
+                    task: task.name.clone(),
+                    image: task.config.image_reference()?,
+                })
+            })
+            .collect()
+    }
+}
+
+impl AllocationTracker {
+    pub fn new(metrics: AllocationMetrics) -> Self {
+        Self {
+            metrics,
+            running: HashMap::new(),
+        }
+    }
+
+    /// Updates the tracked Allocations with the Payload of an Allocation Event
+    pub fn handle(&mut self, payload: serde_json::Value) {
+        let payload: AllocationPayload = match serde_json::from_value(payload) {
+            Ok(p) => p,
+            Err(e) => {
+                tracing::error!("Parsing Allocation Payload: {:?}", e);
+                return;
+            }
+        };
+
+        self.update(payload.allocation);
+    }
+
+    /// Forgets the tracked Allocations that no longer exist or have stopped, as their Events
+    /// might have been missed while the Event-Stream was disconnected
+    pub fn reconcile(&mut self, existing: nomad::AllocationListResponse) {
+        let running: HashSet<String> = existing
+            .into_iter()
+            .filter(|a| !is_stopped(&a.client_status, &a.desired_status))
+            .map(|a| a.id)
+            .collect();
+
+        let gone: Vec<String> = self
+            .running
+            .keys()
+            .filter(|id| !running.contains(*id))
+            .cloned()
+            .collect();
+        for id in gone {
+            self.evict(&id);
+        }
+    }
+
+    fn evict(&mut self, id: &str) {
+        if let Some(previous) = self.running.remove(id) {
+            for task in previous {
+                self.metrics
+                    .remove(id, &task.job, &task.group, &task.task, &task.image);
+            }
+        }
+    }
+
+    fn update(&mut self, allocation: Allocation) {
+        self.evict(&allocation.id);
+
+        if allocation.is_stopped() {
+            return;
+        }
+
+        let tasks = allocation.tasks();
+        for task in tasks.iter() {
+            self.metrics.set(
+                &allocation.id,
+                &task.job,
+                &task.group,
+                &task.task,
+                &task.image,
+            );
+        }
+        self.running.insert(allocation.id, tasks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event_payload(id: &str, image: &str, client_status: &str) -> serde_json::Value {
+        serde_json::json!({
+            "Allocation": {
+                "ID": id,
+                "TaskGroup": "frontend",
+                "ClientStatus": client_status,
+                "DesiredStatus": "run",
+                "Job": {
+                    "Name": "web",
+                    "ParentID": "",
+                    "TaskGroups": [{
+                        "Name": "frontend",
+                        "Tasks": [{
+                            "Name": "nginx",
+                            "Driver": "docker",
+                            "Config": { "image": image }
+                        }, {
+                            "Name": "logs",
+                            "Driver": "exec",
+                            "Config": { "command": "/bin/ship-logs" }
+                        }]
+                    }]
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn tracks_and_expires_allocations() {
+        let reg = prometheus::Registry::new();
+        let mut tracker = AllocationTracker::new(AllocationMetrics::new(&reg).unwrap());
+
+        tracker.handle(event_payload("a1", "nginx:1.24.0", "running"));
+        tracker.handle(event_payload("a2", "nginx:1.25.3", "pending"));
+
+        let metrics = reg.gather();
+        assert_eq!(2, metrics[0].get_metric().len());
+        assert_eq!(2, tracker.running.len());
+
+        tracker.handle(event_payload("a1", "nginx:1.24.0", "complete"));
+
+        let metrics = reg.gather();
+        let remaining = metrics[0].get_metric();
+        assert_eq!(1, remaining.len());
+        assert!(remaining[0]
+            .get_label()
+            .iter()
+            .any(|l| l.get_name() == "image" && l.get_value() == "nginx:1.25.3"));
+        assert_eq!(1, tracker.running.len());
+    }
+
+    #[test]
+    fn evicts_allocations_that_no_longer_exist() {
+        let reg = prometheus::Registry::new();
+        let mut tracker = AllocationTracker::new(AllocationMetrics::new(&reg).unwrap());
+
+        tracker.handle(event_payload("a1", "nginx:1.24.0", "running"));
+        tracker.handle(event_payload("a2", "nginx:1.25.3", "running"));
+        tracker.handle(event_payload("a3", "nginx:1.25.3", "running"));
+
+        // a1 was garbage collected and a2 stopped while no Events were received
+        let existing: nomad::AllocationListResponse = serde_json::from_value(serde_json::json!([
+            { "ID": "a2", "ClientStatus": "complete", "DesiredStatus": "stop" },
+            { "ID": "a3", "ClientStatus": "running", "DesiredStatus": "run" },
+        ]))
+        .unwrap();
+        tracker.reconcile(existing);
+
+        assert_eq!(vec!["a3"], tracker.running.keys().collect::<Vec<_>>());
+        assert_eq!(1, reg.gather()[0].get_metric().len());
+    }
+}
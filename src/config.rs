@@ -0,0 +1,306 @@
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use crate::{
+    docker::{Version, DOCKER_HUB_HOSTS},
+    metrics::UpdatedVersion,
+    select::TrackLine,
+    PushgatewayConfig, RegistryCredentials, VersionOrdering, WebhookConfig,
+};
+
+/// The Configuration for the Monitoring behaviour of a [`Client`](crate::Client)
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Tags that follow a branch instead of a Version, like `main`, and are therefore reported
+    /// as floating instead of being compared
+    pub floating_tags: Vec<String>,
+    /// Patterns of Tags, like `sha-*` or `nightly`, that are never considered as newer
+    /// Versions, Images deployed with such a Tag are skipped. `*` matches any number of
+    /// characters
+    pub ignored_tags: Vec<String>,
+    /// Registries, by their Host like `registry:5000`, that only serve plain HTTP and are
+    /// therefore not accessed using HTTPS
+    pub insecure_registries: Vec<String>,
+    /// The Credentials for private Registries, by their Host like `ghcr.io`. Registries
+    /// without Credentials are accessed anonymously
+    pub registry_credentials: HashMap<String, RegistryCredentials>,
+    /// Labels, like `env="prod"`, that are added to every exported Metric, so the Instances
+    /// monitoring different Environments can be told apart
+    pub const_labels: Vec<(String, String)>,
+    /// How many pages of the Tag-List of a Repository are followed at most
+    pub max_tag_pages: usize,
+    /// How often a Request a Registry rate-limits, using `429 Too Many Requests`, is retried
+    pub rate_limit_retries: u32,
+    /// How long the Tag-List of a Repository is reused without asking the Registry again
+    pub tag_cache_ttl: Duration,
+    /// Pushes the Metrics to a Pushgateway after every Check
+    pub pushgateway: Option<PushgatewayConfig>,
+    /// Posts a Notification to a Webhook whenever a Task becomes out of date
+    pub webhook: Option<WebhookConfig>,
+    /// How long to wait between Checks, a Check also starts early on Events from Nomad
+    pub check_interval: Duration,
+    /// How long to wait before the next Check while Nomad lists no Jobs, like when the Cluster
+    /// is still coming up
+    pub empty_retry_interval: Duration,
+    /// How long no further Event from Nomad has to arrive before starting the Check, so a burst
+    /// of Events, like from a Deployment, only results in a single Check
+    pub event_debounce: Duration,
+    /// Limits how many Tasks are processed in a single Check, the remaining Tasks are
+    /// processed in the following Checks. A limit of 0 would never check any Task
+    pub max_tasks_per_cycle: Option<usize>,
+    /// How many Jobs are read from Nomad concurrently
+    pub job_read_concurrency: usize,
+    /// How many Tasks are checked concurrently, split evenly between the Registries so that
+    /// slow Registries or huge Repositories don't hold up the others
+    pub check_concurrency: usize,
+    /// Resolves the Digest of every deployed Tag to detect Tags that were pushed again
+    pub resolve_digests: bool,
+    /// Reports the newest of the Jobs dispatched from a Parent, like parameterized batch Jobs,
+    /// under the Name of their Parent in place of it, instead of skipping them
+    pub aggregate_dispatched: bool,
+    /// Skips periodic and parameterized Jobs themselves, as they only dispatch other Jobs and
+    /// their Images may be templated
+    pub skip_dispatch_parents: bool,
+    /// Tracks the Image of every running Allocation based on the Event-Stream
+    pub track_allocations: bool,
+    /// How Tasks without an Image to compare, like those using the `raw_exec` or `exec` Driver,
+    /// are reported
+    pub raw_exec: RawExecBehaviour,
+    /// The States of Tasks that are counted in the `needs_attention_total` Metric
+    pub needs_attention: Vec<AttentionState>,
+    /// Named Versions, like `stable`, `beta` and `canary`, ordered from oldest to newest,
+    /// Images using one of them are compared against the newest one available
+    pub channel_order: Vec<String>,
+    /// How Versions with a missing minor/patch, like `1.2`, are compared
+    pub version_ordering: VersionOrdering,
+    /// Ignores the case of Tags when parsing their Version, so `V1.2.3` is the same as `v1.2.3`
+    pub ignore_tag_case: bool,
+    /// Ignores a `git describe` suffix of Tags, like `-g1a2b3c4` or `-3-g1a2b3c4`, so
+    /// `1.4.2-g1a2b3c4` is the same as `1.4.2`
+    pub ignore_describe_suffix: bool,
+    /// Compares the Version of the Nomad Agent against the newest Releases listed at this URL
+    pub nomad_releases_url: Option<reqwest::Url>,
+    /// The Nomad Namespace whose Jobs are monitored, `*` for all of them, or the `default`
+    /// Namespace if unset
+    pub namespace: Option<String>,
+    /// The Metrics are considered stale, and are no longer served, once the last completed
+    /// Check is older than this
+    pub freshness_budget: Option<Duration>,
+    /// The Owner, like the responsible Team, of Jobs by their Name or by their Namespace and
+    /// Name like `team-a/web`, used for Jobs that don't configure one in their Meta
+    pub owners: HashMap<String, String>,
+    /// In how many consecutive Checks a Task has to be observed as out of date, or no longer
+    /// out of date, before its Metrics flip, so a glitching Registry doesn't toggle Alerts
+    pub out_of_date_cycles: usize,
+    /// Feeds of the approved Version of Images, by their Reference without Tag like
+    /// `ghcr.io/user/app`, which these Images are compared against instead of the newest Tag in
+    /// their Registry
+    pub approved_feeds: HashMap<String, reqwest::Url>,
+    /// How much every Version a Task is behind contributes to its `update_urgency_score`
+    pub urgency_weights: UrgencyWeights,
+    /// How far the Images of Jobs, by their Name, may move away from their deployed Version,
+    /// like staying on their major Version
+    pub update_tracks: HashMap<String, UpdateTrack>,
+}
+
+/// How the HTTP-Client talks to Registries and Nomad, like how their Certificates are verified
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    /// A PEM-encoded Certificate, like the one of a private CA, that is trusted in addition to
+    /// the built-in Root Certificates
+    pub ca_cert: Option<PathBuf>,
+    /// Accepts any Certificate, including self-signed and expired ones, which should only ever
+    /// be used during development
+    pub accept_invalid_certs: bool,
+    /// How long a single Request may take before it is aborted, so a hung Connection doesn't
+    /// stall the Check
+    pub timeout: Duration,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            ca_cert: None,
+            accept_invalid_certs: false,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// How Tasks without an Image, like those using the `raw_exec` Driver, are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RawExecBehaviour {
+    /// The Tasks are not reported at all
+    #[default]
+    Skip,
+    /// The Tasks are reported as not trackable
+    NotTrackable,
+}
+
+/// Normalizes the Host of a Registry to the form used in Environment Variables, like `GHCR_IO`
+/// for `ghcr.io`, with all the Hosts of Docker Hub becoming `DOCKER_IO`
+pub fn registry_env_key(host: &str) -> String {
+    let host = host.to_ascii_lowercase();
+    let host = match DOCKER_HUB_HOSTS.contains(&host.as_str()) {
+        true => "docker.io",
+        false => host.as_str(),
+    };
+
+    host.chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect()
+}
+
+impl Config {
+    /// The Credentials configured for the Registry with the given Host, which is compared in
+    /// the form used by Environment Variables, so `ghcr.io` matches `GHCR_IO`. Hosts that only
+    /// differ in their other characters, like `my-reg.io` and `my.reg.io`, share the Credentials
+    pub fn credentials_for(&self, host: &str) -> Option<&RegistryCredentials> {
+        let key = registry_env_key(host);
+        self.registry_credentials
+            .iter()
+            .find(|(h, _)| registry_env_key(h) == key)
+            .map(|(_, c)| c)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            floating_tags: ["main", "master", "develop"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            ignored_tags: Vec::new(),
+            insecure_registries: Vec::new(),
+            registry_credentials: HashMap::new(),
+            const_labels: Vec::new(),
+            max_tag_pages: 10,
+            rate_limit_retries: 3,
+            tag_cache_ttl: Duration::from_secs(60 * 60),
+            pushgateway: None,
+            webhook: None,
+            check_interval: Duration::from_secs(15 * 60),
+            empty_retry_interval: Duration::from_secs(30),
+            event_debounce: Duration::from_secs(5),
+            max_tasks_per_cycle: None,
+            job_read_concurrency: 4,
+            check_concurrency: 8,
+            resolve_digests: false,
+            aggregate_dispatched: false,
+            skip_dispatch_parents: false,
+            track_allocations: false,
+            raw_exec: RawExecBehaviour::default(),
+            needs_attention: vec![
+                AttentionState::OutOfDate,
+                AttentionState::Latest,
+                AttentionState::Floating,
+                AttentionState::CurrentMissing,
+                AttentionState::Unreachable,
+            ],
+            channel_order: Vec::new(),
+            version_ordering: VersionOrdering::default(),
+            ignore_tag_case: false,
+            ignore_describe_suffix: false,
+            nomad_releases_url: None,
+            namespace: None,
+            freshness_budget: None,
+            owners: HashMap::new(),
+            out_of_date_cycles: 1,
+            approved_feeds: HashMap::new(),
+            urgency_weights: UrgencyWeights::default(),
+            update_tracks: HashMap::new(),
+        }
+    }
+}
+
+/// Which Versions a Job is compared against relative to its deployed Version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateTrack {
+    /// Only Versions with the same major Version, so `1.5.0` is not out of date because of `2.0.0`
+    Major,
+    /// Only Versions with the same major and minor Version, like `1.5.x` for `1.5.0`
+    Minor,
+}
+
+impl UpdateTrack {
+    /// The Version line this Track allows for the deployed Version, like `1.x` for `1.5.0` when
+    /// staying on the major Version, only semantic Versions have one
+    pub(crate) fn line_of(&self, deployed: &Version) -> Option<TrackLine> {
+        match deployed {
+            Version::Semantic { major, minor, .. } => Some(TrackLine {
+                major: *major,
+                minor: match self {
+                    Self::Major => None,
+                    Self::Minor => *minor,
+                },
+            }),
+            Version::Latest | Version::Named(_) | Version::CalVer { .. } => None,
+        }
+    }
+}
+
+/// How much being behind by a single major, minor or patch Version contributes to the Urgency
+/// of updating a Task, only the most significant difference counts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UrgencyWeights {
+    pub major: f64,
+    pub minor: f64,
+    pub patch: f64,
+}
+
+impl Default for UrgencyWeights {
+    fn default() -> Self {
+        Self {
+            major: 100.0,
+            minor: 10.0,
+            patch: 1.0,
+        }
+    }
+}
+
+/// The States of a Task that can be considered as needing attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttentionState {
+    OutOfDate,
+    /// The Task uses the `latest` Tag
+    Latest,
+    Floating,
+    DigestPinned,
+    NotTrackable,
+    /// The Task could not be checked, for example because its Registry was unreachable
+    Unreachable,
+    /// The deployed Tag is no longer listed by the Registry, for example because it was deleted
+    CurrentMissing,
+}
+
+impl AttentionState {
+    /// Parses the Name of a State, like `out_of_date`
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "out_of_date" => Some(Self::OutOfDate),
+            "latest" => Some(Self::Latest),
+            "floating" => Some(Self::Floating),
+            "digest_pinned" => Some(Self::DigestPinned),
+            "not_trackable" => Some(Self::NotTrackable),
+            "unreachable" => Some(Self::Unreachable),
+            "current_missing" => Some(Self::CurrentMissing),
+            _ => None,
+        }
+    }
+
+    /// Whether the Result of a Task is in this State
+    pub(crate) fn matches(&self, version: &UpdatedVersion) -> bool {
+        match (self, version) {
+            (Self::OutOfDate, UpdatedVersion::OutOfDate { .. }) => true,
+            (Self::Latest, UpdatedVersion::UpToDate { version }) => version == "latest",
+            (Self::Floating, UpdatedVersion::Floating { .. }) => true,
+            (Self::DigestPinned, UpdatedVersion::DigestPinned { .. }) => true,
+            (Self::NotTrackable, UpdatedVersion::NotTrackable) => true,
+            _ => false,
+        }
+    }
+}
@@ -1,21 +1,85 @@
-use std::{borrow::Cow, collections::BTreeMap, fmt::Display};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
 struct TagListResponse {
-    name: String,
+    /// Only missing in the Responses of some Proxies
+    #[serde(default)]
+    name: Option<String>,
+    /// Required, so other Bodies like `{"errors": [...]}` are not mistaken for an empty List,
+    /// but `null` for Repositories without Tags
+    #[serde(deserialize_with = "nullable_tags")]
     tags: Vec<String>,
 }
 
+/// Deserializes the Tags, treating `null` as no Tags
+fn nullable_tags<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<Vec<String>>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// The Shapes in which Registries return the Tags of a Repository
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TagListShape {
+    /// Like `{"data": {"tags": [...]}}`, returned by some Proxies like JFrog Artifactory
+    Wrapped {
+        data: TagListResponse,
+    },
+    Standard(TagListResponse),
+}
+
+impl TagListShape {
+    fn into_inner(self) -> TagListResponse {
+        match self {
+            Self::Wrapped { data } => data,
+            Self::Standard(content) => content,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CatalogResponse {
+    #[serde(default)]
+    repositories: Vec<String>,
+}
+
 #[derive(Debug)]
 pub enum AuthError {
     SendRequest(reqwest::Error),
     StatusCode(reqwest::StatusCode),
     LoadingBytes(reqwest::Error),
-    JwtToken(jwt::Error),
+    /// The Realm of the Challenge is not a valid URL
+    Realm(url::ParseError),
+    Deserialize(serde_json::Error),
+    /// The Response contains no Token
+    MissingToken,
+}
+
+impl Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SendRequest(e) => write!(f, "sending auth request: {e}"),
+            Self::StatusCode(s) => write!(f, "auth request returned {s}"),
+            Self::LoadingBytes(e) => write!(f, "loading auth response: {e}"),
+            Self::Realm(e) => write!(f, "parsing auth realm: {e}"),
+            Self::Deserialize(e) => write!(f, "parsing auth response: {e}"),
+            Self::MissingToken => write!(f, "auth response contains no token"),
+        }
+    }
 }
 
+impl std::error::Error for AuthError {}
+
 #[derive(Debug)]
 struct AuthConfig {
     realm: String,
@@ -23,8 +87,43 @@ struct AuthConfig {
     scope: String,
 }
 
-async fn auth(client: &reqwest::Client, conf: &AuthConfig) -> Result<String, AuthError> {
-    let mut base_url = reqwest::Url::parse(&conf.realm).unwrap();
+/// The Credentials used to access a private Registry
+#[derive(Clone, PartialEq)]
+pub enum RegistryCredentials {
+    /// Used as HTTP basic auth when requesting a Token from the Token-Server of the Registry
+    Basic { username: String, password: String },
+    /// Sent as the Bearer Token to the Registry directly
+    Token(String),
+}
+
+impl std::fmt::Debug for RegistryCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &"<redacted>")
+                .finish(),
+            Self::Token(_) => f.debug_tuple("Token").field(&"<redacted>").finish(),
+        }
+    }
+}
+
+/// The Response of a Token-Server, which uses either `token` or `access_token`
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+/// Requests a Token from the Token-Server of the Registry, using the basic auth Credentials if
+/// there are any and anonymously otherwise
+async fn auth(
+    client: &reqwest::Client,
+    conf: &AuthConfig,
+    credentials: Option<&RegistryCredentials>,
+) -> Result<String, AuthError> {
+    let mut base_url = reqwest::Url::parse(&conf.realm).map_err(AuthError::Realm)?;
     base_url
         .query_pairs_mut()
         .append_pair("service", &conf.service)
@@ -32,31 +131,25 @@ async fn auth(client: &reqwest::Client, conf: &AuthConfig) -> Result<String, Aut
         .append_pair("client_id", "Nomad-VMonitor")
         .finish();
 
-    let resp = client
-        .get(base_url)
-        .send()
-        .await
-        .map_err(AuthError::SendRequest)?;
+    let mut req = client.get(base_url);
+    if let Some(RegistryCredentials::Basic { username, password }) = credentials {
+        req = req.basic_auth(username, Some(password));
+    }
+
+    let resp = req.send().await.map_err(AuthError::SendRequest)?;
     if !resp.status().is_success() {
         return Err(AuthError::StatusCode(resp.status()));
     }
 
     let raw_content = resp.bytes().await.map_err(AuthError::LoadingBytes)?;
 
-    let content: serde_json::Value = serde_json::from_slice(&raw_content).unwrap();
-
-    let token = content
-        .as_object()
-        .unwrap()
-        .get("token")
-        .unwrap()
-        .as_str()
-        .unwrap();
-
-    let _: jwt::Token<jwt::Header, serde_json::Value, jwt::Unverified> =
-        jwt::Token::parse_unverified(token).map_err(AuthError::JwtToken)?;
-
-    Ok(token.to_string())
+    let content: TokenResponse =
+        serde_json::from_slice(&raw_content).map_err(AuthError::Deserialize)?;
+    // The Token is opaque, ghcr.io for example doesn't issue JWTs for anonymous Pulls
+    content
+        .token
+        .or(content.access_token)
+        .ok_or(AuthError::MissingToken)
 }
 
 #[derive(Debug)]
@@ -66,29 +159,136 @@ pub enum GetTagsError {
     SendRequest(reqwest::Error),
     StatusCode(reqwest::StatusCode),
     LoadingBytes(reqwest::Error),
+    Url(url::ParseError),
+    Deserialize(serde_json::Error),
+    /// The `WWW-Authenticate` Challenge of the Registry is not a Bearer Challenge
+    MalformedAuthChallenge(String),
+    /// The `WWW-Authenticate` Challenge of the Registry lacks a required Field
+    MissingChallengeField(&'static str),
+    /// The Registry returned the Tags of a different Repository than the requested one, like
+    /// a misconfigured Proxy would
+    RepositoryMismatch {
+        expected: String,
+        returned: String,
+    },
+}
+
+impl Display for GetTagsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AuthError(e) => write!(f, "authenticating: {e}"),
+            Self::FailedAuth => write!(f, "registry rejected authentication"),
+            Self::SendRequest(e) => write!(f, "sending tags request: {e}"),
+            Self::StatusCode(s) => write!(f, "tags request returned {s}"),
+            Self::LoadingBytes(e) => write!(f, "loading tags response: {e}"),
+            Self::Url(e) => write!(f, "building registry url: {e}"),
+            Self::Deserialize(e) => write!(f, "parsing registry response: {e}"),
+            Self::MalformedAuthChallenge(c) => write!(f, "malformed auth challenge {c:?}"),
+            Self::MissingChallengeField(field) => {
+                write!(f, "auth challenge is missing the {field:?} field")
+            }
+            Self::RepositoryMismatch { expected, returned } => write!(
+                f,
+                "registry returned tags of {returned:?} instead of {expected:?}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GetTagsError {}
+
+/// A successful Response from a Registry
+struct Fetched {
+    status: reqwest::StatusCode,
+    headers: reqwest::header::HeaderMap,
+    content: bytes::Bytes,
 }
 
 enum FetchResult {
-    Ok(TagListResponse),
+    Ok(Fetched),
     NeedsAuth(AuthConfig),
+    /// The Registry responded with `429 Too Many Requests`, with how long it asked to wait
+    RateLimited(Option<Duration>),
     Err(GetTagsError),
 }
 
-async fn try_get_tags(
-    client: &reqwest::Client,
-    image: &Image,
-    token: Option<String>,
-) -> FetchResult {
-    let registry_url = reqwest::Url::parse("https://registry.hub.docker.com").unwrap();
+/// The longest a single wait for a rate-limiting Registry may take, so a Registry asking to
+/// wait for hours doesn't stall the Check
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
 
-    let target_url = registry_url
-        .join(&match &image.namespace {
-            Some(n) => format!("v2/{}/{}/tags/list", n, image.name),
-            None => format!("v2/library/{}/tags/list", image.name),
-        })
-        .unwrap();
+/// How long to wait before retrying a rate-limited Request, based on the `Retry-After` header
+/// in seconds if present or otherwise doubling with every attempt
+fn rate_limit_wait(retry_after: Option<Duration>, attempt: u32) -> Duration {
+    retry_after
+        .unwrap_or_else(|| Duration::from_secs(1 << attempt.min(6)))
+        .min(MAX_RATE_LIMIT_WAIT)
+}
+
+/// A Request against a Registry, that can be repeated once authenticated
+#[derive(Debug, Clone)]
+struct RegistryRequest {
+    method: reqwest::Method,
+    url: reqwest::Url,
+    headers: reqwest::header::HeaderMap,
+}
+
+impl RegistryRequest {
+    fn get(url: reqwest::Url) -> Self {
+        Self {
+            method: reqwest::Method::GET,
+            url,
+            headers: reqwest::header::HeaderMap::new(),
+        }
+    }
+}
+
+/// Parses a Bearer Challenge like `Bearer realm="...",service="...",scope="..."`, whose quoted
+/// Values may contain commas
+fn parse_challenge(challenge: &str) -> Result<AuthConfig, GetTagsError> {
+    let malformed = || GetTagsError::MalformedAuthChallenge(challenge.to_string());
+
+    let (scheme, raw_params) = challenge.trim().split_once(' ').ok_or_else(malformed)?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return Err(malformed());
+    }
+
+    let mut params = BTreeMap::new();
+    let mut rest = raw_params.trim();
+    while !rest.is_empty() {
+        let (key, after_key) = rest.split_once('=').ok_or_else(malformed)?;
+        let (value, after_value) = match after_key.strip_prefix('"') {
+            Some(quoted) => {
+                let (value, after) = quoted.split_once('"').ok_or_else(malformed)?;
+                (value, after)
+            }
+            None => after_key.split_once(',').unwrap_or((after_key, "")),
+        };
 
-    let mut req = client.get(target_url);
+        params.insert(key.trim().to_ascii_lowercase(), value.to_string());
+        rest = after_value.trim_start_matches([',', ' ']);
+    }
+
+    let mut field = |name: &'static str| {
+        params
+            .remove(name)
+            .ok_or(GetTagsError::MissingChallengeField(name))
+    };
+
+    Ok(AuthConfig {
+        realm: field("realm")?,
+        service: field("service")?,
+        scope: field("scope")?,
+    })
+}
+
+async fn try_fetch(
+    client: &reqwest::Client,
+    request: &RegistryRequest,
+    token: Option<&str>,
+) -> FetchResult {
+    let mut req = client
+        .request(request.method.clone(), request.url.clone())
+        .headers(request.headers.clone());
     if let Some(token) = token {
         req = req.bearer_auth(token);
     }
@@ -106,72 +306,574 @@ async fn try_get_tags(
         Err(e) => return FetchResult::Err(e),
     };
 
-    if !statuscode.is_success() {
+    if !statuscode.is_success() && statuscode != reqwest::StatusCode::NOT_MODIFIED {
         if statuscode.as_u16() == 401 {
             let auth_header = match headers.get("www-authenticate") {
                 Some(h) => h,
                 None => return FetchResult::Err(GetTagsError::FailedAuth),
             };
 
-            let auth_header_content = auth_header.to_str().unwrap();
-
-            let (_, raw_parts) = auth_header_content.split_once(' ').unwrap();
+            let challenge = match auth_header.to_str() {
+                Ok(c) => c,
+                Err(_) => {
+                    let raw = String::from_utf8_lossy(auth_header.as_bytes()).into_owned();
+                    return FetchResult::Err(GetTagsError::MalformedAuthChallenge(raw));
+                }
+            };
 
-            let mut parts = raw_parts
-                .split(',')
-                .filter_map(|part| part.split_once('='))
-                .map(|(key, val)| (key, val.replace('"', "")))
-                .collect::<BTreeMap<_, _>>();
+            return match parse_challenge(challenge) {
+                Ok(conf) => FetchResult::NeedsAuth(conf),
+                Err(e) => FetchResult::Err(e),
+            };
+        }
 
-            return FetchResult::NeedsAuth(AuthConfig {
-                realm: parts.remove("realm").unwrap(),
-                service: parts.remove("service").unwrap(),
-                scope: parts.remove("scope").unwrap(),
-            });
+        if statuscode == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = headers
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|h| h.trim().parse().ok())
+                .map(Duration::from_secs);
+            return FetchResult::RateLimited(retry_after);
         }
 
         return FetchResult::Err(GetTagsError::StatusCode(statuscode));
     }
 
-    FetchResult::Ok(serde_json::from_slice(&raw_content).unwrap())
+    FetchResult::Ok(Fetched {
+        status: statuscode,
+        headers,
+        content: raw_content,
+    })
 }
 
-pub async fn get_tags(
+/// Sends the Request, retrying it up to `retries` times while the Registry is rate-limiting
+async fn try_fetch_retrying(
     client: &reqwest::Client,
-    image: &Image,
-) -> Result<Vec<String>, GetTagsError> {
-    let auth_conf = match try_get_tags(client, image, None).await {
-        FetchResult::Ok(r) => return Ok(r.tags),
+    request: &RegistryRequest,
+    token: Option<&str>,
+    retries: u32,
+) -> FetchResult {
+    let mut attempt = 0;
+    loop {
+        let retry_after = match try_fetch(client, request, token).await {
+            FetchResult::RateLimited(retry_after) => retry_after,
+            other => return other,
+        };
+
+        if attempt >= retries {
+            return FetchResult::Err(GetTagsError::StatusCode(
+                reqwest::StatusCode::TOO_MANY_REQUESTS,
+            ));
+        }
+
+        let wait = rate_limit_wait(retry_after, attempt);
+        attempt += 1;
+        tracing::warn!(
+            "Rate-limited by Registry for {}, retrying in {:?} ({}/{})",
+            request.url,
+            wait,
+            attempt,
+            retries
+        );
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Sends the Request to a Registry, authenticating if the Registry requests it.
+///
+/// A token obtained on the way is stored in `token` so that following requests against the
+/// same Registry can reuse it. A `304 Not Modified` is returned as is, for conditional requests.
+/// Requests the Registry rate-limits are retried up to `retries` times. A configured Token is
+/// used until the Registry asks for a different one.
+#[tracing::instrument(skip_all, fields(url = %request.url))]
+async fn fetch(
+    client: &reqwest::Client,
+    request: &RegistryRequest,
+    token: &mut Option<String>,
+    retries: u32,
+    credentials: Option<&RegistryCredentials>,
+) -> Result<Fetched, GetTagsError> {
+    if let (None, Some(RegistryCredentials::Token(configured))) = (token.as_ref(), credentials) {
+        *token = Some(configured.clone());
+    }
+
+    let auth_conf = match try_fetch_retrying(client, request, token.as_deref(), retries).await {
+        FetchResult::Ok(fetched) => return Ok(fetched),
         FetchResult::NeedsAuth(conf) => conf,
+        FetchResult::RateLimited(_) => unreachable!("Rate-Limits are retried"),
         FetchResult::Err(e) => return Err(e),
     };
 
-    let token = auth(client, &auth_conf)
+    let new_token = auth(client, &auth_conf, credentials)
         .await
         .map_err(GetTagsError::AuthError)?;
 
-    match try_get_tags(client, image, Some(token)).await {
-        FetchResult::Ok(r) => Ok(r.tags),
+    let result = match try_fetch_retrying(client, request, Some(&new_token), retries).await {
+        FetchResult::Ok(fetched) => Ok(fetched),
         FetchResult::NeedsAuth(_) => Err(GetTagsError::FailedAuth),
+        FetchResult::RateLimited(_) => unreachable!("Rate-Limits are retried"),
         FetchResult::Err(e) => Err(e),
+    };
+
+    *token = Some(new_token);
+    result
+}
+
+/// Extracts the URL of the next page from a `Link` header
+fn next_link(headers: &reqwest::header::HeaderMap, base: &reqwest::Url) -> Option<reqwest::Url> {
+    let raw = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+
+    raw.split(',').find_map(|link| {
+        let (target, params) = link.split_once(';')?;
+        if !params.contains("rel=\"next\"") {
+            return None;
+        }
+
+        let target = target.trim().strip_prefix('<')?.strip_suffix('>')?;
+        base.join(target).ok()
+    })
+}
+
+/// Whether the page is served by the Registry itself, as a `Link` may point to another Host
+/// which must neither see the configured Credentials nor the Token obtained for the Registry
+fn same_registry(page_url: &reqwest::Url, registry_url: &reqwest::Url) -> bool {
+    page_url.origin() == registry_url.origin()
+}
+
+/// The Tag-Lists Registries returned together with their `ETag`, so that following requests
+/// can be conditional and reuse the List if it did not change. Lists younger than the TTL are
+/// reused without any request
+#[derive(Debug, Default)]
+pub struct TagCache {
+    entries: Mutex<HashMap<(String, String), CachedTags>>,
+    ttl: Duration,
+}
+
+impl TagCache {
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Forgets all cached Tag-Lists, so the next requests load them fresh, and returns how many
+    /// there were
+    pub fn clear(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let cleared = entries.len();
+        entries.clear();
+
+        cleared
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedTags {
+    /// The `ETag` of the List, only known for Lists that were not paginated
+    etag: Option<reqwest::header::HeaderValue>,
+    tags: Vec<String>,
+    /// When the List was last loaded or confirmed by the Registry
+    fetched_at: Instant,
+}
+
+/// The URL listing the Tags of the Repository in the given Registry
+fn tags_url(
+    registry_url: &reqwest::Url,
+    repository: &str,
+) -> Result<reqwest::Url, url::ParseError> {
+    registry_url.join(&format!("v2/{repository}/tags/list"))
+}
+
+/// Parses a single page of a Tag-List, which has to belong to the requested Repository
+fn parse_tags_page(content: &[u8], repository: &str) -> Result<Vec<String>, GetTagsError> {
+    let content = serde_json::from_slice::<TagListShape>(content)
+        .map_err(GetTagsError::Deserialize)?
+        .into_inner();
+    if let Some(name) = content.name.filter(|n| n != repository) {
+        return Err(GetTagsError::RepositoryMismatch {
+            expected: repository.to_string(),
+            returned: name,
+        });
+    }
+
+    Ok(content.tags)
+}
+
+/// Lists all the Tags of the Repository in the given Registry, following at most `max_pages`
+/// pages of the Tag-List and retrying every page up to `retries` times while rate-limited
+#[tracing::instrument(skip(client, cache))]
+pub async fn list_tags(
+    client: &reqwest::Client,
+    cache: &TagCache,
+    registry_url: &reqwest::Url,
+    repository: &str,
+    max_pages: usize,
+    retries: u32,
+    credentials: Option<&RegistryCredentials>,
+) -> Result<Vec<String>, GetTagsError> {
+    let target_url = tags_url(registry_url, repository).map_err(GetTagsError::Url)?;
+
+    let key = (registry_url.to_string(), repository.to_string());
+    let cached = cache.entries.lock().unwrap().get(&key).cloned();
+    if let Some(cached) = cached
+        .as_ref()
+        .filter(|c| c.fetched_at.elapsed() < cache.ttl)
+    {
+        tracing::debug!("Using cached Tags of {:?}", repository);
+        return Ok(cached.tags.clone());
+    }
+
+    let mut request = RegistryRequest::get(target_url.clone());
+    if let Some(etag) = cached.as_ref().and_then(|c| c.etag.clone()) {
+        request.headers.insert(reqwest::header::IF_NONE_MATCH, etag);
+    }
+
+    let mut token = None;
+    let fetched = fetch(client, &request, &mut token, retries, credentials).await?;
+    if fetched.status == reqwest::StatusCode::NOT_MODIFIED {
+        let mut cached = match cached {
+            Some(c) => c,
+            None => return Err(GetTagsError::StatusCode(fetched.status)),
+        };
+
+        cached.fetched_at = Instant::now();
+        let tags = cached.tags.clone();
+        cache.entries.lock().unwrap().insert(key, cached);
+        return Ok(tags);
+    }
+
+    let mut tags = parse_tags_page(&fetched.content, repository)?;
+
+    let mut next_url = next_link(&fetched.headers, &target_url);
+    let paginated = next_url.is_some();
+    for _ in 1..max_pages.max(1) {
+        let page_url = match next_url.take() {
+            Some(u) => u,
+            None => break,
+        };
+
+        let page = if same_registry(&page_url, registry_url) {
+            fetch(
+                client,
+                &RegistryRequest::get(page_url.clone()),
+                &mut token,
+                retries,
+                credentials,
+            )
+            .await?
+        } else {
+            let request = RegistryRequest::get(page_url.clone());
+            fetch(client, &request, &mut None, retries, None).await?
+        };
+        tags.extend(parse_tags_page(&page.content, repository)?);
+
+        next_url = next_link(&page.headers, &page_url);
+    }
+    if next_url.is_some() {
+        tracing::warn!(
+            "Stopped listing Tags of {:?} after {} pages",
+            repository,
+            max_pages
+        );
+    }
+
+    // The ETag only covers the first page, so paginated Lists can only be reused within the TTL
+    let etag = fetched
+        .headers
+        .get(reqwest::header::ETAG)
+        .filter(|_| !paginated)
+        .cloned();
+    let mut entries = cache.entries.lock().unwrap();
+    if etag.is_some() || !cache.ttl.is_zero() {
+        entries.insert(
+            key,
+            CachedTags {
+                etag,
+                tags: tags.clone(),
+                fetched_at: Instant::now(),
+            },
+        );
+    } else {
+        entries.remove(&key);
+    }
+
+    Ok(tags)
+}
+
+pub async fn get_tags(
+    client: &reqwest::Client,
+    cache: &TagCache,
+    image: &Image,
+    insecure_registries: &[String],
+    max_pages: usize,
+    retries: u32,
+    credentials: Option<&RegistryCredentials>,
+) -> Result<Vec<String>, GetTagsError> {
+    list_tags(
+        client,
+        cache,
+        &image
+            .registry_url(insecure_registries)
+            .map_err(GetTagsError::Url)?,
+        &image.repository(),
+        max_pages,
+        retries,
+        credentials,
+    )
+    .await
+}
+
+/// The Manifest types accepted when resolving a Digest, the lists/indices come first so that
+/// multi-platform Images resolve to the same Digest the container runtime pulls
+const MANIFEST_ACCEPT: &str = "application/vnd.docker.distribution.manifest.list.v2+json, \
+    application/vnd.oci.image.index.v1+json, \
+    application/vnd.docker.distribution.manifest.v2+json, \
+    application/vnd.oci.image.manifest.v1+json";
+
+/// Resolves the Digest a reference (Tag or Digest) in the Repository currently points to
+pub async fn get_digest(
+    client: &reqwest::Client,
+    registry_url: &reqwest::Url,
+    repository: &str,
+    reference: &str,
+    retries: u32,
+    credentials: Option<&RegistryCredentials>,
+) -> Result<Option<String>, GetTagsError> {
+    let target_url = registry_url
+        .join(&format!("v2/{repository}/manifests/{reference}"))
+        .map_err(GetTagsError::Url)?;
+
+    let mut request = RegistryRequest::get(target_url);
+    request.method = reqwest::Method::HEAD;
+    request.headers.insert(
+        reqwest::header::ACCEPT,
+        reqwest::header::HeaderValue::from_static(MANIFEST_ACCEPT),
+    );
+
+    let fetched = fetch(client, &request, &mut None, retries, credentials).await?;
+
+    Ok(fetched
+        .headers
+        .get("docker-content-digest")
+        .and_then(|d| d.to_str().ok())
+        .map(String::from))
+}
+
+/// The maximum number of Catalog pages that are followed
+const MAX_CATALOG_PAGES: usize = 100;
+
+/// Lists all the Repositories in the given Registry using its `_catalog` endpoint
+pub async fn list_repositories(
+    client: &reqwest::Client,
+    registry_url: &reqwest::Url,
+    retries: u32,
+    credentials: Option<&RegistryCredentials>,
+) -> Result<Vec<String>, GetTagsError> {
+    let mut target_url = Some(
+        registry_url
+            .join("v2/_catalog")
+            .map_err(GetTagsError::Url)?,
+    );
+    let mut token = None;
+    let mut repositories = Vec::new();
+
+    for _ in 0..MAX_CATALOG_PAGES {
+        let page_url = match target_url.take() {
+            Some(u) => u,
+            None => return Ok(repositories),
+        };
+
+        let fetched = if same_registry(&page_url, registry_url) {
+            fetch(
+                client,
+                &RegistryRequest::get(page_url.clone()),
+                &mut token,
+                retries,
+                credentials,
+            )
+            .await?
+        } else {
+            let request = RegistryRequest::get(page_url.clone());
+            fetch(client, &request, &mut None, retries, None).await?
+        };
+
+        let content: CatalogResponse =
+            serde_json::from_slice(&fetched.content).map_err(GetTagsError::Deserialize)?;
+        repositories.extend(content.repositories);
+
+        target_url = next_link(&fetched.headers, &page_url);
+    }
+
+    if target_url.is_some() {
+        tracing::warn!("Stopped listing Catalog after {} pages", MAX_CATALOG_PAGES);
+    }
+
+    Ok(repositories)
+}
+
+/// The Registry used for Images that don't specify one, which is also the Host of the Docker
+/// Hub API
+const DEFAULT_REGISTRY: &str = "registry.hub.docker.com";
+
+/// The Hosts of Docker Hub, which are all queried using the [`DEFAULT_REGISTRY`]
+pub(crate) const DOCKER_HUB_HOSTS: [&str; 4] = [
+    "docker.io",
+    "index.docker.io",
+    "registry-1.docker.io",
+    DEFAULT_REGISTRY,
+];
+
+/// Whether the first Component of a Reference is the Host of a Registry instead of a Namespace,
+/// which is the case for Domains like `ghcr.io`, `localhost` and Hosts with a Port like
+/// `registry:5000`, the Port is kept as part of the Registry
+fn is_registry_host(component: &str) -> bool {
+    let (host, port) = match component.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (component, None),
+    };
+
+    match port {
+        Some(port) => !host.is_empty() && port.parse::<u16>().is_ok(),
+        None => host.contains('.') || host == "localhost",
     }
 }
 
+/// The Transport prefixes of References used by Tools like skopeo, which are ignored
+const TRANSPORT_SCHEMES: [&str; 2] = ["docker://", "oci://"];
+
 #[derive(Debug, PartialEq)]
 pub struct Image {
     pub registry: Cow<'static, str>,
     pub namespace: Option<String>,
     pub name: String,
     pub tag: RawTag<'static>,
+    /// The Digest the Image is pinned to, like `sha256:...`
+    pub digest: Option<String>,
 }
 
 impl Image {
+    /// Whether the Image is hosted on Docker Hub, under any of its Hosts like `docker.io`
+    pub fn is_docker_hub(&self) -> bool {
+        DOCKER_HUB_HOSTS
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(&self.registry))
+    }
+
+    /// The Base-URL of the Registry hosting the Image, Registries in the explicit list of
+    /// insecure ones are accessed using plain HTTP and all others using HTTPS. All Hosts of
+    /// Docker Hub are queried using its API Host
+    pub fn registry_url(
+        &self,
+        insecure_registries: &[String],
+    ) -> Result<reqwest::Url, url::ParseError> {
+        if self.is_docker_hub() {
+            return reqwest::Url::parse(&format!("https://{DEFAULT_REGISTRY}"));
+        }
+
+        let scheme = if insecure_registries.iter().any(|r| *r == self.registry) {
+            "http"
+        } else {
+            "https"
+        };
+
+        let raw = if self.registry.contains("://") {
+            self.registry.to_string()
+        } else {
+            format!("{scheme}://{}", self.registry)
+        };
+
+        reqwest::Url::parse(&raw)
+    }
+
+    /// The Repository of the Image in its Registry, Docker Hub keeps its official Images, like
+    /// `nginx`, in the `library` namespace
+    pub fn repository(&self) -> String {
+        match &self.namespace {
+            Some(n) => format!("{}/{}", n, self.name),
+            None if self.is_docker_hub() => format!("library/{}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// The Reference of the Image without Tag or Digest, like `ghcr.io/user/app`, leaving out
+    /// the default Registry
+    pub fn name_reference(&self) -> String {
+        let mut result = String::new();
+
+        if self.registry != DEFAULT_REGISTRY {
+            result.push_str(&self.registry);
+            result.push('/');
+        }
+        if let Some(namespace) = &self.namespace {
+            result.push_str(namespace);
+            result.push('/');
+        }
+        result.push_str(&self.name);
+
+        result
+    }
+
+    /// Reconstructs the full Reference of the Image, leaving out the default Registry and the
+    /// implicit `latest` Tag of Images pinned by Digest
+    ///
+    /// ```
+    /// # use nomad_vmonitor::Image;
+    /// let image = Image::parse("ghcr.io/user/app:1.2.3".to_string()).unwrap();
+    /// assert_eq!("ghcr.io/user/app:1.2.3", image.reference());
+    ///
+    /// let image = Image::parse("nginx".to_string()).unwrap();
+    /// assert_eq!("nginx:latest", image.reference());
+    ///
+    /// let image = Image::parse("user/app@sha256:abcdef".to_string()).unwrap();
+    /// assert_eq!("user/app@sha256:abcdef", image.reference());
+    /// ```
+    pub fn reference(&self) -> String {
+        let mut result = self.name_reference();
+
+        match &self.digest {
+            Some(digest) if self.tag.as_str() == "latest" => {
+                result.push('@');
+                result.push_str(digest);
+            }
+            Some(digest) => {
+                result.push(':');
+                result.push_str(self.tag.as_str());
+                result.push('@');
+                result.push_str(digest);
+            }
+            None => {
+                result.push(':');
+                result.push_str(self.tag.as_str());
+            }
+        }
+
+        result
+    }
+
     pub fn parse(raw: String) -> Result<Self, String> {
         if raw.contains('$') {
             return Err(raw);
         }
 
-        let (raw_name, tag) = match raw.split_once(':') {
+        let reference = TRANSPORT_SCHEMES
+            .iter()
+            .find_map(|scheme| raw.strip_prefix(scheme))
+            .unwrap_or(raw.as_str());
+
+        let (raw_reference, digest) = match reference.split_once('@') {
+            Some((reference, digest)) => (reference, Some(digest.to_string())),
+            None => (reference, None),
+        };
+
+        // The Tag follows the last `:` after the last `/`, an earlier one belongs to the Port of
+        // the Registry like in `registry:5000/app`
+        let name_start = raw_reference.rfind('/').map(|i| i + 1).unwrap_or(0);
+        let tag_separator = raw_reference[name_start..].find(':').map(|i| {
+            (
+                &raw_reference[..name_start + i],
+                &raw_reference[name_start + i + 1..],
+            )
+        });
+        let (raw_name, tag) = match tag_separator {
             Some((first, second)) => (
                 first,
                 RawTag {
@@ -179,7 +881,7 @@ impl Image {
                 },
             ),
             None => (
-                raw.as_str(),
+                raw_reference,
                 RawTag {
                     tag: Cow::Borrowed("latest"),
                 },
@@ -188,13 +890,15 @@ impl Image {
 
         let mut parts: Vec<_> = raw_name.split('/').collect();
 
-        if parts.is_empty() {
+        // Empty Components, like in `ghcr.io//app` or `ghcr.io/app:`, would otherwise result in
+        // looking up the wrong Repository
+        if parts.iter().any(|p| p.is_empty()) || tag.as_str().is_empty() {
             return Err(raw);
         }
-        let registry = if parts.first().unwrap().contains('.') {
+        let registry = if parts.len() > 1 && is_registry_host(parts[0]) {
             Cow::Owned(parts.remove(0).to_string())
         } else {
-            Cow::Borrowed("registry.hub.docker.com")
+            Cow::Borrowed(DEFAULT_REGISTRY)
         };
 
         let (namespace, name) = if parts.len() == 1 {
@@ -210,6 +914,7 @@ impl Image {
             namespace,
             name: name.to_string(),
             tag,
+            digest,
         })
     }
 }
@@ -219,34 +924,118 @@ pub struct RawTag<'a> {
     tag: Cow<'a, str>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Versions are equal whenever they are ordered as equal, so Fields that are only kept for
+/// displaying them, like the Build Metadata, are ignored when comparing them as well
+#[derive(Debug, Clone)]
 pub enum Version {
     Latest,
+    /// A named Release or Channel, like `stable`, which is only ordered by an explicit
+    /// [`ChannelOrder`](crate::select::ChannelOrder)
+    Named(String),
     Semantic {
+        /// Whether the Tag starts with a `v`, like `v1.2.3`, which is kept for displaying the
+        /// Version but ignored when ordering it
+        v_prefix: bool,
         major: usize,
         minor: Option<usize>,
         patch: Option<usize>,
+        /// A fourth Component after the patch, like the Build Number `1` in `7.17.0.1`. Versions
+        /// without one are the base Release and sort below those with one, so
+        /// `7.17.0 < 7.17.0.1`
+        revision: Option<usize>,
+        /// The Pre-Release after the first `-`, like `rc1` in `1.4.2-rc1`, which orders the
+        /// Version below the corresponding final Release
+        pre_release: Option<String>,
+        /// The Variant of the Image, like `alpine` in `1.21-alpine`, which only Versions of the
+        /// same Variant are compared against
+        variant: Option<String>,
+        /// Everything after the first `+`, like `20240115` in `1.2.3+20240115`, which is kept
+        /// for displaying the Version but ignored when ordering it
+        build: Option<String>,
+        /// The Tag as it was written, if its Case was ignored when parsing it, which is
+        /// displayed instead while the Version is compared in lower case
+        written: Option<String>,
+    },
+    /// A Calendar based Version, like `2024.01.2` or `20240115`, which is ordered
+    /// chronologically and never compared against [`Semantic`](Self::Semantic) Versions
+    CalVer {
+        /// The numeric Components starting with the year, like `[2024, 1, 15]` for `20240115`
+        components: Vec<usize>,
+        /// The Components as they were written in the Tag, like `2024.01`, for displaying them
+        release: String,
+        pre_release: Option<String>,
+        variant: Option<String>,
+        /// The Tag as it was written, if its Case was ignored when parsing it
+        written: Option<String>,
     },
 }
 
 impl Display for Version {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::Semantic {
+                written: Some(written),
+                ..
+            }
+            | Self::CalVer {
+                written: Some(written),
+                ..
+            } => write!(f, "{written}"),
             Self::Latest => write!(f, "latest"),
+            Self::Named(name) => write!(f, "{name}"),
             Self::Semantic {
+                v_prefix,
                 major,
                 minor,
                 patch,
+                revision,
+                pre_release,
+                variant,
+                build,
+                ..
             } => {
+                if *v_prefix {
+                    write!(f, "v")?;
+                }
                 write!(f, "{major}")?;
 
-                match minor {
-                    Some(minor) => write!(f, ".{minor}")?,
-                    None => return Ok(()),
-                };
+                if let Some(minor) = minor {
+                    write!(f, ".{minor}")?;
+
+                    if let Some(patch) = patch {
+                        write!(f, ".{patch}")?;
+
+                        if let Some(revision) = revision {
+                            write!(f, ".{revision}")?;
+                        }
+                    }
+                }
+
+                if let Some(pre_release) = pre_release {
+                    write!(f, "-{pre_release}")?;
+                }
+                if let Some(variant) = variant {
+                    write!(f, "-{variant}")?;
+                }
+
+                match build {
+                    Some(build) => write!(f, "+{build}"),
+                    None => Ok(()),
+                }
+            }
+            Self::CalVer {
+                release,
+                pre_release,
+                variant,
+                ..
+            } => {
+                write!(f, "{release}")?;
 
-                match patch {
-                    Some(patch) => write!(f, ".{patch}"),
+                if let Some(pre_release) = pre_release {
+                    write!(f, "-{pre_release}")?;
+                }
+                match variant {
+                    Some(variant) => write!(f, "-{variant}"),
                     None => Ok(()),
                 }
             }
@@ -254,52 +1043,191 @@ impl Display for Version {
     }
 }
 
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+impl Eq for Version {}
 impl PartialOrd for Version {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         match (self, other) {
-            (Self::Latest, Self::Latest) => Some(std::cmp::Ordering::Equal),
-            (Self::Latest, _) => Some(std::cmp::Ordering::Less),
-            (_, Self::Latest) => Some(std::cmp::Ordering::Greater),
+            (Self::Latest, Self::Latest) => std::cmp::Ordering::Equal,
+            (Self::Latest, _) => std::cmp::Ordering::Less,
+            (_, Self::Latest) => std::cmp::Ordering::Greater,
+            // Only keeps the Order total, which of the names is newer is not known here
+            (Self::Named(sname), Self::Named(oname)) => sname.cmp(oname),
+            (Self::Named(_), _) => std::cmp::Ordering::Less,
+            (_, Self::Named(_)) => std::cmp::Ordering::Greater,
+            (
+                Self::CalVer {
+                    components: scomponents,
+                    pre_release: spre,
+                    variant: svariant,
+                    ..
+                },
+                Self::CalVer {
+                    components: ocomponents,
+                    pre_release: opre,
+                    variant: ovariant,
+                    ..
+                },
+            ) => scomponents
+                .cmp(ocomponents)
+                .then_with(|| compare_pre_release(spre.as_deref(), opre.as_deref()))
+                .then_with(|| svariant.cmp(ovariant)),
+            (Self::CalVer { .. }, _) => std::cmp::Ordering::Greater,
+            (_, Self::CalVer { .. }) => std::cmp::Ordering::Less,
             (
                 Self::Semantic {
                     major: smajor,
                     minor: sminor,
                     patch: spatch,
+                    revision: srevision,
+                    pre_release: spre,
+                    variant: svariant,
+                    ..
                 },
                 Self::Semantic {
                     major: omajor,
                     minor: ominor,
                     patch: opatch,
+                    revision: orevision,
+                    pre_release: opre,
+                    variant: ovariant,
+                    ..
                 },
             ) => {
-                match smajor.cmp(omajor) {
-                    std::cmp::Ordering::Equal => {}
-                    other => return Some(other),
-                };
-
-                match (sminor, ominor) {
-                    (None, None) => return Some(std::cmp::Ordering::Equal),
-                    (Some(_), None) => return Some(std::cmp::Ordering::Less),
-                    (None, Some(_)) => return Some(std::cmp::Ordering::Greater),
-                    (Some(sm), Some(om)) => match sm.cmp(om) {
+                let release = || {
+                    match smajor.cmp(omajor) {
                         std::cmp::Ordering::Equal => {}
-                        other => return Some(other),
-                    },
+                        other => return other,
+                    };
+
+                    match (sminor, ominor) {
+                        (None, None) => return std::cmp::Ordering::Equal,
+                        (Some(_), None) => return std::cmp::Ordering::Less,
+                        (None, Some(_)) => return std::cmp::Ordering::Greater,
+                        (Some(sm), Some(om)) => match sm.cmp(om) {
+                            std::cmp::Ordering::Equal => {}
+                            other => return other,
+                        },
+                    };
+
+                    match (spatch, opatch) {
+                        (None, None) => std::cmp::Ordering::Equal,
+                        (Some(_), None) => std::cmp::Ordering::Less,
+                        (None, Some(_)) => std::cmp::Ordering::Greater,
+                        (Some(sp), Some(op)) => sp.cmp(op).then_with(|| srevision.cmp(orevision)),
+                    }
                 };
 
-                match (spatch, opatch) {
-                    (None, None) => Some(std::cmp::Ordering::Equal),
-                    (Some(_), None) => Some(std::cmp::Ordering::Less),
-                    (None, Some(_)) => Some(std::cmp::Ordering::Greater),
-                    (Some(sp), Some(op)) => Some(sp.cmp(op)),
-                }
+                release()
+                    .then_with(|| compare_pre_release(spre.as_deref(), opre.as_deref()))
+                    .then_with(|| svariant.cmp(ovariant))
             }
         }
     }
 }
-impl Ord for Version {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).expect("Should always work")
+
+/// Splits the Release of a Calendar based Version into its Components, if it starts with a
+/// year like `2024.01` or a date like `20240115`
+fn calver_components(release: &str) -> Option<Vec<usize>> {
+    let is_digits = |raw: &str| !raw.is_empty() && raw.chars().all(|c| c.is_ascii_digit());
+    let is_year = |year: usize| (1900..2100).contains(&year);
+    let is_month = |month: usize| (1..=12).contains(&month);
+
+    let mut parts = release.split('.');
+    let first = parts.next()?;
+    if !is_digits(first) {
+        return None;
+    }
+
+    let mut components = match first.len() {
+        4 => vec![first.parse().ok()?],
+        8 => {
+            let day: usize = first[6..].parse().ok()?;
+            if !(1..=31).contains(&day) {
+                return None;
+            }
+
+            vec![first[..4].parse().ok()?, first[4..6].parse().ok()?, day]
+        }
+        _ => return None,
+    };
+    for part in parts {
+        if !is_digits(part) {
+            return None;
+        }
+        components.push(part.parse().ok()?);
+    }
+
+    let month_valid = components.get(1).map(|m| is_month(*m)).unwrap_or(true);
+    match is_year(components[0]) && month_valid {
+        true => Some(components),
+        false => None,
+    }
+}
+
+/// The Identifiers, ignoring trailing digits, that start a Pre-Release instead of a Variant
+const PRE_RELEASE_IDENTIFIERS: [&str; 7] =
+    ["alpha", "beta", "rc", "pre", "preview", "dev", "snapshot"];
+
+/// Splits the Suffix of a Version, everything after the first `-`, into its Pre-Release, like
+/// `rc1`, and its Variant, like `alpine`, so `rc1-alpine` has both
+fn split_pre_release(suffix: &str) -> (Option<&str>, Option<&str>) {
+    let (first, rest) = match suffix.split_once('-') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (suffix, None),
+    };
+
+    let identifier = first.split('.').next().unwrap_or(first);
+    let name = identifier.trim_end_matches(|c: char| c.is_ascii_digit());
+    let is_pre_release = name.is_empty()
+        || PRE_RELEASE_IDENTIFIERS
+            .iter()
+            .any(|p| p.eq_ignore_ascii_case(name));
+
+    match (is_pre_release, rest) {
+        (true, rest) => (Some(first), rest.filter(|r| !r.is_empty())),
+        (false, _) => (None, Some(suffix)),
+    }
+}
+
+/// Compares the Pre-Releases of otherwise equal Versions like SemVer does, a Pre-Release is
+/// lower than the final Release and numeric identifiers are compared numerically, so
+/// `rc.2 < rc.10`
+fn compare_pre_release(first: Option<&str>, second: Option<&str>) -> std::cmp::Ordering {
+    let (first, second) = match (first, second) {
+        (None, None) => return std::cmp::Ordering::Equal,
+        (Some(_), None) => return std::cmp::Ordering::Less,
+        (None, Some(_)) => return std::cmp::Ordering::Greater,
+        (Some(f), Some(s)) => (f, s),
+    };
+
+    let mut first_ids = first.split('.');
+    let mut second_ids = second.split('.');
+    loop {
+        let ordering = match (first_ids.next(), second_ids.next()) {
+            (None, None) => return std::cmp::Ordering::Equal,
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(f), Some(s)) => match (f.parse::<usize>(), s.parse::<usize>()) {
+                (Ok(f), Ok(s)) => f.cmp(&s),
+                (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+                (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+                (Err(_), Err(_)) => f.cmp(s),
+            },
+        };
+
+        if ordering != std::cmp::Ordering::Equal {
+            return ordering;
+        }
     }
 }
 
@@ -309,17 +1237,96 @@ impl<'a> RawTag<'a> {
             tag: Cow::Borrowed(t),
         }
     }
-    pub fn parse_version(&self) -> Result<Version, ()> {
+    pub fn as_str(&self) -> &str {
+        &self.tag
+    }
+
+    /// The underlying Tag, borrowed or owned as it was created
+    pub fn raw(&self) -> &Cow<'a, str> {
+        &self.tag
+    }
+
+    pub fn is_one_of(&self, tags: &[String]) -> bool {
+        tags.iter().any(|t| t == self.tag.as_ref())
+    }
+
+    /// Whether the Tag matches any of the Patterns, in which `*` matches any number of characters,
+    /// like `sha-*`
+    pub fn matches_any(&self, patterns: &[String]) -> bool {
+        patterns.iter().any(|p| glob_matches(p, &self.tag))
+    }
+
+    /// Parses the Version ignoring the case of prefixes and suffixes, so `V1.2.3` is the same as
+    /// `v1.2.3`, while the Tag is still displayed as it was written
+    pub(crate) fn parse_version_ignore_case(&self) -> Result<Version, ()> {
+        let lower = self.tag.to_ascii_lowercase();
+        let mut version = RawTag::new(&lower).parse_version()?;
+        if let Version::Semantic { written, .. } | Version::CalVer { written, .. } = &mut version {
+            if lower != self.tag {
+                *written = Some(self.tag.to_string());
+            }
+        }
+
+        Ok(version)
+    }
+
+    /// The Tag without a `git describe` suffix, like `-g1a2b3c4` or `-3-g1a2b3c4`, so
+    /// `1.4.2-3-g1a2b3c4` becomes `1.4.2`
+    pub(crate) fn without_describe_suffix(&self) -> RawTag<'_> {
+        let is_hash = |h: &str| !h.is_empty() && h.chars().all(|c| c.is_ascii_hexdigit());
+        let is_count = |c: &str| !c.is_empty() && c.chars().all(|c| c.is_ascii_digit());
+
+        let tag = self.tag.as_ref();
+        let base = match tag.rsplit_once("-g") {
+            Some((base, hash)) if is_hash(hash) => match base.rsplit_once('-') {
+                Some((base, count)) if is_count(count) => base,
+                _ => base,
+            },
+            _ => tag,
+        };
+
+        RawTag::new(base)
+    }
+
+    pub(crate) fn parse_version(&self) -> Result<Version, ()> {
         if self.tag.eq("latest") {
             return Ok(Version::Latest);
         }
 
-        let tag = self.tag.strip_prefix('v').unwrap_or(self.tag.as_ref());
+        let (tag, v_prefix) = match self.tag.strip_prefix('v') {
+            Some(tag) => (tag, true),
+            None => (self.tag.as_ref(), false),
+        };
+        let (tag, build) = match tag.split_once('+') {
+            Some((version, build)) => (version, Some(build.to_string())),
+            None => (tag, None),
+        };
+        let (tag, pre_release, variant) = match tag.split_once('-') {
+            Some((release, suffix)) => {
+                let (pre_release, variant) = split_pre_release(suffix);
+                (
+                    release,
+                    pre_release.map(String::from),
+                    variant.map(String::from),
+                )
+            }
+            None => (tag, None, None),
+        };
+
+        if let Some(components) = calver_components(tag) {
+            return Ok(Version::CalVer {
+                components,
+                release: tag.to_string(),
+                pre_release,
+                variant,
+                written: None,
+            });
+        }
 
         let mut parts = tag.split('.');
 
         let raw_major = parts.next().ok_or(())?;
-        let major: usize = raw_major.parse().map_err(|e| ())?;
+        let major: usize = raw_major.parse().map_err(|_| ())?;
 
         let raw_minor = parts.next();
         let minor: Option<usize> = raw_minor.and_then(|m| m.parse().ok());
@@ -327,27 +1334,181 @@ impl<'a> RawTag<'a> {
         let raw_patch = parts.next();
         let patch: Option<usize> = raw_patch.and_then(|m| m.parse().ok());
 
+        let raw_revision = parts.next();
+        let revision: Option<usize> = raw_revision.and_then(|m| m.parse().ok());
+
         Ok(Version::Semantic {
+            v_prefix,
             major,
             minor,
             patch,
+            revision,
+            pre_release,
+            variant,
+            build,
+            written: None,
         })
     }
 }
 
 impl Version {
+    /// The Variant of the Image, like `alpine` in `1.21-alpine`
+    pub fn variant(&self) -> Option<&str> {
+        match self {
+            Self::Latest | Self::Named(_) => None,
+            Self::Semantic { variant, .. } | Self::CalVer { variant, .. } => variant.as_deref(),
+        }
+    }
+
+    /// Whether the Version is Calendar based, like `2024.01`
+    pub fn is_calver(&self) -> bool {
+        matches!(self, Self::CalVer { .. })
+    }
+
+    /// Whether the Version is a Pre-Release, like `1.5.0-rc1`
+    pub fn is_pre_release(&self) -> bool {
+        match self {
+            Self::Latest | Self::Named(_) => false,
+            Self::Semantic { pre_release, .. } | Self::CalVer { pre_release, .. } => {
+                pre_release.is_some()
+            }
+        }
+    }
+
     pub fn fully_qualified(&self) -> bool {
         match self {
-            Self::Latest => true,
+            Self::Latest | Self::Named(_) | Self::CalVer { .. } => true,
             Self::Semantic { minor, patch, .. } => minor.is_some() && patch.is_some(),
         }
     }
+
+    /// Whether both Versions are semantic and share their major Version, like `1.2.0` and `1.5.3`
+    pub fn same_major(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Semantic { major, .. }, Self::Semantic { major: other, .. }) => major == other,
+            _ => false,
+        }
+    }
+
+    /// Whether both Versions are semantic and share their major and minor Version, like `1.2.0`
+    /// and `1.2.7`
+    pub fn same_minor(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::Semantic { major, minor, .. },
+                Self::Semantic {
+                    major: other_major,
+                    minor: other_minor,
+                    ..
+                },
+            ) => major == other_major && minor == other_minor,
+            _ => false,
+        }
+    }
+}
+
+/// How Versions are compared with each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VersionOrdering {
+    /// A missing minor/patch sorts above any present one, so `1.2.0 < 1.2`
+    #[default]
+    Strict,
+    /// A missing minor/patch is treated as `0`, so `1.2 == 1.2.0`
+    MissingAsZero,
+}
+
+impl VersionOrdering {
+    /// Compares which of the Versions is newer, named Versions compare as Equal with each other
+    /// as only a [`ChannelOrder`](crate::select::ChannelOrder) knows their Order
+    pub fn compare(&self, first: &Version, second: &Version) -> std::cmp::Ordering {
+        match (self, first, second) {
+            (_, Version::Named(_), Version::Named(_)) => std::cmp::Ordering::Equal,
+            (
+                Self::MissingAsZero,
+                Version::Semantic {
+                    major: smajor,
+                    minor: sminor,
+                    patch: spatch,
+                    revision: srevision,
+                    pre_release: spre,
+                    variant: svariant,
+                    ..
+                },
+                Version::Semantic {
+                    major: omajor,
+                    minor: ominor,
+                    patch: opatch,
+                    revision: orevision,
+                    pre_release: opre,
+                    variant: ovariant,
+                    ..
+                },
+            ) => (
+                smajor,
+                sminor.unwrap_or(0),
+                spatch.unwrap_or(0),
+                srevision.unwrap_or(0),
+            )
+                .cmp(&(
+                    omajor,
+                    ominor.unwrap_or(0),
+                    opatch.unwrap_or(0),
+                    orevision.unwrap_or(0),
+                ))
+                .then_with(|| compare_pre_release(spre.as_deref(), opre.as_deref()))
+                .then_with(|| svariant.cmp(ovariant)),
+            _ => first.cmp(second),
+        }
+    }
+}
+
+/// Matches the Text against the Pattern, in which `*` matches any number of characters
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match text.strip_prefix(first) {
+        Some(r) => r,
+        None => return false,
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let last = match parts.split_last() {
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(i) => rest = &rest[i + part.len()..],
+                    None => return false,
+                }
+            }
+            last
+        }
+        // Without a `*` the whole Text has to match the Pattern
+        None => return rest.is_empty(),
+    };
+
+    rest.len() >= last.len() && rest.ends_with(last)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn ignored_tag_patterns() {
+        let patterns = vec!["nightly".to_string(), "sha-*".to_string()];
+
+        assert!(RawTag::new("nightly").matches_any(&patterns));
+        assert!(RawTag::new("sha-abc123").matches_any(&patterns));
+        assert!(!RawTag::new("1.2.3").matches_any(&patterns));
+        assert!(!RawTag::new("nightly-2024").matches_any(&patterns));
+
+        assert!(glob_matches("*-rc*", "1.2.3-rc1"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("a*b*a", "abba"));
+        assert!(!glob_matches("a*a", "a"));
+        assert!(!glob_matches("*-rc", "1.2.3-rc1"));
+    }
+
     #[test]
     fn parse_image() {
         assert_eq!(
@@ -357,7 +1518,8 @@ mod tests {
                 name: "test".to_string(),
                 tag: RawTag {
                     tag: Cow::Borrowed("version")
-                }
+                },
+                digest: None,
             }),
             Image::parse("user/test:version".to_string()),
         );
@@ -369,7 +1531,8 @@ mod tests {
                 name: "test".to_string(),
                 tag: RawTag {
                     tag: Cow::Borrowed("version")
-                }
+                },
+                digest: None,
             }),
             Image::parse("test:version".to_string()),
         );
@@ -381,12 +1544,995 @@ mod tests {
                 name: "test".to_string(),
                 tag: RawTag {
                     tag: Cow::Borrowed("version")
-                }
+                },
+                digest: None,
             }),
             Image::parse("test.com/user/test:version".to_string()),
         );
     }
 
+    #[test]
+    fn parse_ambiguous_image() {
+        let image = Image::parse("a.b/c/d:tag".to_string()).unwrap();
+        assert_eq!("a.b", image.registry);
+        assert_eq!(Some("c".to_string()), image.namespace);
+        assert_eq!("d", image.name);
+        assert_eq!("tag", image.tag.as_str());
+
+        for raw in [
+            "a.b//d:tag",
+            "a.b/c/:tag",
+            "/c/d:tag",
+            "a.b/c/d:",
+            "a.b/c/d/e:tag",
+        ] {
+            assert_eq!(Err(raw.to_string()), Image::parse(raw.to_string()), "{raw}");
+        }
+    }
+
+    #[test]
+    fn same_version_line() {
+        let version = |raw: &str| RawTag::new(raw).parse_version().unwrap();
+
+        assert!(version("1.5.0").same_major(&version("1.0.3")));
+        assert!(!version("1.5.0").same_major(&version("2.0.0")));
+        assert!(version("1.5.0").same_minor(&version("1.5.7")));
+        assert!(!version("1.5.0").same_minor(&version("1.6.0")));
+        assert!(!version("latest").same_major(&version("latest")));
+    }
+
+    #[test]
+    fn parse_registry_port() {
+        let image = Image::parse("registry:5000/user/app:1.0.0".to_string()).unwrap();
+
+        assert_eq!("registry:5000", image.registry);
+        assert_eq!(Some("user".to_string()), image.namespace);
+        assert_eq!("app", image.name);
+        assert_eq!("1.0.0", image.tag.as_str());
+
+        for (raw, registry, tag, expected_url) in [
+            (
+                "localhost:5000/app:1.2.3",
+                "localhost:5000",
+                "1.2.3",
+                "https://localhost:5000/v2/app/tags/list",
+            ),
+            (
+                "registry.internal:5000/team/app:1.0",
+                "registry.internal:5000",
+                "1.0",
+                "https://registry.internal:5000/v2/team/app/tags/list",
+            ),
+            (
+                "localhost/app",
+                "localhost",
+                "latest",
+                "https://localhost/v2/app/tags/list",
+            ),
+        ] {
+            let image = Image::parse(raw.to_string()).unwrap();
+            assert_eq!(registry, image.registry, "{raw}");
+            assert_eq!("app", image.name, "{raw}");
+            assert_eq!(tag, image.tag.as_str(), "{raw}");
+
+            let url = tags_url(&image.registry_url(&[]).unwrap(), &image.repository()).unwrap();
+            assert_eq!(expected_url, url.as_str(), "{raw}");
+        }
+    }
+
+    #[test]
+    fn registry_hosts() {
+        for host in [
+            "ghcr.io",
+            "localhost",
+            "localhost:5000",
+            "registry:5000",
+            "registry.internal:5000",
+        ] {
+            assert!(is_registry_host(host), "{host}");
+        }
+        for namespace in [
+            "user",
+            "registry:",
+            ":5000",
+            "registry:port",
+            "registry:70000",
+        ] {
+            assert!(!is_registry_host(namespace), "{namespace}");
+        }
+    }
+
+    #[test]
+    fn insecure_registry_url() {
+        let insecure = vec!["registry:5000".to_string()];
+
+        let image = Image::parse("registry:5000/user/app:1.0.0".to_string()).unwrap();
+        let url = tags_url(&image.registry_url(&insecure).unwrap(), &image.repository()).unwrap();
+        assert_eq!("http://registry:5000/v2/user/app/tags/list", url.as_str());
+
+        let image = Image::parse("other:5000/user/app:1.0.0".to_string()).unwrap();
+        assert_eq!("https", image.registry_url(&insecure).unwrap().scheme());
+        let image = Image::parse("registry:5000/user/app:1.0.0".to_string()).unwrap();
+        assert_eq!("https", image.registry_url(&[]).unwrap().scheme());
+        let image = Image::parse("bad^host:5000/user/app:1.0.0".to_string()).unwrap();
+        assert!(image.registry_url(&[]).is_err());
+    }
+
+    #[test]
+    fn registry_url_from_image() {
+        for (raw, expected) in [
+            (
+                "ghcr.io/org/app:1.0.0",
+                "https://ghcr.io/v2/org/app/tags/list",
+            ),
+            (
+                "quay.io/org/app:1.0.0",
+                "https://quay.io/v2/org/app/tags/list",
+            ),
+            (
+                "user/app:1.0.0",
+                "https://registry.hub.docker.com/v2/user/app/tags/list",
+            ),
+            (
+                "nginx",
+                "https://registry.hub.docker.com/v2/library/nginx/tags/list",
+            ),
+            (
+                "docker.io/user/app:1.0.0",
+                "https://registry.hub.docker.com/v2/user/app/tags/list",
+            ),
+            (
+                "index.docker.io/library/nginx:1.25",
+                "https://registry.hub.docker.com/v2/library/nginx/tags/list",
+            ),
+            (
+                "registry-1.docker.io/nginx:1.25",
+                "https://registry.hub.docker.com/v2/library/nginx/tags/list",
+            ),
+        ] {
+            let image = Image::parse(raw.to_string()).unwrap();
+            let url = tags_url(&image.registry_url(&[]).unwrap(), &image.repository()).unwrap();
+            assert_eq!(expected, url.as_str(), "{raw}");
+        }
+    }
+
+    #[test]
+    fn parse_transport_scheme() {
+        assert_eq!(
+            Ok(Image {
+                registry: Cow::Borrowed("registry.hub.docker.com"),
+                namespace: Some("library".to_string()),
+                name: "nginx".to_string(),
+                tag: RawTag {
+                    tag: Cow::Borrowed("1.25")
+                },
+                digest: None,
+            }),
+            Image::parse("docker://registry.hub.docker.com/library/nginx:1.25".to_string()),
+        );
+
+        assert_eq!(
+            Image::parse("ghcr.io/user/app:1.2.3".to_string()),
+            Image::parse("oci://ghcr.io/user/app:1.2.3".to_string()),
+        );
+    }
+
+    #[tokio::test]
+    async fn catalog_pagination() {
+        use wiremock::{
+            matchers::{method, path, query_param},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/_catalog"))
+            .and(query_param("last", "b"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "repositories": ["c"] })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/_catalog"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Link", "</v2/_catalog?last=b&n=2>; rel=\"next\"")
+                    .set_body_json(serde_json::json!({ "repositories": ["a", "b"] })),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        let repositories = list_repositories(&client, &registry_url, 3, None)
+            .await
+            .expect("Listing Repositories");
+
+        assert_eq!(vec!["a", "b", "c"], repositories);
+    }
+
+    #[test]
+    fn parse_digest_image() {
+        assert_eq!(
+            Ok(Image {
+                registry: Cow::Borrowed("registry.hub.docker.com"),
+                namespace: Some("user".to_string()),
+                name: "test".to_string(),
+                tag: RawTag {
+                    tag: Cow::Borrowed("latest")
+                },
+                digest: Some("sha256:abcdef".to_string()),
+            }),
+            Image::parse("user/test@sha256:abcdef".to_string()),
+        );
+
+        assert_eq!(
+            Ok(Image {
+                registry: Cow::Borrowed("registry.hub.docker.com"),
+                namespace: None,
+                name: "test".to_string(),
+                tag: RawTag {
+                    tag: Cow::Borrowed("1.2.3")
+                },
+                digest: Some("sha256:abcdef".to_string()),
+            }),
+            Image::parse("test:1.2.3@sha256:abcdef".to_string()),
+        );
+    }
+
+    #[tokio::test]
+    async fn manifest_digest() {
+        use wiremock::{
+            matchers::{header_exists, method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/v2/library/app/manifests/1.2.3"))
+            .and(header_exists("accept"))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("Docker-Content-Digest", "sha256:aaa"),
+            )
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        assert_eq!(
+            Some("sha256:aaa".to_string()),
+            get_digest(&client, &registry_url, "library/app", "1.2.3", 3, None)
+                .await
+                .expect("Resolving Digest")
+        );
+    }
+
+    #[tokio::test]
+    async fn tags_not_modified() {
+        use wiremock::{
+            matchers::{header, method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .and(header("if-none-match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_json(serde_json::json!({
+                        "name": "user/app",
+                        "tags": ["1.0.0", "1.1.0"],
+                    })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let cache = TagCache::default();
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        let first = list_tags(&client, &cache, &registry_url, "user/app", 10, 3, None)
+            .await
+            .expect("Listing Tags");
+        let second = list_tags(&client, &cache, &registry_url, "user/app", 10, 3, None)
+            .await
+            .expect("Reusing cached Tags");
+
+        assert_eq!(vec!["1.0.0", "1.1.0"], first);
+        assert_eq!(first, second);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn tags_cached_within_ttl() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "user/app",
+                "tags": ["1.0.0"],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        {
+            let cache = TagCache::default().with_ttl(Duration::from_secs(60));
+            let first = list_tags(&client, &cache, &registry_url, "user/app", 10, 3, None)
+                .await
+                .expect("Listing Tags");
+            let second = list_tags(&client, &cache, &registry_url, "user/app", 10, 3, None)
+                .await
+                .expect("Reusing cached Tags");
+            assert_eq!(first, second);
+        }
+        server.verify().await;
+        server.reset().await;
+
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "user/app",
+                "tags": ["1.0.0"],
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let ttl = Duration::from_secs(60);
+        let cache = TagCache::default().with_ttl(ttl);
+        list_tags(&client, &cache, &registry_url, "user/app", 10, 3, None)
+            .await
+            .expect("Listing Tags");
+        // Ages the cached List instead of waiting for the TTL to pass
+        for cached in cache.entries.lock().unwrap().values_mut() {
+            cached.fetched_at = cached.fetched_at.checked_sub(ttl).unwrap();
+        }
+        list_tags(&client, &cache, &registry_url, "user/app", 10, 3, None)
+            .await
+            .expect("Listing Tags after the TTL");
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn tags_cache_cleared() {
+        use wiremock::{
+            matchers::{header_exists, method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .and(header_exists("if-none-match"))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_json(serde_json::json!({
+                        "name": "user/app",
+                        "tags": ["1.0.0"],
+                    })),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let cache = TagCache::default();
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        list_tags(&client, &cache, &registry_url, "user/app", 10, 3, None)
+            .await
+            .expect("Listing Tags");
+        assert_eq!(1, cache.clear());
+        list_tags(&client, &cache, &registry_url, "user/app", 10, 3, None)
+            .await
+            .expect("Listing Tags without Cache");
+
+        server.verify().await;
+    }
+
+    #[test]
+    fn tags_page_shapes() {
+        assert_eq!(
+            vec!["1.0.0".to_string()],
+            parse_tags_page(br#"{"name": "user/app", "tags": ["1.0.0"]}"#, "user/app").unwrap()
+        );
+        assert!(
+            parse_tags_page(br#"{"name": "user/app", "tags": null}"#, "user/app")
+                .unwrap()
+                .is_empty()
+        );
+
+        for body in [
+            r#"{"errors": [{"code": "NAME_UNKNOWN", "message": "repository name not known"}]}"#,
+            r#"{"name": "user/app"}"#,
+            r#"{}"#,
+        ] {
+            assert!(
+                matches!(
+                    parse_tags_page(body.as_bytes(), "user/app"),
+                    Err(GetTagsError::Deserialize(_))
+                ),
+                "{body}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn tags_wrapped_shape() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": { "tags": ["1.0.0", "1.1.0"] },
+            })))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        let tags = list_tags(
+            &client,
+            &TagCache::default(),
+            &registry_url,
+            "user/app",
+            10,
+            3,
+            None,
+        )
+        .await
+        .expect("Listing wrapped Tags");
+        assert_eq!(vec!["1.0.0", "1.1.0"], tags);
+    }
+
+    #[tokio::test]
+    async fn tags_from_registry_port() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/team/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "team/app",
+                "tags": ["1.0", "1.1"],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let host = server.address().to_string();
+        let image = Image::parse(format!("{host}/team/app:1.0")).unwrap();
+        assert_eq!(host, image.registry);
+
+        let tags = get_tags(
+            &reqwest::Client::new(),
+            &TagCache::default(),
+            &image,
+            &[host],
+            10,
+            3,
+            None,
+        )
+        .await
+        .expect("Listing Tags");
+        assert_eq!(vec!["1.0", "1.1"], tags);
+    }
+
+    #[test]
+    fn auth_challenge() {
+        let conf = parse_challenge(
+            r#"Bearer realm="https://auth.example.com/token",service="registry",scope="repository:user/app:pull,push""#,
+        )
+        .expect("Valid Challenge");
+        assert_eq!("https://auth.example.com/token", conf.realm);
+        assert_eq!("registry", conf.service);
+        assert_eq!("repository:user/app:pull,push", conf.scope);
+
+        assert!(matches!(
+            parse_challenge(r#"Bearer realm="https://auth.example.com/token",service="registry""#),
+            Err(GetTagsError::MissingChallengeField("scope"))
+        ));
+        assert!(matches!(
+            parse_challenge(r#"Basic realm="registry""#),
+            Err(GetTagsError::MalformedAuthChallenge(_))
+        ));
+        assert!(matches!(
+            parse_challenge("Bearer"),
+            Err(GetTagsError::MalformedAuthChallenge(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn tags_challenge_without_scope() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(
+                ResponseTemplate::new(401).insert_header(
+                    "WWW-Authenticate",
+                    format!(
+                        r#"Bearer realm="{}/token",service="registry""#,
+                        server.uri()
+                    )
+                    .as_str(),
+                ),
+            )
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        match list_tags(
+            &client,
+            &TagCache::default(),
+            &registry_url,
+            "user/app",
+            10,
+            3,
+            None,
+        )
+        .await
+        {
+            Err(GetTagsError::MissingChallengeField("scope")) => {}
+            other => panic!("Expected a missing scope, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn rate_limit_backoff() {
+        assert_eq!(
+            Duration::from_secs(5),
+            rate_limit_wait(Some(Duration::from_secs(5)), 0)
+        );
+        assert_eq!(Duration::from_secs(1), rate_limit_wait(None, 0));
+        assert_eq!(Duration::from_secs(4), rate_limit_wait(None, 2));
+        assert_eq!(
+            MAX_RATE_LIMIT_WAIT,
+            rate_limit_wait(Some(Duration::from_secs(3600)), 0)
+        );
+        assert_eq!(MAX_RATE_LIMIT_WAIT, rate_limit_wait(None, 20));
+    }
+
+    #[tokio::test]
+    async fn tags_rate_limited() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "user/app",
+                "tags": ["1.0"],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        {
+            let _limited = Mock::given(method("GET"))
+                .and(path("/v2/user/app/tags/list"))
+                .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+                .with_priority(1)
+                .up_to_n_times(1)
+                .expect(1)
+                .mount_as_scoped(&server)
+                .await;
+
+            let tags = list_tags(
+                &client,
+                &TagCache::default(),
+                &registry_url,
+                "user/app",
+                10,
+                3,
+                None,
+            )
+            .await
+            .expect("Retried after the Rate-Limit");
+            assert_eq!(vec!["1.0"], tags);
+        }
+
+        let _limited = Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .with_priority(1)
+            .expect(1)
+            .mount_as_scoped(&server)
+            .await;
+
+        match list_tags(
+            &client,
+            &TagCache::default(),
+            &registry_url,
+            "user/app",
+            10,
+            0,
+            None,
+        )
+        .await
+        {
+            Err(GetTagsError::StatusCode(reqwest::StatusCode::TOO_MANY_REQUESTS)) => {}
+            other => panic!("Expected the Rate-Limit, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn tags_ghcr_anonymous() {
+        use wiremock::{
+            matchers::{header, header_exists, method, path, query_param},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        const TOKEN: &str = "djE6b3duZXIvYXBwOjE3MDAwMDAwMDA=";
+
+        // Mirrors how ghcr.io challenges anonymous Pulls of public Images
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        Mock::given(method("GET"))
+            .and(path("/v2/owner/app/tags/list"))
+            .and(header("authorization", format!("Bearer {TOKEN}").as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "owner/app",
+                "tags": ["1.0.0", "1.1.0"],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/owner/app/tags/list"))
+            .respond_with(ResponseTemplate::new(401).insert_header(
+                "WWW-Authenticate",
+                format!(
+                    r#"Bearer realm="{}/token",service="ghcr.io",scope="repository:owner/app:pull""#,
+                    server.uri()
+                )
+                .as_str(),
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/token"))
+            .and(header_exists("authorization"))
+            .respond_with(ResponseTemplate::new(403))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/token"))
+            .and(query_param("service", "ghcr.io"))
+            .and(query_param("scope", "repository:owner/app:pull"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "token": TOKEN })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let image = Image::parse(format!("{host}/owner/app:1.0.0")).unwrap();
+        let tags = get_tags(
+            &reqwest::Client::new(),
+            &TagCache::default(),
+            &image,
+            &[host],
+            10,
+            3,
+            None,
+        )
+        .await
+        .expect("Anonymous Pull");
+
+        assert_eq!(vec!["1.0.0".to_string(), "1.1.0".to_string()], tags);
+    }
+
+    #[tokio::test]
+    async fn tags_with_credentials() {
+        use wiremock::{
+            matchers::{header, method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        const TOKEN: &str = "eyJhbGciOiJub25lIn0.e30.";
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/private/tags/list"))
+            .and(header("authorization", format!("Bearer {TOKEN}").as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "user/private",
+                "tags": ["1.0"],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/private/tags/list"))
+            .respond_with(ResponseTemplate::new(401).insert_header(
+                "WWW-Authenticate",
+                format!(
+                    r#"Bearer realm="{}/token",service="registry",scope="repository:user/private:pull""#,
+                    server.uri()
+                )
+                .as_str(),
+            ))
+            .mount(&server)
+            .await;
+        // `user:secret`
+        Mock::given(method("GET"))
+            .and(path("/token"))
+            .and(header("authorization", "Basic dXNlcjpzZWNyZXQ="))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "token": TOKEN })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+        let list = |credentials: Option<RegistryCredentials>| {
+            let client = &client;
+            let registry_url = &registry_url;
+            async move {
+                let cache = TagCache::default();
+                list_tags(
+                    client,
+                    &cache,
+                    registry_url,
+                    "user/private",
+                    10,
+                    3,
+                    credentials.as_ref(),
+                )
+                .await
+            }
+        };
+
+        let basic = RegistryCredentials::Basic {
+            username: "user".to_string(),
+            password: "secret".to_string(),
+        };
+        let tags = list(Some(basic))
+            .await
+            .expect("Authenticated using Basic auth");
+        assert_eq!(vec!["1.0"], tags);
+
+        let tags = list(Some(RegistryCredentials::Token(TOKEN.to_string())))
+            .await
+            .expect("Authenticated using the configured Token");
+        assert_eq!(vec!["1.0"], tags);
+
+        assert!(matches!(
+            list(None).await,
+            Err(GetTagsError::AuthError(AuthError::StatusCode(
+                reqwest::StatusCode::UNAUTHORIZED
+            )))
+        ));
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn credentials_kept_from_other_hosts() {
+        use wiremock::{
+            matchers::{header, method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let other = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/private/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tags": ["1.1"],
+            })))
+            .expect(1)
+            .mount(&other)
+            .await;
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/private/tags/list"))
+            .and(header("authorization", "Bearer secret"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header(
+                        "Link",
+                        format!(
+                            r#"<{}/v2/user/private/tags/list?last=1.0>; rel="next""#,
+                            other.uri()
+                        )
+                        .as_str(),
+                    )
+                    .set_body_json(serde_json::json!({ "tags": ["1.0"] })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+        let tags = list_tags(
+            &reqwest::Client::new(),
+            &TagCache::default(),
+            &registry_url,
+            "user/private",
+            10,
+            3,
+            Some(&RegistryCredentials::Token("secret".to_string())),
+        )
+        .await
+        .expect("Listing both pages");
+        assert_eq!(vec!["1.0", "1.1"], tags);
+
+        let requests = other.received_requests().await.unwrap();
+        assert!(requests
+            .iter()
+            .flat_map(|r| r.headers.iter())
+            .all(|(name, _)| name.as_str() != "authorization"));
+    }
+
+    #[tokio::test]
+    async fn tags_paginated() {
+        use wiremock::{
+            matchers::{header, method, path, query_param},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        // `{"alg":"none"}.{}` without a Signature
+        const TOKEN: &str = "eyJhbGciOiJub25lIn0.e30.";
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .and(query_param("last", "1.1.0"))
+            .and(header("Authorization", format!("Bearer {TOKEN}").as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tags": ["1.2.0"],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .and(header("Authorization", format!("Bearer {TOKEN}").as_str()))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header(
+                        "Link",
+                        r#"</v2/user/app/tags/list?n=2&last=1.1.0>; rel="next""#,
+                    )
+                    .insert_header("ETag", "\"first\"")
+                    .set_body_json(serde_json::json!({ "tags": ["1.0.0", "1.1.0"] })),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(401).insert_header(
+                "WWW-Authenticate",
+                format!(
+                    r#"Bearer realm="{}/token",service="registry",scope="repository:user/app:pull""#,
+                    server.uri()
+                )
+                .as_str(),
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "token": TOKEN,
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let cache = TagCache::default();
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        let tags = list_tags(&client, &cache, &registry_url, "user/app", 10, 3, None)
+            .await
+            .expect("Listing all pages");
+        assert_eq!(vec!["1.0.0", "1.1.0", "1.2.0"], tags);
+        assert_eq!(0, cache.clear(), "Paginated Lists are not cached");
+
+        let tags = list_tags(&client, &cache, &registry_url, "user/app", 1, 3, None)
+            .await
+            .expect("Listing the first page");
+        assert_eq!(vec!["1.0.0", "1.1.0"], tags);
+    }
+
+    #[tokio::test]
+    async fn tags_repository_mismatch() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "user/other",
+                "tags": ["1.0.0", "9.9.9"],
+            })))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        match list_tags(
+            &client,
+            &TagCache::default(),
+            &registry_url,
+            "user/app",
+            10,
+            3,
+            None,
+        )
+        .await
+        {
+            Err(GetTagsError::RepositoryMismatch { expected, returned }) => {
+                assert_eq!("user/app", expected);
+                assert_eq!("user/other", returned);
+            }
+            other => panic!("Expected Repository Mismatch, got {:?}", other),
+        };
+    }
+
     #[test]
     fn tag_latest() {
         let tag = RawTag::new("latest");
@@ -402,12 +2548,268 @@ mod tests {
 
         assert_eq!(
             Version::Semantic {
+                v_prefix: false,
                 major: 1,
                 minor: Some(2),
-                patch: Some(3)
+                patch: Some(3),
+                revision: None,
+                pre_release: None,
+                variant: None,
+                build: None,
+                written: None,
+            },
+            version
+        );
+    }
+
+    #[test]
+    fn tag_pre_release() {
+        let version = RawTag::new("1.4.2-rc1")
+            .parse_version()
+            .expect("Valid Version");
+        assert_eq!(
+            Version::Semantic {
+                v_prefix: false,
+                major: 1,
+                minor: Some(4),
+                patch: Some(2),
+                revision: None,
+                pre_release: Some("rc1".to_string()),
+                variant: None,
+                build: None,
+                written: None,
+            },
+            version
+        );
+        assert!(version.fully_qualified());
+        assert_eq!("1.4.2-rc1", format!("{version}"));
+        assert_eq!(
+            "2.0.0-beta.3",
+            format!("{}", RawTag::new("2.0.0-beta.3").parse_version().unwrap())
+        );
+
+        let release = RawTag::new("1.4.2").parse_version().unwrap();
+        assert!(version < release);
+        assert!(RawTag::new("1.4.1").parse_version().unwrap() < version);
+
+        let rc2 = RawTag::new("1.4.2-rc.2").parse_version().unwrap();
+        let rc10 = RawTag::new("1.4.2-rc.10").parse_version().unwrap();
+        assert!(rc2 < rc10);
+        assert!(rc10 < release);
+        assert_eq!(
+            std::cmp::Ordering::Less,
+            VersionOrdering::MissingAsZero.compare(&rc2, &rc10)
+        );
+    }
+
+    #[test]
+    fn tag_variant() {
+        for (raw, major, minor, variant) in [
+            ("1.21-alpine", 1, Some(21), Some("alpine")),
+            ("16-bullseye", 16, None, Some("bullseye")),
+            ("16", 16, None, None),
+            ("3.11-slim-bookworm", 3, Some(11), Some("slim-bookworm")),
+        ] {
+            let version = RawTag::new(raw).parse_version().expect("Valid Version");
+            assert_eq!(
+                Version::Semantic {
+                    v_prefix: false,
+                    major,
+                    minor,
+                    patch: None,
+                    revision: None,
+                    pre_release: None,
+                    variant: variant.map(String::from),
+                    build: None,
+                    written: None,
+                },
+                version,
+                "{raw}"
+            );
+            assert_eq!(raw, format!("{version}"));
+        }
+
+        let version = RawTag::new("1.4.2-rc1-alpine").parse_version().unwrap();
+        assert_eq!(Some("alpine"), version.variant());
+        assert!(version < RawTag::new("1.4.2-alpine").parse_version().unwrap());
+        assert_eq!("1.4.2-rc1-alpine", format!("{version}"));
+    }
+
+    #[test]
+    fn tag_build_metadata() {
+        let first = RawTag::new("1.2.3+a")
+            .parse_version()
+            .expect("Valid Version");
+        let second = RawTag::new("1.2.3+b")
+            .parse_version()
+            .expect("Valid Version");
+
+        assert_eq!(std::cmp::Ordering::Equal, first.cmp(&second));
+        assert_eq!(first, second);
+        assert_eq!(
+            std::cmp::Ordering::Equal,
+            VersionOrdering::MissingAsZero.compare(&first, &second)
+        );
+        assert!(first < RawTag::new("1.2.4").parse_version().unwrap());
+        assert_eq!("1.2.3+a", format!("{first}"));
+
+        let version = RawTag::new("1.4.2-rc.1+20240115").parse_version().unwrap();
+        assert_eq!(
+            Version::Semantic {
+                v_prefix: false,
+                major: 1,
+                minor: Some(4),
+                patch: Some(2),
+                revision: None,
+                pre_release: Some("rc.1".to_string()),
+                variant: None,
+                build: Some("20240115".to_string()),
+                written: None,
+            },
+            version
+        );
+        assert_eq!("1.4.2-rc.1+20240115", format!("{version}"));
+    }
+
+    #[test]
+    fn tag_describe_suffix() {
+        for (raw, expected) in [
+            ("1.4.2-g1a2b3c", "1.4.2"),
+            ("1.4.2-3-g1a2b3c4", "1.4.2"),
+            ("v1.4.2-12-gABCDEF", "v1.4.2"),
+            ("1.4.2-alpine", "1.4.2-alpine"),
+            ("1.4.2-gnu", "1.4.2-gnu"),
+        ] {
+            assert_eq!(
+                expected,
+                RawTag::new(raw).without_describe_suffix().as_str(),
+                "{raw}"
+            );
+        }
+    }
+
+    #[test]
+    fn tag_ignore_case() {
+        for (upper, lower) in [
+            ("V1.2.3", "v1.2.3"),
+            ("1.2.3-RC1", "1.2.3-rc1"),
+            ("1.2.3-ALPINE", "1.2.3-alpine"),
+        ] {
+            assert_eq!(
+                RawTag::new(lower).parse_version(),
+                RawTag::new(upper).parse_version_ignore_case(),
+                "{upper}"
+            );
+        }
+
+        assert!(RawTag::new("V1.2.3").parse_version().is_err());
+        let version = RawTag::new("V1.2.3-RC1")
+            .parse_version_ignore_case()
+            .unwrap();
+        assert_eq!("V1.2.3-RC1", format!("{version}"));
+        assert!(version.is_pre_release());
+        assert_eq!(
+            RawTag::new("V1.2.3").as_str(),
+            "V1.2.3",
+            "The original Tag is kept for display"
+        );
+    }
+
+    #[test]
+    fn ordering_modes() {
+        let short = RawTag::new("1.2").parse_version().unwrap();
+        let full = RawTag::new("1.2.0").parse_version().unwrap();
+        let extended = RawTag::new("1.2.0.0").parse_version().unwrap();
+
+        assert_eq!(
+            std::cmp::Ordering::Greater,
+            VersionOrdering::Strict.compare(&short, &full)
+        );
+        assert_eq!(
+            std::cmp::Ordering::Equal,
+            VersionOrdering::MissingAsZero.compare(&short, &full)
+        );
+        assert_eq!(
+            std::cmp::Ordering::Equal,
+            VersionOrdering::MissingAsZero.compare(&full, &extended)
+        );
+        assert_eq!(
+            std::cmp::Ordering::Less,
+            VersionOrdering::MissingAsZero
+                .compare(&short, &RawTag::new("1.2.1").parse_version().unwrap())
+        );
+
+        let prefixed = |raw: &str| RawTag::new(raw).parse_version().unwrap();
+        assert_eq!(
+            std::cmp::Ordering::Equal,
+            VersionOrdering::MissingAsZero.compare(&prefixed("v1.2"), &prefixed("v1.2.0"))
+        );
+        assert_eq!(
+            std::cmp::Ordering::Equal,
+            VersionOrdering::MissingAsZero.compare(&prefixed("v1.2"), &full)
+        );
+        assert_eq!(
+            std::cmp::Ordering::Less,
+            VersionOrdering::MissingAsZero.compare(&prefixed("v1.2"), &prefixed("v1.2.1"))
+        );
+
+        let stable = Version::Named("stable".to_string());
+        let beta = Version::Named("beta".to_string());
+        for ordering in [VersionOrdering::Strict, VersionOrdering::MissingAsZero] {
+            assert_eq!(std::cmp::Ordering::Equal, ordering.compare(&stable, &beta));
+            assert_eq!(std::cmp::Ordering::Equal, ordering.compare(&beta, &stable));
+        }
+    }
+
+    #[test]
+    fn calendar_versions() {
+        let version = |raw: &str| RawTag::new(raw).parse_version().unwrap();
+
+        assert!(version("2024.01") > version("2023.12"));
+        assert!(version("2024.01.2") > version("2024.01"));
+        assert!(version("20240201") > version("20240115"));
+        assert!(version("2024.01").is_calver());
+        assert!(version("20240115").is_calver());
+        assert!(!version("1.2.3").is_calver());
+        assert!(!version("2024.13").is_calver());
+        assert!(!version("12345678").is_calver());
+        assert_eq!(version("2024.01"), version("2024.1"));
+        assert_eq!("2024.01", format!("{}", version("2024.01")));
+
+        assert_eq!("2024.01-alpine", format!("{}", version("2024.01-alpine")));
+        assert_eq!(Some("alpine"), version("2024.01-alpine").variant());
+    }
+
+    #[test]
+    fn four_components() {
+        let version = |raw: &str| RawTag::new(raw).parse_version().unwrap();
+
+        assert!(version("7.17.0.1") < version("7.17.0.2"));
+        assert!(version("7.17.0.9") < version("7.17.0.10"));
+        assert!(version("7.17.0.2") < version("7.17.1"));
+        assert!(version("7.17.0") < version("7.17.0.1"));
+        assert_eq!(
+            std::cmp::Ordering::Less,
+            VersionOrdering::MissingAsZero.compare(&version("7.17.0.1"), &version("7.17.0.2"))
+        );
+
+        let version = version("7.17.0.1-alpine");
+        assert!(version.fully_qualified());
+        assert_eq!(
+            Version::Semantic {
+                v_prefix: false,
+                major: 7,
+                minor: Some(17),
+                patch: Some(0),
+                revision: Some(1),
+                pre_release: None,
+                variant: Some("alpine".to_string()),
+                build: None,
+                written: None,
             },
             version
         );
+        assert_eq!("7.17.0.1-alpine", format!("{version}"));
     }
 
     #[test]
@@ -417,11 +2819,24 @@ mod tests {
 
         assert_eq!(
             Version::Semantic {
+                v_prefix: true,
                 major: 1,
                 minor: Some(2),
-                patch: Some(3)
+                patch: Some(3),
+                revision: None,
+                pre_release: None,
+                variant: None,
+                build: None,
+                written: None,
             },
             version
         );
+        assert_eq!("v1.2.3", format!("{version}"));
+        assert_eq!(
+            std::cmp::Ordering::Equal,
+            version.cmp(&RawTag::new("1.2.3").parse_version().unwrap())
+        );
+        assert_eq!(RawTag::new("1.2.3").parse_version().unwrap(), version);
+        assert!(version < RawTag::new("1.2.4").parse_version().unwrap());
     }
 }
@@ -1,75 +1,145 @@
 use std::{future::Future, sync::Arc, time::Duration};
 
-use bytes::{Buf, BytesMut};
+use bytes::BytesMut;
 use reqwest::Url;
 use serde::Deserialize;
 
+use crate::{allocations::AllocationTracker, metrics::NomadRequestMetrics};
+
+/// The Topics that are subscribed to, as only their Events trigger a Check
+const TOPICS: [&str; 2] = ["Job", "Allocation"];
+
+/// How long a single Subscription is kept open, which replaces the Timeout of the Client as the
+/// Subscription is expected to stay open. It is simply reopened at the current index afterwards
+const SUBSCRIPTION_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
 pub struct EventStream {
     client: reqwest::Client,
     base_url: Url,
     index: usize,
+    metrics: NomadRequestMetrics,
+    /// Only the Events of this Namespace are subscribed to, if set
+    namespace: Option<String>,
+    /// The ACL Token sent along with the Subscription, if set
+    token: Option<String>,
+    allocations: Option<AllocationTracker>,
 }
 
 impl EventStream {
-    pub fn new(client: reqwest::Client, base_url: Url) -> Self {
+    pub fn new(
+        client: reqwest::Client,
+        base_url: Url,
+        metrics: NomadRequestMetrics,
+        namespace: Option<String>,
+    ) -> Self {
         Self {
             client,
             base_url,
             index: 0,
+            metrics,
+            namespace,
+            token: None,
+            allocations: None,
+        }
+    }
+
+    /// Authenticates the Subscription using the ACL Token
+    pub fn with_token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Tracks the Images of the individual Allocations based on their Events
+    pub fn with_allocations(mut self, tracker: AllocationTracker) -> Self {
+        self.allocations = Some(tracker);
+        self
+    }
+
+    /// The URL to subscribe to the Events starting at the current index
+    fn stream_url(&self) -> Url {
+        let mut url = self.base_url.join("v1/event/stream").expect("");
+
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("index", &self.index.to_string());
+            for topic in TOPICS {
+                query.append_pair("topic", topic);
+            }
+            if let Some(namespace) = self.namespace.as_deref() {
+                query.append_pair("namespace", namespace);
+            }
+        }
+
+        url
+    }
+
+    /// Forgets the tracked Allocations that stopped while no Events were received, like before
+    /// (re)subscribing
+    async fn reconcile_allocations(&mut self) {
+        let tracker = match self.allocations.as_mut() {
+            Some(t) => t,
+            None => return,
+        };
+
+        match crate::nomad::list_allocations(
+            &self.client,
+            &self.base_url,
+            self.namespace.as_deref(),
+            self.token.as_deref(),
+            &self.metrics,
+        )
+        .await
+        {
+            Ok(existing) => tracker.reconcile(existing),
+            Err(e) => tracing::error!("Listing Allocations: {}", e),
         }
     }
 
     #[tracing::instrument(skip(self, notify))]
     async fn listen(mut self, notify: Arc<tokio::sync::Notify>) {
-        let req_url = self.base_url.join("v1/event/stream").expect("");
+        loop {
+            self.reconcile_allocations().await;
 
-        let mut pending = BytesMut::new();
+            let specific_url = self.stream_url();
 
-        loop {
-            let mut specific_url = req_url.clone();
-            specific_url.set_query(Some(&format!("index={}", self.index)));
+            let mut req = self.client.get(specific_url).timeout(SUBSCRIPTION_TIMEOUT);
+            if let Some(token) = self.token.as_deref() {
+                req = req.header(crate::nomad::TOKEN_HEADER, token);
+            }
 
-            let resp = self.client.get(specific_url).send().await;
+            let start = std::time::Instant::now();
+            let resp = req.send().await;
+            self.metrics.observe(
+                "stream",
+                start.elapsed(),
+                resp.as_ref().ok().map(|r| r.status()),
+            );
 
             tracing::debug!("Starting Event-Stream: {}", resp.is_ok());
 
             if let Ok(mut resp) = resp {
+                let mut frames = Frames::default();
+
                 loop {
                     let chunk = match resp.chunk().await {
                         Ok(Some(c)) => c,
                         _ => break,
                     };
 
-                    pending.extend(chunk.as_ref());
-
-                    let tmp = pending
-                        .iter()
-                        .enumerate()
-                        .find(|(_, v)| **v == b'\n')
-                        .map(|(i, _)| i);
-                    let end_index = match tmp {
-                        Some(i) => i,
-                        None => continue,
-                    };
-
-                    let content = pending.split_to(end_index);
-                    let _ = pending.split_to(1);
+                    frames.push(chunk.as_ref());
+                    while let Some(result) = frames.next_event() {
+                        let event = match result {
+                            Ok(e) => e,
+                            Err(err) => {
+                                tracing::error!("Parsing Event: {:?}", err);
+                                continue;
+                            }
+                        };
 
-                    let event: EventResponse = match serde_json::from_slice(&content) {
-                        Ok(e) => e,
-                        Err(err) => {
-                            tracing::error!("Parsing Event: {:?}", err);
-                            continue;
+                        if self.handle(event) {
+                            notify.notify_one();
                         }
-                    };
-
-                    tracing::debug!("Event: {:#?}", event);
-
-                    if let Some(index) = event.index {
-                        self.index = core::cmp::max(self.index, index);
                     }
-
-                    notify.notify_waiters();
                 }
             } else {
                 tracing::error!("{:?}", resp);
@@ -79,6 +149,32 @@ impl EventStream {
         }
     }
 
+    /// Handles the Events and returns whether any of them concerns Jobs or Allocations, which
+    /// should trigger a Check
+    fn handle(&mut self, event: EventResponse) -> bool {
+        tracing::debug!("Event: {:#?}", event);
+
+        if let Some(index) = event.index {
+            self.index = core::cmp::max(self.index, index);
+        }
+
+        let mut relevant = false;
+        for event in event.events.into_iter().flatten() {
+            match event.topic {
+                EventTopic::Allocation => {
+                    if let Some(tracker) = self.allocations.as_mut() {
+                        tracker.handle(event.payload);
+                    }
+                    relevant = true;
+                }
+                EventTopic::Job => relevant = true,
+                _ => {}
+            };
+        }
+
+        relevant
+    }
+
     pub fn run(self) -> (impl Future<Output = ()>, Arc<tokio::sync::Notify>) {
         let notifier = Arc::new(tokio::sync::Notify::new());
 
@@ -86,7 +182,59 @@ impl EventStream {
     }
 }
 
+/// Splits the Event-Stream into its newline delimited Events, keeping the Bytes of Events that
+/// are split across Chunks until the rest of them arrives
+#[derive(Debug, Default)]
+struct Frames {
+    pending: BytesMut,
+    /// Up to where the pending Bytes are known to not contain a complete Event
+    checked: usize,
+}
+
+impl Frames {
+    fn push(&mut self, chunk: &[u8]) {
+        self.pending.extend_from_slice(chunk);
+    }
+
+    /// Returns the next complete Event, or None if more Bytes are needed for it
+    fn next_event(&mut self) -> Option<Result<EventResponse, serde_json::Error>> {
+        loop {
+            let end = self.checked
+                + self.pending[self.checked..]
+                    .iter()
+                    .position(|b| *b == b'\n')?;
+
+            let frame = &self.pending[..end];
+            if frame.iter().all(u8::is_ascii_whitespace) {
+                let _ = self.pending.split_to(end + 1);
+                self.checked = 0;
+                continue;
+            }
+
+            match serde_json::from_slice::<EventResponse>(frame) {
+                // The Event is not complete yet, so the newline has to be part of it
+                Err(e) if e.is_eof() => {
+                    self.checked = end + 1;
+                }
+                Ok(event) => {
+                    let _ = self.pending.split_to(end + 1);
+                    self.checked = 0;
+                    return Some(Ok(event));
+                }
+                // Only the first line is discarded, the following ones might be valid Events
+                Err(e) => {
+                    let first_end = self.pending.iter().position(|b| *b == b'\n').unwrap_or(end);
+                    let _ = self.pending.split_to(first_end + 1);
+                    self.checked = 0;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
+#[allow(dead_code)]
 struct EventResponse {
     #[serde(rename = "Events")]
     events: Option<Vec<Event>>,
@@ -95,6 +243,7 @@ struct EventResponse {
 }
 
 #[derive(Debug, Deserialize)]
+#[allow(dead_code)]
 struct Event {
     #[serde(rename = "FilterKeys", default)]
     filter_keys: Option<Vec<String>>,
@@ -157,3 +306,105 @@ enum EventType {
     #[serde(other)]
     Unknown,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(namespace: Option<&str>) -> EventStream {
+        let reg = prometheus::Registry::new();
+
+        EventStream::new(
+            reqwest::Client::new(),
+            Url::parse("http://localhost:4646").unwrap(),
+            NomadRequestMetrics::new(&reg).unwrap(),
+            namespace.map(String::from),
+        )
+    }
+
+    #[test]
+    fn topics_in_stream_url() {
+        let url = stream(None).stream_url();
+        let topics: Vec<_> = url
+            .query_pairs()
+            .filter(|(key, _)| key == "topic")
+            .map(|(_, value)| value.into_owned())
+            .collect();
+
+        assert_eq!(vec!["Job", "Allocation"], topics);
+        assert!(url
+            .query_pairs()
+            .any(|(key, value)| key == "index" && value == "0"));
+    }
+
+    #[test]
+    fn namespace_in_stream_url() {
+        assert_eq!(
+            "http://localhost:4646/v1/event/stream?index=0&topic=Job&topic=Allocation&namespace=team-a",
+            stream(Some("team-a")).stream_url().as_str()
+        );
+        assert_eq!(
+            "http://localhost:4646/v1/event/stream?index=0&topic=Job&topic=Allocation",
+            stream(None).stream_url().as_str()
+        );
+    }
+
+    #[test]
+    fn relevant_topics() {
+        let event = |topic: &str| {
+            serde_json::from_value::<EventResponse>(serde_json::json!({
+                "Index": 3,
+                "Events": [{
+                    "Index": 3,
+                    "Key": "web",
+                    "Namespace": "default",
+                    "Payload": {},
+                    "Topic": topic,
+                    "Type": "Unknown",
+                }],
+            }))
+            .unwrap()
+        };
+
+        let mut stream = stream(None);
+        assert!(stream.handle(event("Job")));
+        assert!(stream.handle(event("Allocation")));
+        assert!(!stream.handle(event("Node")));
+        assert!(
+            !stream.handle(serde_json::from_str("{}").unwrap()),
+            "Heartbeat"
+        );
+        assert_eq!(3, stream.index);
+    }
+
+    #[test]
+    fn frame_split_across_chunks() {
+        let mut frames = Frames::default();
+
+        frames.push(br#"{"Index": 5, "Ev"#);
+        assert!(frames.next_event().is_none());
+
+        frames.push(b"ents\": []}\n{}\n");
+        let event = frames.next_event().unwrap().expect("Complete Event");
+        assert_eq!(Some(5), event.index);
+        assert!(frames.next_event().unwrap().is_ok(), "Heartbeat");
+        assert!(frames.next_event().is_none());
+    }
+
+    #[test]
+    fn frame_with_newline_or_invalid() {
+        let mut frames = Frames::default();
+
+        // A newline inside the Event does not end it
+        frames.push(b"{\"Index\": 7,\n");
+        assert!(frames.next_event().is_none());
+        frames.push(b"\"Events\": null}\n");
+        assert_eq!(Some(7), frames.next_event().unwrap().unwrap().index);
+
+        // An invalid line is discarded without losing the following Event
+        frames.push(b"{\"Index\": 7,\n{\"Index\": 8}\n");
+        assert!(frames.next_event().unwrap().is_err());
+        assert_eq!(Some(8), frames.next_event().unwrap().unwrap().index);
+        assert!(frames.next_event().is_none());
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum FeedError {
+    Request(reqwest::Error),
+    Status(reqwest::StatusCode),
+    Io(std::io::Error),
+    /// The Feed did not contain a Version
+    Empty,
+}
+
+impl Display for FeedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request(e) => write!(f, "sending request to feed: {e}"),
+            Self::Status(s) => write!(f, "feed returned {s}"),
+            Self::Io(e) => write!(f, "reading feed file: {e}"),
+            Self::Empty => write!(f, "feed contains no version"),
+        }
+    }
+}
+
+impl std::error::Error for FeedError {}
+
+/// Loads the approved Version of an Image from the Feed, which is either an HTTP Endpoint or a
+/// `file://` URL whose content is the Version, like `1.2.3`
+pub async fn approved_version(
+    client: &reqwest::Client,
+    feed_url: &reqwest::Url,
+) -> Result<String, FeedError> {
+    let content = match feed_url.to_file_path() {
+        Ok(path) if feed_url.scheme() == "file" => tokio::fs::read_to_string(path)
+            .await
+            .map_err(FeedError::Io)?,
+        _ => {
+            let resp = client
+                .get(feed_url.clone())
+                .send()
+                .await
+                .map_err(FeedError::Request)?;
+            if !resp.status().is_success() {
+                return Err(FeedError::Status(resp.status()));
+            }
+
+            resp.text().await.map_err(FeedError::Request)?
+        }
+    };
+
+    match content.trim() {
+        "" => Err(FeedError::Empty),
+        version => Ok(version.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn approved_version_from_file() {
+        let path = std::env::temp_dir().join(format!("vmonitor-feed-{}", std::process::id()));
+        std::fs::write(&path, "1.4.2\n").unwrap();
+
+        let url = reqwest::Url::from_file_path(&path).unwrap();
+        let version = approved_version(&reqwest::Client::new(), &url).await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!("1.4.2", version.expect("Reading Feed"));
+    }
+}
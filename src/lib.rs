@@ -1,200 +1,3434 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
 
+use futures::StreamExt;
 use prometheus::{Encoder, Registry, TextEncoder};
+use serde::Serialize;
 
+mod allocations;
+mod config;
 mod docker;
+mod feed;
 mod metrics;
 mod nomad;
+mod pushgateway;
+mod schedule;
+mod select;
+mod state;
+mod status;
+mod webhook;
 
 mod eventstream;
 
+pub use config::{
+    AttentionState, Config, HttpConfig, RawExecBehaviour, UpdateTrack, UrgencyWeights,
+};
+pub use docker::{GetTagsError, Image, RawTag, RegistryCredentials, VersionOrdering};
+pub use nomad::RELEASES_URL as NOMAD_RELEASES_URL;
+pub use pushgateway::{PushError, PushgatewayConfig};
+pub use status::{StatusFormat, TaskDetail, TaskState, TaskStatus};
+pub use webhook::{Notification, WebhookConfig};
+
+/// The newest Version available for a Repository in a Registry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RepositoryVersion {
+    pub repository: String,
+    pub newest: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The Nomad URL is not valid
+    Url(reqwest::Error),
+    /// The HTTP-Client could not be built, like when the TLS Backend fails to initialize
+    Http(reqwest::Error),
+    /// The configured CA Certificate could not be read
+    ReadCertificate(std::io::Error),
+    /// The configured CA Certificate is not a valid PEM-encoded Certificate
+    Certificate(reqwest::Error),
+    /// The Metrics could not be registered
+    Metrics(prometheus::Error),
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Url(e) => write!(f, "invalid nomad url: {e}"),
+            Self::Http(e) => write!(f, "building http client: {e}"),
+            Self::ReadCertificate(e) => write!(f, "reading ca certificate: {e}"),
+            Self::Certificate(e) => write!(f, "parsing ca certificate: {e}"),
+            Self::Metrics(e) => write!(f, "registering metrics: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+/// Builds the HTTP-Client used for all Requests to Registries and Nomad
+fn http_client(config: &HttpConfig) -> Result<reqwest::Client, ClientError> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(config.timeout)
+        .danger_accept_invalid_certs(config.accept_invalid_certs);
+    if let Some(path) = config.ca_cert.as_ref() {
+        let pem = std::fs::read(path).map_err(ClientError::ReadCertificate)?;
+        let cert = reqwest::Certificate::from_pem(&pem).map_err(ClientError::Certificate)?;
+        builder = builder.add_root_certificate(cert);
+    }
+
+    builder.build().map_err(ClientError::Http)
+}
+
+/// Why a Check could not be completed, the Check is simply attempted again in the next Cycle
+#[derive(Debug)]
+enum CheckError {
+    /// The Jobs could not be listed, so no Task could be checked
+    ListJobs(nomad::NomadError),
+}
+
+impl Display for CheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ListJobs(e) => write!(f, "listing jobs: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CheckError {}
+
+/// How many Entries were removed from the Caches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CacheCleared {
+    /// The cached Tag-Lists of Repositories
+    pub tags: usize,
+}
+
+/// How many of the newer Versions of a Task are kept for its Status Details
+const MAX_NEWER_VERSIONS: usize = 50;
+
+/// What checking the Image of a single Task found out
+#[derive(Debug, Default)]
+struct ImageOutcome {
+    version: Option<metrics::UpdatedVersion>,
+    candidates: Option<select::CandidateCounts>,
+    /// The Versions newer than the deployed one, oldest first
+    newer: Vec<String>,
+    /// The parsed Versions, if the Image is out of date
+    compared: Option<state::ComparedVersions>,
+    /// The Repository of the Image with the newest Version its Registry has
+    registry_newest: Option<(String, docker::Version)>,
+    /// Whether the Image uses the plain `latest` Tag, whose Digest is compared across Checks
+    latest: bool,
+    /// Whether the Registry or Feed of the Image could not be reached
+    unreachable: bool,
+    /// Whether the deployed Tag is no longer listed by the Registry
+    current_missing: bool,
+}
+
+/// A Task that was read from Nomad and is waiting to be checked
+struct PendingTask {
+    key: state::TaskKey,
+    /// The Version of the Job the Task belongs to
+    job_version: u64,
+    filter: select::CandidateFilter,
+    upstream: Option<String>,
+    config: nomad::ReadJobConfig,
+}
+
+/// Everything that was learned about the monitored Tasks while reading the Jobs
+#[derive(Debug, Default)]
+struct ReadTasks {
+    existing: BTreeSet<state::TaskKey>,
+    /// The Repositories, like `ghcr.io/user/app`, of the Images of the Tasks
+    repositories: BTreeSet<String>,
+    /// The Owner of the Job of every Task that has one
+    owners: BTreeMap<state::TaskKey, String>,
+    /// The IDs of the Jobs that could not be read
+    failures: Vec<String>,
+    fingerprint: String,
+}
+
+/// Everything a Check found out about a single Task
+#[derive(Debug, Default)]
+struct TaskOutcome {
+    version: Option<metrics::UpdatedVersion>,
+    /// The Tag and the Digest it resolved to
+    digest: Option<(String, String)>,
+    /// The Digest `latest` resolves to, if the Task uses the plain `latest` Tag
+    latest_digest: Option<String>,
+    mirror: Option<select::MirrorStatus>,
+    candidates: Option<select::CandidateCounts>,
+    /// The Versions newer than the deployed one, oldest first
+    newer: Vec<String>,
+    /// The parsed Versions, if the Task is out of date
+    compared: Option<state::ComparedVersions>,
+    /// The Repository of the Image with the newest Version its Registry has
+    registry_newest: Option<(String, docker::Version)>,
+    /// Whether the Task should have been checked but could not be
+    unreachable: bool,
+    /// Whether the deployed Tag of the Task is no longer listed by the Registry
+    current_missing: bool,
+}
+
 #[derive(Debug)]
 pub struct Client {
     client: reqwest::Client,
     nomad_url: reqwest::Url,
+    /// The ACL Token sent along with every Request to Nomad
+    nomad_token: Option<String>,
     registry: Registry,
+    config: Config,
+    tag_cache: docker::TagCache,
     general: metrics::Metrics,
+    nomad_metrics: metrics::NomadRequestMetrics,
+    allocation_metrics: metrics::AllocationMetrics,
+    status: RwLock<Vec<TaskStatus>>,
+    state: Mutex<state::CycleState>,
+    notifier: Option<webhook::Notifier>,
+    /// When the last Check completed, or when the Client was created before the first Check
+    last_check: RwLock<Instant>,
+    /// The Fingerprint of the Tasks monitored in the last Check
+    fingerprint: RwLock<String>,
+    /// Whether Nomad listed any Jobs in the last Check
+    found_jobs: RwLock<bool>,
+    /// Whether any Check completed successfully yet
+    ready: RwLock<bool>,
+    /// Starts the next Check right away, see [`trigger_check`](Self::trigger_check)
+    trigger: tokio::sync::Notify,
+    /// Set while the Checks are paused, with the Instant at which they resume on their own
+    paused: RwLock<Option<Pause>>,
+}
+
+/// A Pause of the Checks, like during Maintenance
+#[derive(Debug, Clone, Copy)]
+struct Pause {
+    /// When the Checks resume on their own, if at all
+    until: Option<Instant>,
 }
 
 impl Client {
     pub fn new(nomad_url: impl reqwest::IntoUrl) -> Self {
-        let reg = Registry::new();
+        Self::with_registry(nomad_url, Registry::new()).expect("Creating Client")
+    }
+
+    /// Creates a Client that registers its Metrics in the given Registry, which fails if the
+    /// Metrics are already registered there
+    pub fn with_registry(
+        nomad_url: impl reqwest::IntoUrl,
+        reg: Registry,
+    ) -> Result<Self, ClientError> {
+        let client = http_client(&HttpConfig::default())?;
+
+        Self::build(nomad_url, client, reg)
+    }
+
+    /// Creates a Client whose HTTP-Client is configured for talking to Registries and Nomad,
+    /// like trusting a private CA or using a different Timeout
+    pub fn with_http(
+        nomad_url: impl reqwest::IntoUrl,
+        http: &HttpConfig,
+    ) -> Result<Self, ClientError> {
+        let client = http_client(http)?;
+
+        Self::build(nomad_url, client, Registry::new())
+    }
 
-        let general_metrics = metrics::Metrics::new(&reg);
+    /// Creates a Client that sends all its Requests using the given HTTP-Client, for example
+    /// one that is already configured with a Proxy or shares its Connection-Pool
+    pub fn with_client(
+        nomad_url: impl reqwest::IntoUrl,
+        client: reqwest::Client,
+    ) -> Result<Self, ClientError> {
+        Self::build(nomad_url, client, Registry::new())
+    }
+
+    fn build(
+        nomad_url: impl reqwest::IntoUrl,
+        client: reqwest::Client,
+        reg: Registry,
+    ) -> Result<Self, ClientError> {
+        let nomad_url = nomad_url.into_url().map_err(ClientError::Url)?;
+        let general_metrics = metrics::Metrics::new(&reg).map_err(ClientError::Metrics)?;
+        let nomad_metrics =
+            metrics::NomadRequestMetrics::new(&reg).map_err(ClientError::Metrics)?;
+        let allocation_metrics =
+            metrics::AllocationMetrics::new(&reg).map_err(ClientError::Metrics)?;
 
-        Self {
-            client: reqwest::Client::builder().build().unwrap(),
-            nomad_url: nomad_url.into_url().unwrap(),
+        Ok(Self {
+            client,
+            nomad_url,
+            nomad_token: None,
             registry: reg,
+            config: Config::default(),
+            tag_cache: docker::TagCache::default().with_ttl(Config::default().tag_cache_ttl),
             general: general_metrics,
+            nomad_metrics,
+            allocation_metrics,
+            status: RwLock::new(Vec::new()),
+            state: Mutex::new(state::CycleState::default()),
+            notifier: None,
+            last_check: RwLock::new(Instant::now()),
+            fingerprint: RwLock::new(state::fingerprint([])),
+            found_jobs: RwLock::new(true),
+            ready: RwLock::new(false),
+            trigger: tokio::sync::Notify::new(),
+            paused: RwLock::new(None),
+        })
+    }
+
+    /// Applies the Configuration, a configured Webhook only starts sending Notifications once
+    /// [`run`](Self::run) is called
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.notifier = config
+            .webhook
+            .clone()
+            .map(|conf| webhook::Notifier::new(self.client.clone(), conf));
+        self.tag_cache = docker::TagCache::default().with_ttl(config.tag_cache_ttl);
+        self.general
+            .set_registry_credentials(config.registry_credentials.keys());
+        self.config = config;
+        self
+    }
+
+    /// Waits the Interval between Checks instead of the configured one, has to be called after
+    /// [`with_config`](Self::with_config) as that replaces it again
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.config.check_interval = interval;
+        self
+    }
+
+    /// Authenticates all Requests to Nomad, including the Event-Stream, using the ACL Token
+    pub fn with_nomad_token(mut self, token: impl Into<String>) -> Self {
+        self.nomad_token = Some(token.into());
+        self
+    }
+
+    /// Sends the pending Webhook Notifications before shutting down
+    pub async fn shutdown(&self) {
+        if let Some(notifier) = self.notifier.as_ref() {
+            notifier.drain().await;
         }
     }
 
+    /// Encodes the Metrics, panicking if they can not be encoded
     pub fn get_metrics(&self) -> String {
+        self.encode_metrics().expect("Encoding the Metrics")
+    }
+
+    /// Encodes the Metrics in the Text-Format, which fails for Metrics of custom Collectors in
+    /// the Registry that are not valid
+    pub fn encode_metrics(&self) -> Result<String, prometheus::Error> {
         let mut buffer = vec![];
         let encoder = TextEncoder::new();
-        let metric_families = self.registry.gather();
-        encoder.encode(&metric_families, &mut buffer).unwrap();
+        let mut metric_families = self.registry.gather();
+        metrics::add_const_labels(&mut metric_families, &self.config.const_labels);
+        encoder.encode(&metric_families, &mut buffer)?;
+
+        Ok(String::from_utf8(buffer).expect("The Text-Format is UTF-8"))
+    }
+
+    /// Returns the Status of all the Tasks from the last Check
+    pub fn status(&self) -> Vec<TaskStatus> {
+        self.status.read().unwrap().clone()
+    }
+
+    /// Clears the cached Tag-Lists so the next Check loads everything fresh from the Registries,
+    /// Registry Tokens are not cached and therefore always fresh
+    pub fn clear_cache(&self) -> CacheCleared {
+        let cleared = CacheCleared {
+            tags: self.tag_cache.clear(),
+        };
+        tracing::info!("Cleared Caches: {:?}", cleared);
+
+        cleared
+    }
 
-        String::from_utf8(buffer).unwrap()
+    /// Returns the Details of the Task in every Group of the Job from the last Check
+    pub fn task_detail(&self, job: &str, task: &str) -> Vec<TaskDetail> {
+        let state = self.state.lock().unwrap();
+
+        self.status
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|status| status.job == job && status.task == task)
+            .map(|status| {
+                let key = state::TaskKey {
+                    namespace: status.namespace.clone(),
+                    job: status.job.clone(),
+                    group: status.group.clone(),
+                    task: status.task.clone(),
+                };
+
+                TaskDetail {
+                    status: status.clone(),
+                    newer: state.newer().get(&key).cloned().unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns a stable Hash over the Namespaces, Jobs, Groups, Tasks and Images monitored in the last Check,
+    /// which changes whenever the monitored Tasks change
+    pub fn fingerprint(&self) -> String {
+        self.fingerprint.read().unwrap().clone()
+    }
+
+    /// Starts the next Check right away instead of waiting for the Interval, like after
+    /// deploying a new Job. Triggers while a Check is running are coalesced into a single Check
+    /// that starts once the running one completed
+    pub fn trigger_check(&self) {
+        self.trigger.notify_one();
+    }
+
+    /// Pauses the Checks, keeping the current Metrics as they are, until they are resumed or,
+    /// if given, the Duration passed
+    pub fn pause(&self, resume_after: Option<Duration>) {
+        let until = resume_after.map(|d| Instant::now() + d);
+        tracing::info!("Pausing Checks for {:?}", resume_after);
+
+        *self.paused.write().unwrap() = Some(Pause { until });
+        self.general.set_paused(true);
+    }
+
+    /// Resumes the Checks after a Pause
+    pub fn resume(&self) {
+        tracing::info!("Resuming Checks");
+
+        *self.paused.write().unwrap() = None;
+        self.general.set_paused(false);
+    }
+
+    /// Whether the Checks are currently paused, resuming them if their Pause is over
+    pub fn is_paused(&self) -> bool {
+        let pause = *self.paused.read().unwrap();
+        match pause {
+            Some(Pause { until: Some(until) }) if until <= Instant::now() => {
+                self.resume();
+                false
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// Whether the first Check completed successfully, so the Metrics reflect the Cluster
+    pub fn is_ready(&self) -> bool {
+        *self.ready.read().unwrap()
+    }
+
+    /// Whether the last completed Check is older than the configured freshness budget, which
+    /// is never the case while the Checks are paused
+    pub fn is_stale(&self) -> bool {
+        if self.is_paused() {
+            return false;
+        }
+
+        match self.config.freshness_budget {
+            Some(budget) => self.last_check.read().unwrap().elapsed() > budget,
+            None => false,
+        }
+    }
+
+    /// How long to wait before the next Check, which is shorter while Nomad lists no Jobs, like
+    /// when the Cluster is still coming up, to pick up the first Jobs quickly
+    fn next_interval(&self) -> Duration {
+        if *self.found_jobs.read().unwrap() {
+            self.config.check_interval
+        } else {
+            self.config.empty_retry_interval
+        }
     }
 
     pub async fn run(self: Arc<Self>) {
-        let sleep_time = Duration::from_secs(15 * 60);
-        let event = eventstream::EventStream::new(self.client.clone(), self.nomad_url.clone());
+        if let Some(notifier) = self.notifier.as_ref() {
+            notifier.start();
+        }
+
+        let mut event = eventstream::EventStream::new(
+            self.client.clone(),
+            self.nomad_url.clone(),
+            self.nomad_metrics.clone(),
+            self.config.namespace.clone(),
+        );
+        if let Some(token) = self.nomad_token.clone() {
+            event = event.with_token(token);
+        }
+        if self.config.track_allocations {
+            event = event.with_allocations(allocations::AllocationTracker::new(
+                self.allocation_metrics.clone(),
+            ));
+        }
         let (event_runner, notify) = event.run();
         tokio::spawn(event_runner);
 
         loop {
-            self.check().await;
+            self.cycle().await;
+
+            tokio::select! {
+                _ = tokio::time::sleep(self.next_interval()) => {}
+                _ = self.trigger.notified() => {}
+                _ = notify.notified() => {
+                    wait_quiet(&notify, self.config.event_debounce, self.next_interval()).await;
+                }
+            }
+        }
+    }
+
+    /// Runs a single Check and publishes its results
+    async fn cycle(&self) {
+        if self.is_paused() {
+            tracing::info!("Skipping Check while paused");
+            return;
+        }
+
+        if let Err(e) = self.check().await {
+            tracing::error!("Running Check: {}", e);
+        }
+
+        if let Some(releases_url) = self.config.nomad_releases_url.as_ref() {
+            self.check_nomad_version(releases_url).await;
+        }
+
+        if let Some(conf) = self.config.pushgateway.as_ref() {
+            if let Err(e) = self.push_metrics(conf).await {
+                tracing::error!("Pushing Metrics: {}", e);
+                self.general.push_failed();
+            }
+        }
+    }
+
+    /// Compares the Version of the Nomad Agent with the newest stable Nomad Release
+    async fn check_nomad_version(&self, releases_url: &reqwest::Url) {
+        let current = match nomad::agent_version(
+            &self.client,
+            &self.nomad_url,
+            self.nomad_token.as_deref(),
+            &self.nomad_metrics,
+        )
+        .await
+        {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::error!("Loading Nomad Version: {}", e);
+                return;
+            }
+        };
+        let releases = match nomad::list_releases(&self.client, releases_url).await {
+            Ok(r) => r,
+            Err(e) => {
+                tracing::error!("Loading Nomad Releases: {}", e);
+                return;
+            }
+        };
+
+        let current_version = match RawTag::new(&current).parse_version() {
+            Ok(v) => v,
+            Err(_) => {
+                tracing::error!("Parsing Nomad Version: {:?}", current);
+                return;
+            }
+        };
+        let filter = select::CandidateFilter::default().with_ordering(self.config.version_ordering);
+        let newest = match filter.newest(&releases) {
+            Some(n) => n,
+            None => {
+                tracing::warn!("No stable Nomad Release found");
+                return;
+            }
+        };
 
-            let _ = tokio::time::timeout(sleep_time, notify.notified()).await;
+        let out_of_date =
+            filter.ordering.compare(&newest, &current_version) == std::cmp::Ordering::Greater;
+        self.general
+            .update_nomad(&current, &format!("{newest}"), out_of_date);
+    }
+
+    async fn push_metrics(&self, conf: &PushgatewayConfig) -> Result<(), PushError> {
+        let body = self.get_metrics().into_bytes();
+
+        pushgateway::push(&self.client, conf, prometheus::TEXT_FORMAT, body).await
+    }
+
+    /// Lists all the Repositories of a Registry, independent of what is deployed, together
+    /// with the newest Version available for each of them
+    #[tracing::instrument(skip(self))]
+    pub async fn registry_inventory(
+        &self,
+        registry_url: &reqwest::Url,
+    ) -> Result<Vec<RepositoryVersion>, GetTagsError> {
+        let credentials = self.config.credentials_for(registry_url.authority());
+        let repositories = docker::list_repositories(
+            &self.client,
+            registry_url,
+            self.config.rate_limit_retries,
+            credentials,
+        )
+        .await?;
+
+        let mut result = Vec::with_capacity(repositories.len());
+        for repository in repositories {
+            let newest = match docker::list_tags(
+                &self.client,
+                &self.tag_cache,
+                registry_url,
+                &repository,
+                self.config.max_tag_pages,
+                self.config.rate_limit_retries,
+                credentials,
+            )
+            .await
+            {
+                Ok(tags) => select::CandidateFilter::default()
+                    .newest(&tags)
+                    .map(|v| format!("{v}")),
+                Err(e) => {
+                    tracing::error!("Listing Tags for {:?}: {:?}", repository, e);
+                    None
+                }
+            };
+
+            result.push(RepositoryVersion { repository, newest });
         }
+
+        Ok(result)
     }
 
     #[tracing::instrument(skip(self))]
-    async fn check(&self) {
+    async fn check(&self) -> Result<(), CheckError> {
+        let start = Instant::now();
         tracing::info!("Running Check");
         tracing::info!("Loading Tasks...");
-        let raw_task_list = match nomad::list_jobs(&self.client, &self.nomad_url).await {
-            Ok(t) => t,
-            Err(e) => {
-                tracing::error!("Loading List: {:?}", e);
-                return;
+        let raw_task_list = nomad::list_jobs(
+            &self.client,
+            &self.nomad_url,
+            self.config.namespace.as_deref(),
+            self.nomad_token.as_deref(),
+            &self.nomad_metrics,
+        )
+        .await
+        .inspect_err(|_| self.general.check_failed("list_jobs"))
+        .map_err(CheckError::ListJobs)?;
+
+        *self.found_jobs.write().unwrap() = !raw_task_list.is_empty();
+
+        tracing::info!("Processing Jobs...");
+
+        // The Tasks of a Job are already checked while the remaining Jobs are still being read
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        let checks = receiver.map(|task: PendingTask| {
+            let host = task
+                .config
+                .image_reference()
+                .and_then(|image| docker::Image::parse(image).ok())
+                .map(|i| i.registry.to_string())
+                .unwrap_or_default();
+
+            let check = async move {
+                let outcome = self
+                    .check_task(&task.filter, task.upstream, task.config)
+                    .await;
+                (task.key, task.job_version, outcome)
+            };
+            (host, check)
+        });
+        let (read, outcomes) = futures::join!(
+            self.read_tasks(raw_task_list, sender),
+            schedule::fair_buffered(checks, self.config.check_concurrency.max(1))
+                .collect::<Vec<_>>()
+        );
+        let ReadTasks {
+            existing,
+            repositories,
+            owners,
+            failures: read_failures,
+            fingerprint,
+        } = read;
+
+        let mut digests = Vec::new();
+        let mut mirrors = Vec::new();
+        let mut candidates = Vec::new();
+        let mut newer = Vec::new();
+        let mut compared = Vec::new();
+        // Tasks sharing a Repository see the same Tags, so every Repository is recorded once
+        let mut registry_newest = BTreeMap::new();
+        let mut unreachable = 0;
+        let mut current_missing = BTreeSet::new();
+        let mut latest = Vec::new();
+        let mut updates = Vec::with_capacity(outcomes.len());
+        for (key, job_version, outcome) in outcomes {
+            if outcome.unreachable {
+                unreachable += 1;
+            }
+            if outcome.current_missing {
+                current_missing.insert(key.clone());
+            }
+            if let Some((tag, digest)) = outcome.digest {
+                digests.push((key.clone(), tag, digest));
+            }
+            if let Some(digest) = outcome.latest_digest {
+                latest.push((key.clone(), job_version, digest));
+            }
+            mirrors.push((key.clone(), outcome.mirror));
+            candidates.push((key.clone(), outcome.candidates));
+            newer.push((key.clone(), outcome.newer));
+            compared.push((key.clone(), outcome.compared));
+            registry_newest.extend(outcome.registry_newest);
+            updates.push((key, outcome.version));
+        }
+        self.general.set_monitored_tasks(updates.len());
+
+        tracing::info!("Updating Metrics...");
+
+        let mut state = self.state.lock().unwrap();
+        for (key, job_version, digest) in latest {
+            let version = state.compare_latest(key.clone(), job_version, digest);
+            if let Some((_, update)) = updates.iter_mut().find(|(k, _)| *k == key) {
+                *update = Some(version);
+            }
+        }
+        let updates = state.debounce(updates, self.config.out_of_date_cycles);
+        if let Some(notifier) = self.notifier.as_ref() {
+            for (key, version) in updates.iter() {
+                let (current, newest) = match version {
+                    Some(metrics::UpdatedVersion::OutOfDate {
+                        current, newest, ..
+                    }) => (current, newest),
+                    _ => continue,
+                };
+                if state.results().get(key) == version.as_ref() {
+                    continue;
+                }
+
+                notifier.notify(webhook::Notification {
+                    job: key.job.clone(),
+                    group: key.group.clone(),
+                    task: key.task.clone(),
+                    current: current.clone(),
+                    newest: newest.clone(),
+                });
+            }
+        }
+        state.merge(&existing, updates);
+        for (key, tag, digest) in digests {
+            if state.record_digest(key.clone(), tag, digest) {
+                tracing::warn!("Digest of deployed Tag changed for {:?}", key);
+            }
+        }
+
+        for (key, status) in mirrors {
+            state.record_mirror(key, status);
+        }
+        for (key, counts) in candidates {
+            state.record_candidates(key, counts);
+        }
+        for (key, versions) in newer {
+            state.record_newer(key, versions);
+        }
+        for (key, versions) in compared {
+            state.record_compared(key, versions);
+        }
+        state.retain_repositories(&repositories);
+        for (repository, version) in registry_newest {
+            let previous = state
+                .registry_newest()
+                .get(&repository)
+                .map(|r| format!("{}", r.version));
+            let current = format!("{version}");
+            if state.record_registry_newest(
+                repository.clone(),
+                version,
+                self.config.version_ordering,
+            ) {
+                tracing::warn!(
+                    "Newest Version of {:?} went back from {} to {}",
+                    repository,
+                    previous.unwrap_or_default(),
+                    current
+                );
             }
+        }
+
+        let statuses = state
+            .results()
+            .iter()
+            .map(|(key, version)| TaskStatus::new(key, version))
+            .collect();
+        *self.status.write().unwrap() = statuses;
+
+        self.general.clear();
+        self.general.set_read_failures(&read_failures);
+        state.publish(&self.general, &owners);
+        let mut urgency_total = 0.0;
+        for (key, version) in state.results() {
+            let owner = owners.get(key).map(String::as_str).unwrap_or_default();
+            let score = match (version, state.compared().get(key)) {
+                (metrics::UpdatedVersion::OutOfDate { newer, .. }, Some(compared)) => {
+                    metrics::urgency_score(&self.config.urgency_weights, compared, *newer)
+                }
+                _ => 0.0,
+            };
+            self.general.set_urgency(key, owner, score);
+            urgency_total += score;
+        }
+        self.general.set_urgency_total(urgency_total);
+        self.general
+            .set_needs_attention(metrics::count_needs_attention(
+                &self.config.needs_attention,
+                state.results(),
+                &current_missing,
+                unreachable,
+            ));
+        for (key, status) in state.mirrors() {
+            self.general.update_mirror(key, status);
+        }
+        for (key, counts) in state.candidates() {
+            self.general.update_candidates(key, counts);
+        }
+        for (repository, record) in state.registry_newest() {
+            self.general
+                .set_newest_regressed(repository, record.regressed);
+        }
+        for (key, record) in state.digests() {
+            self.general
+                .update_digest(key, &record.tag, &record.digest, record.changed);
+        }
+
+        self.general.set_fingerprint(&fingerprint);
+        *self.fingerprint.write().unwrap() = fingerprint;
+        *self.last_check.write().unwrap() = Instant::now();
+        self.general.set_last_check();
+        *self.ready.write().unwrap() = true;
+        self.general.observe_check(start.elapsed());
+
+        tracing::info!("Check Done");
+        Ok(())
+    }
+
+    /// Reads all the listed Jobs with a bounded number of concurrent Requests, in the order
+    /// they finish, skipping Jobs that have a parent unless they are aggregated under it. Jobs
+    /// that could not be read are returned as their ID
+    fn read_job_stream(
+        &self,
+        jobs: nomad::JobListResponse,
+    ) -> impl futures::Stream<Item = Result<nomad::ReadJobResponse, String>> + '_ {
+        let jobs = if self.config.aggregate_dispatched {
+            nomad::collapse_dispatched(jobs)
+        } else {
+            jobs
         };
 
-        let tasks = {
-            let mut tmp = Vec::new();
-            for raw_task in raw_task_list {
-                let task = match nomad::read_job(&self.client, &self.nomad_url, &raw_task.id).await
-                {
+        futures::stream::iter(jobs)
+            .map(move |entry| async move {
+                // Jobs listed from all Namespaces have to be read from their own
+                let namespace = entry
+                    .namespace
+                    .clone()
+                    .or_else(|| self.config.namespace.clone().filter(|n| n != "*"));
+                let id = match entry.into_job() {
+                    Ok(job) => return Ok(job),
+                    Err(id) => id,
+                };
+
+                nomad::read_job(
+                    &self.client,
+                    &self.nomad_url,
+                    &id,
+                    namespace.as_deref(),
+                    self.nomad_token.as_deref(),
+                    &self.nomad_metrics,
+                )
+                .await
+                .map_err(|e| {
+                    tracing::error!("Reading Job {:?} from Nomad: {}", id, e);
+                    self.general.check_failed("read_job");
+                    id
+                })
+            })
+            .buffer_unordered(self.config.job_read_concurrency.max(1))
+            .filter_map(move |task| async move {
+                let mut task = match task {
                     Ok(t) => t,
-                    Err(e) => {
-                        tracing::error!("Reading Job from Nomad");
-                        continue;
-                    }
+                    Err(id) => return Some(Err(id)),
                 };
 
+                if self.config.skip_dispatch_parents && task.dispatches() {
+                    tracing::debug!("Skipping periodic or parameterized Job - {:?}", task.name);
+                    return None;
+                }
+
                 if !task.parent_id.is_empty() {
-                    tracing::warn!("Skipping Job that has ParentID - {:?}", task.name);
+                    if !self.config.aggregate_dispatched {
+                        tracing::warn!("Skipping Job that has ParentID - {:?}", task.name);
+                        return None;
+                    }
+
+                    task.name = task.parent_id.clone();
+                }
+
+                Some(Ok(task))
+            })
+    }
+
+    /// Reads the listed Jobs and sends every Task to be checked as soon as its Job was read, or,
+    /// if the Tasks per Check are limited, once all of them are known so the same Window can be
+    /// selected in every Check
+    async fn read_tasks(
+        &self,
+        jobs: nomad::JobListResponse,
+        sender: futures::channel::mpsc::UnboundedSender<PendingTask>,
+    ) -> ReadTasks {
+        let mut read = ReadTasks::default();
+        let mut names = BTreeSet::new();
+        let mut images = Vec::new();
+        let mut windowed = Vec::new();
+
+        let mut jobs = std::pin::pin!(self.read_job_stream(jobs));
+        while let Some(job) = jobs.next().await {
+            let job = match job {
+                Ok(j) => j,
+                Err(id) => {
+                    read.failures.push(id);
                     continue;
                 }
+            };
 
-                tmp.push(task);
+            // Parents and the Children aggregated under them are already collapsed into a
+            // single Job, this only guards against checking the same Tasks twice
+            if self.config.aggregate_dispatched && !names.insert(job.name.clone()) {
+                continue;
             }
-            tmp
-        };
 
-        tracing::info!("Processing Jobs...");
+            let owner = select::owner_from_meta(
+                job.meta.as_ref(),
+                &job.namespace,
+                &job.name,
+                &self.config.owners,
+            );
 
-        let job_task_iter = tasks.into_iter().flat_map(|job| {
-            job.task_groups.into_iter().flat_map(move |jgroup| {
-                let j_name = job.name.clone();
-                let g_name = jgroup.name.clone();
-                jgroup
-                    .tasks
-                    .into_iter()
-                    .map(move |task| (j_name.clone(), g_name.clone(), task))
+            for task in self.job_tasks(job) {
+                if let Some(owner) = owner.as_ref() {
+                    read.owners.insert(task.key.clone(), owner.clone());
+                }
+                let image = task.config.image_reference();
+                if let Some(image) = image.as_ref() {
+                    if let Ok(image) = docker::Image::parse(image.clone()) {
+                        read.repositories.insert(image.name_reference());
+                    }
+                }
+                read.existing.insert(task.key.clone());
+                images.push((task.key.clone(), image.unwrap_or_default()));
+
+                if self.config.max_tasks_per_cycle.is_some() {
+                    windowed.push(task);
+                } else {
+                    let _ = sender.unbounded_send(task);
+                }
+            }
+        }
+
+        read.failures.sort();
+        read.fingerprint =
+            state::fingerprint(images.iter().map(|(key, image)| (key, image.as_str())));
+
+        if self.config.max_tasks_per_cycle.is_some() {
+            // Keep the order stable between Checks, regardless of which Job was read first
+            windowed.sort_by(|a, b| a.key.cmp(&b.key));
+            let window = self
+                .state
+                .lock()
+                .unwrap()
+                .next_window(windowed.len(), self.config.max_tasks_per_cycle);
+            if window.len() < windowed.len() {
+                tracing::info!(
+                    "Processing {} out of {} Tasks in this Check",
+                    window.len(),
+                    windowed.len()
+                );
+            }
+
+            for (_, task) in windowed
+                .into_iter()
+                .enumerate()
+                .filter(|(idx, _)| window.contains(idx))
+            {
+                let _ = sender.unbounded_send(task);
+            }
+        }
+
+        read
+    }
+
+    /// Splits a Job into its Tasks, together with the Filter configured for them
+    fn job_tasks(&self, job: nomad::ReadJobResponse) -> Vec<PendingTask> {
+        let filter = select::CandidateFilter::from_meta(job.meta.as_ref())
+            .with_channels(select::ChannelOrder::new(self.config.channel_order.clone()))
+            .with_ordering(self.config.version_ordering)
+            .with_ignore_case(self.config.ignore_tag_case)
+            .with_ignore_describe_suffix(self.config.ignore_describe_suffix)
+            .with_update_track(self.config.update_tracks.get(&job.name).copied());
+        let upstream = select::upstream_from_meta(job.meta.as_ref());
+
+        job.task_groups
+            .into_iter()
+            .flat_map(|jgroup| {
+                let filter = &filter;
+                let upstream = &upstream;
+                let namespace = &job.namespace;
+                let job_version = job.version;
+                let job = &job.name;
+                jgroup.tasks.into_iter().map(move |task| PendingTask {
+                    key: state::TaskKey {
+                        namespace: namespace.clone(),
+                        job: job.clone(),
+                        group: jgroup.name.clone(),
+                        task: task.name,
+                    },
+                    job_version,
+                    filter: filter.clone(),
+                    upstream: upstream.clone(),
+                    config: task.config,
+                })
             })
-        });
+            .collect()
+    }
 
-        let updates = {
-            let mut tmp = Vec::new();
-
-            for (jname, gname, task) in job_task_iter {
-                let get_version = move || async {
-                    match task.config {
-                        nomad::ReadJobConfig::Docker { image: raw_image } => {
-                            let image = match docker::Image::parse(raw_image) {
-                                Ok(i) => i,
-                                Err(raw) => {
-                                    tracing::warn!("Could not parse Image: {:?}", raw);
-                                    return None;
-                                }
-                            };
-
-                            let image_version = match image.tag.parse_version() {
-                                Ok(v) => v,
-                                Err(e) => {
-                                    tracing::error!(
-                                        "Parsing Image ({}) Version: {:?}",
-                                        image.name,
-                                        image.tag
-                                    );
-
-                                    return None;
-                                }
-                            };
-
-                            if docker::Version::Latest == image_version {
-                                tracing::warn!("Skipping Image check as its already latest");
-                                return Some(metrics::UpdatedVersion::UpToDate {
-                                    version: format!("{image_version}"),
-                                });
-                            }
-
-                            let tags = match docker::get_tags(&self.client, &image).await {
-                                Ok(t) => t,
-                                Err(e) => {
-                                    tracing::error!("Getting Tags for '{:?}': {:?}", image, e);
-                                    return None;
-                                }
-                            };
-
-                            let latest_version = match tags
-                                .iter()
-                                .filter_map(|tag| {
-                                    let raw_tag = docker::RawTag::new(tag);
-                                    raw_tag.parse_version().ok()
-                                })
-                                .filter(|v| v.fully_qualified())
-                                .max()
-                            {
-                                Some(v) => v,
-                                None => {
-                                    return None;
-                                }
-                            };
-
-                            if latest_version > image_version {
-                                Some(metrics::UpdatedVersion::OutOfDate {
-                                    current: format!("{image_version}"),
-                                    newest: format!("{latest_version}"),
-                                })
-                            } else {
-                                Some(metrics::UpdatedVersion::UpToDate {
-                                    version: format!("{image_version}"),
-                                })
-                            }
-                        }
-                        nomad::ReadJobConfig::RawExec {} => {
-                            tracing::warn!("Not implemented for Raw-Exec");
-
-                            None
-                        }
-                    }
+    /// Checks a single Task
+    async fn check_task(
+        &self,
+        filter: &select::CandidateFilter,
+        upstream: Option<String>,
+        config: nomad::ReadJobConfig,
+    ) -> TaskOutcome {
+        let raw_image = match config.image_reference() {
+            Some(image) => image,
+            None => {
+                let version = match self.config.raw_exec {
+                    RawExecBehaviour::Skip => None,
+                    RawExecBehaviour::NotTrackable => Some(metrics::UpdatedVersion::NotTrackable),
                 };
 
-                let result = match get_version().await {
-                    Some(r) => r,
-                    None => continue,
+                return TaskOutcome {
+                    version,
+                    ..Default::default()
                 };
-
-                tmp.push((jname, gname, task.name, result));
             }
+        };
 
-            tmp
+        let (mirror, mirror_tags) = match upstream {
+            Some(upstream) => self.check_mirror(&raw_image, &upstream, filter).await,
+            None => (None, None),
         };
 
-        tracing::info!("Updating Metrics...");
+        let image = self
+            .check_docker_image(raw_image.clone(), filter, mirror_tags)
+            .await;
 
-        self.general.clear();
-        for (job_name, group_name, task_name, version) in updates {
-            self.general
-                .update(&job_name, &group_name, &task_name, version);
+        // Nomad does not know the Digest of Images using the plain `latest` Tag, so the Digest
+        // `latest` resolves to is compared across Checks instead
+        let resolved = if self.config.resolve_digests || image.latest {
+            self.resolve_digest(&raw_image).await
+        } else {
+            None
+        };
+        let latest_digest = resolved
+            .as_ref()
+            .filter(|_| image.latest)
+            .map(|(_, digest)| digest.clone());
+        let digest = resolved.filter(|_| self.config.resolve_digests);
+
+        TaskOutcome {
+            unreachable: image.unreachable,
+            current_missing: image.current_missing,
+            version: image.version,
+            digest,
+            latest_digest,
+            mirror,
+            candidates: image.candidates,
+            newer: image.newer,
+            compared: image.compared,
+            registry_newest: image.registry_newest,
         }
+    }
 
-        tracing::info!("Check Done");
+    /// Resolves the Digest the deployed Tag of the Image currently points to
+    async fn resolve_digest(&self, raw_image: &str) -> Option<(String, String)> {
+        let image = docker::Image::parse(raw_image.to_string()).ok()?;
+        if image.digest.is_some() {
+            return None;
+        }
+
+        let registry_url = match image.registry_url(&self.config.insecure_registries) {
+            Ok(u) => u,
+            Err(e) => {
+                tracing::error!("Invalid Registry of '{:?}': {}", image, e);
+                return None;
+            }
+        };
+
+        let tag = image.tag.as_str();
+        match docker::get_digest(
+            &self.client,
+            &registry_url,
+            &image.repository(),
+            tag,
+            self.config.rate_limit_retries,
+            self.config.credentials_for(&image.registry),
+        )
+        .await
+        {
+            Ok(digest) => digest.map(|d| (tag.to_string(), d)),
+            Err(e) => {
+                tracing::error!("Resolving Digest for '{:?}': {}", image, e);
+                None
+            }
+        }
+    }
+
+    /// Compares the deployed Digest of an Image using the `latest` Tag with the Digest `latest`
+    /// currently points to in its Registry, as there is no Version to compare
+    async fn check_latest_digest(&self, image: &docker::Image, deployed: &str) -> ImageOutcome {
+        let registry_url = match image.registry_url(&self.config.insecure_registries) {
+            Ok(u) => u,
+            Err(e) => {
+                tracing::error!("Invalid Registry of '{:?}': {}", image, e);
+                return ImageOutcome::default();
+            }
+        };
+
+        let tag = image.tag.as_str();
+        let newest = match docker::get_digest(
+            &self.client,
+            &registry_url,
+            &image.repository(),
+            tag,
+            self.config.rate_limit_retries,
+            self.config.credentials_for(&image.registry),
+        )
+        .await
+        {
+            Ok(Some(d)) => d,
+            Ok(None) => {
+                tracing::error!("Registry returned no Digest for '{:?}'", image);
+                return ImageOutcome::default();
+            }
+            Err(e) => {
+                tracing::error!("Resolving Digest for '{:?}': {}", image, e);
+                return ImageOutcome {
+                    unreachable: true,
+                    ..Default::default()
+                };
+            }
+        };
+
+        let version = if newest == deployed {
+            metrics::UpdatedVersion::UpToDate {
+                version: tag.to_string(),
+            }
+        } else {
+            metrics::UpdatedVersion::OutOfDate {
+                current: format!("{tag}@{deployed}"),
+                newest: format!("{tag}@{newest}"),
+                newer: 1,
+            }
+        };
+
+        ImageOutcome {
+            version: Some(version),
+            ..Default::default()
+        }
+    }
+
+    /// Compares the Tags available in the Mirror the Image is deployed from with the Tags of
+    /// its Upstream. The Tags of the Mirror are returned as well, so they are not fetched again
+    /// to check the deployed Image
+    async fn check_mirror(
+        &self,
+        raw_image: &str,
+        raw_upstream: &str,
+        filter: &select::CandidateFilter,
+    ) -> (Option<select::MirrorStatus>, Option<Vec<String>>) {
+        let (mirror, upstream) = match (
+            docker::Image::parse(raw_image.to_string()),
+            docker::Image::parse(raw_upstream.to_string()),
+        ) {
+            (Ok(m), Ok(u)) => (m, u),
+            (Err(raw), _) | (_, Err(raw)) => {
+                tracing::warn!("Could not parse Image: {:?}", raw);
+                return (None, None);
+            }
+        };
+
+        let mirror_tags = docker::get_tags(
+            &self.client,
+            &self.tag_cache,
+            &mirror,
+            &self.config.insecure_registries,
+            self.config.max_tag_pages,
+            self.config.rate_limit_retries,
+            self.config.credentials_for(&mirror.registry),
+        )
+        .await;
+        let upstream_tags = docker::get_tags(
+            &self.client,
+            &self.tag_cache,
+            &upstream,
+            &self.config.insecure_registries,
+            self.config.max_tag_pages,
+            self.config.rate_limit_retries,
+            self.config.credentials_for(&upstream.registry),
+        )
+        .await;
+        let status = match (&mirror_tags, &upstream_tags) {
+            (Ok(m), Ok(u)) => Some(select::MirrorStatus::compare(m, u, filter)),
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::error!("Getting Tags for Mirror '{:?}': {}", mirror, e);
+                self.general.check_failed("get_tags");
+                None
+            }
+        };
+
+        (status, mirror_tags.ok())
+    }
+
+    async fn check_docker_image(
+        &self,
+        raw_image: String,
+        filter: &select::CandidateFilter,
+        mirror_tags: Option<Vec<String>>,
+    ) -> ImageOutcome {
+        let image = match docker::Image::parse(raw_image) {
+            Ok(i) => i,
+            // Images using Variables, like `${VERSION}`, are only resolved by Nomad itself
+            Err(raw) if raw.contains('$') => {
+                tracing::warn!("Skipping templated Image: {:?}", raw);
+                self.general.task_skipped("templated_image");
+                return ImageOutcome::default();
+            }
+            Err(raw) => {
+                tracing::warn!("Could not parse Image: {:?}", raw);
+                self.general.check_failed("parse_image");
+                self.general.task_skipped("image_parse");
+                return ImageOutcome::default();
+            }
+        };
+
+        if image.tag.matches_any(&self.config.ignored_tags) {
+            tracing::debug!("Skipping Image with an ignored Tag: {:?}", image.tag);
+            self.general.task_skipped("ignored_tag");
+            return ImageOutcome::default();
+        }
+
+        if image.tag.is_one_of(&self.config.floating_tags) {
+            tracing::warn!("Image uses a floating Tag: {:?}", image.tag);
+            let version = metrics::UpdatedVersion::Floating {
+                tag: image.tag.as_str().to_string(),
+            };
+            return ImageOutcome {
+                version: Some(version),
+                ..Default::default()
+            };
+        }
+
+        let deployed = match (filter.parse(&image.tag), &image.digest) {
+            (Ok(docker::Version::Latest), Some(digest)) if filter.track.is_none() => {
+                return self.check_latest_digest(&image, digest).await;
+            }
+            (Ok(docker::Version::Latest) | Err(_), Some(digest)) => {
+                if filter.track.is_none() {
+                    tracing::warn!(
+                        "Skipping Image ({}) pinned by Digest without a tracked Tag line",
+                        image.name
+                    );
+                    return ImageOutcome::default();
+                }
+
+                select::Deployed::Digest(digest.clone())
+            }
+            (Ok(docker::Version::Latest), None) => {
+                tracing::debug!("Comparing the Digest of {} across Checks", image.name);
+                let version = metrics::UpdatedVersion::UpToDate {
+                    version: format!("{}", docker::Version::Latest),
+                };
+                return ImageOutcome {
+                    version: Some(version),
+                    latest: true,
+                    ..Default::default()
+                };
+            }
+            (Ok(v), _) => select::Deployed::Version(v),
+            (Err(_), None) if filter.channels.contains(image.tag.as_str()) => {
+                select::Deployed::Version(docker::Version::Named(image.tag.as_str().to_string()))
+            }
+            (Err(_), None) => {
+                tracing::error!("Parsing Image ({}) Version: {:?}", image.name, image.tag);
+                self.general.check_failed("parse_version");
+                self.general.task_skipped("version_parse");
+
+                return ImageOutcome::default();
+            }
+        };
+
+        // Only Tags of the same Variant, like `alpine`, as the deployed Version are compared
+        let deployed_version = match &deployed {
+            select::Deployed::Version(v) => Some(v),
+            select::Deployed::Digest(_) => None,
+        };
+        let approved_feed = self.config.approved_feeds.get(&image.name_reference());
+        let filter = &filter
+            .clone()
+            .with_variant(deployed_version.and_then(|v| v.variant()).map(String::from))
+            .with_deployed(deployed_version)
+            .with_approved(approved_feed.is_some());
+
+        // Images with an approved Feed are only compared against the approved Version instead
+        // of everything the Registry offers
+        let tags = match (approved_feed, mirror_tags) {
+            (Some(feed_url), _) => match feed::approved_version(&self.client, feed_url).await {
+                Ok(v) => vec![v],
+                Err(e) => {
+                    tracing::error!("Loading approved Version for '{:?}': {}", image, e);
+                    return ImageOutcome {
+                        unreachable: true,
+                        ..Default::default()
+                    };
+                }
+            },
+            // The Tags of a Mirror were already fetched to compare it with its Upstream
+            (None, Some(tags)) => tags,
+            (None, None) => match docker::get_tags(
+                &self.client,
+                &self.tag_cache,
+                &image,
+                &self.config.insecure_registries,
+                self.config.max_tag_pages,
+                self.config.rate_limit_retries,
+                self.config.credentials_for(&image.registry),
+            )
+            .await
+            {
+                Ok(t) => t,
+                Err(e) => {
+                    tracing::error!("Getting Tags for '{:?}': {:?}", image, e);
+                    self.general.check_failed("get_tags");
+                    return ImageOutcome {
+                        unreachable: true,
+                        ..Default::default()
+                    };
+                }
+            },
+        };
+
+        // The approved Version of a Feed is not a listing of the Registry, so it can't tell
+        // whether the deployed Tag still exists
+        let current_missing = approved_feed.is_none()
+            && matches!(deployed, select::Deployed::Version(_))
+            && !tags.iter().any(|t| t == image.tag.as_str());
+        if current_missing {
+            tracing::warn!(
+                "The Registry no longer lists the deployed Tag of {:?}",
+                image
+            );
+        }
+
+        let tags: Vec<_> = tags
+            .into_iter()
+            .filter(|t| !RawTag::new(t).matches_any(&self.config.ignored_tags))
+            .collect();
+
+        let candidates = select::CandidateCounts {
+            image: image.name_reference(),
+            before: tags.len(),
+            after: filter.count_candidates(&tags),
+        };
+        if candidates.after == 0 {
+            tracing::warn!(
+                "No Tag of {:?} is left as a Candidate after filtering {} Tags",
+                candidates.image,
+                candidates.before
+            );
+        }
+
+        // Only the Versions closest to the deployed one are kept, to bound the Details of Tasks
+        // that are far behind
+        let mut newer = match &deployed {
+            select::Deployed::Version(current) => filter.newer_versions(current, &tags),
+            select::Deployed::Digest(_) => Vec::new(),
+        };
+        newer.truncate(MAX_NEWER_VERSIONS);
+
+        // Only the Tags of the Registry itself show when its newest Version goes back, which is
+        // looked for in the Scheme of the deployed Version as calendar based Versions would
+        // otherwise always be newer than semantic ones
+        let registry_newest = if approved_feed.is_some() {
+            None
+        } else {
+            select::CandidateFilter::default()
+                .with_ordering(self.config.version_ordering)
+                .with_ignore_case(self.config.ignore_tag_case)
+                .with_deployed(deployed_version)
+                .newest_release(&tags)
+                .map(|v| (image.name_reference(), v))
+        };
+
+        let version = select::evaluate(&deployed, &tags, filter);
+        if version.is_none() {
+            self.general.task_skipped("no_valid_tags");
+        }
+
+        // The Urgency is scored on the parsed Versions instead of how they are displayed
+        let compared = match (&deployed, &version) {
+            (
+                select::Deployed::Version(current),
+                Some(metrics::UpdatedVersion::OutOfDate { .. }),
+            ) => {
+                let newest = match current {
+                    docker::Version::Named(_) => filter.channels.newest(&tags),
+                    _ => filter.newest(&tags),
+                };
+                newest.map(|newest| state::ComparedVersions {
+                    current: current.clone(),
+                    newest,
+                })
+            }
+            _ => None,
+        };
+
+        ImageOutcome {
+            version,
+            candidates: Some(candidates),
+            newer: newer.iter().map(|v| format!("{v}")).collect(),
+            compared,
+            registry_newest,
+            latest: false,
+            unreachable: false,
+            current_missing,
+        }
+    }
+}
+
+/// Waits until there was no Notification for the `quiet` period, so a burst of Events, like
+/// from a `nomad job run`, results in a single Check, but at most for `max`
+async fn wait_quiet(notify: &tokio::sync::Notify, quiet: Duration, max: Duration) {
+    let burst = async { while tokio::time::timeout(quiet, notify.notified()).await.is_ok() {} };
+    let _ = tokio::time::timeout(max, burst).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A self-signed CA Certificate only used for loading it
+    const TEST_CA_CERT: &str = "-----BEGIN CERTIFICATE-----\n\
+MIIBjjCCATOgAwIBAgIUEk6tFl8k5UUwak95mnQ4JbX/YwwwCgYIKoZIzj0EAwIw\n\
+GzEZMBcGA1UEAwwQdm1vbml0b3ItdGVzdC1jYTAgFw0yNjEwMTYxMDA4MzlaGA8y\n\
+MTI2MDkyMjEwMDgzOVowGzEZMBcGA1UEAwwQdm1vbml0b3ItdGVzdC1jYTBZMBMG\n\
+ByqGSM49AgEGCCqGSM49AwEHA0IABPgswvs1j9LeFjPvKJ+G2w17988Xcglorr8o\n\
+82hvksJeViUGaoKIBE3qnbWxmzHgXdovgPYNLJx9qGjr1iRNTkajUzBRMB0GA1Ud\n\
+DgQWBBQpGkJWPY9wdpZfeBF4oozc3x1vbjAfBgNVHSMEGDAWgBQpGkJWPY9wdpZf\n\
+eBF4oozc3x1vbjAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0kAMEYCIQDo\n\
+7brf7m5jmIFlj3dTd5d57KcPMLw+kQGRCwfAgMuX6AIhAJ22bYQDyGNvtlEn6tXF\n\
+RJzktCAiNwiNap7pkEiG6Hl0\n\
+-----END CERTIFICATE-----";
+
+    /// Lets Nomad list the Jobs and return each of them when it is read by its Name
+    async fn mount_jobs(server: &wiremock::MockServer, jobs: Vec<serde_json::Value>) {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, ResponseTemplate,
+        };
+
+        let list: Vec<_> = jobs
+            .iter()
+            .map(|job| serde_json::json!({ "ID": job["Name"] }))
+            .collect();
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(list))
+            .mount(server)
+            .await;
+        for job in jobs {
+            let name = job["Name"].as_str().expect("Jobs have a Name").to_string();
+            Mock::given(method("GET"))
+                .and(path(format!("/v1/job/{name}")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(job))
+                .mount(server)
+                .await;
+        }
+    }
+
+    #[test]
+    fn custom_ca_cert() {
+        let path = std::env::temp_dir().join(format!("vmonitor-ca-{}.pem", std::process::id()));
+        std::fs::write(&path, TEST_CA_CERT).unwrap();
+
+        let http = HttpConfig {
+            ca_cert: Some(path.clone()),
+            ..Default::default()
+        };
+        let client = Client::with_http("http://localhost:4646", &http);
+        std::fs::remove_file(&path).unwrap();
+        client.expect("Loading the CA Certificate");
+
+        let http = HttpConfig {
+            ca_cert: Some(path),
+            accept_invalid_certs: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Client::with_http("http://localhost:4646", &http),
+            Err(ClientError::ReadCertificate(_))
+        ));
+    }
+
+    #[test]
+    fn shared_registry() {
+        let reg = Registry::new();
+
+        Client::with_registry("http://localhost:4646", reg.clone()).expect("First Client works");
+
+        assert!(Client::with_registry("http://localhost:4646", reg).is_err());
+    }
+
+    #[test]
+    fn registry_credentials_configured() {
+        let config = Config {
+            registry_credentials: std::collections::HashMap::from([(
+                "GHCR_IO".to_string(),
+                RegistryCredentials::Token("secret".to_string()),
+            )]),
+            ..Default::default()
+        };
+        let client = Client::new("http://localhost:4646").with_config(config);
+
+        let metrics = client.get_metrics();
+        assert!(metrics.contains(r#"registry_credentials_configured{registry="GHCR_IO"} 1"#));
+        assert_eq!(
+            1,
+            metrics.matches("registry_credentials_configured{").count()
+        );
+        assert!(!metrics.contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn stale_after_budget() {
+        use wiremock::MockServer;
+
+        let server = MockServer::start().await;
+        mount_jobs(&server, Vec::new()).await;
+
+        let client = Client::new(server.uri()).with_config(Config {
+            freshness_budget: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+        assert!(!client.is_stale());
+
+        *client.last_check.write().unwrap() = Instant::now() - Duration::from_secs(120);
+        assert!(client.is_stale());
+
+        client.check().await.expect("Running Check");
+        assert!(!client.is_stale());
+
+        *client.last_check.write().unwrap() = Instant::now() - Duration::from_secs(120);
+        let unlimited = client.with_config(Config::default());
+        assert!(!unlimited.is_stale());
+    }
+
+    #[tokio::test]
+    async fn check_duration_recorded() {
+        use wiremock::MockServer;
+
+        let server = MockServer::start().await;
+        mount_jobs(&server, Vec::new()).await;
+
+        let client = Client::with_registry(server.uri(), Registry::new()).unwrap();
+        assert!(client
+            .get_metrics()
+            .contains("check_duration_seconds_count 0"));
+
+        client.check().await.expect("Running Check");
+
+        assert!(client
+            .get_metrics()
+            .contains("check_duration_seconds_count 1"));
+    }
+
+    #[tokio::test]
+    async fn injected_client() {
+        use wiremock::{
+            matchers::{header, method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .and(header("user-agent", "embedder/1.0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let http = reqwest::Client::builder()
+            .user_agent("embedder/1.0")
+            .build()
+            .unwrap();
+        let client = Client::with_client(server.uri(), http).expect("Creating Client");
+
+        client.check().await.expect("Running Check");
+
+        server.verify().await;
+        assert!(matches!(
+            Client::with_client("not a url", reqwest::Client::new()),
+            Err(ClientError::Url(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn registry_inventory() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/_catalog"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "repositories": ["team/app", "empty"] })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/team/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                serde_json::json!({ "name": "team/app", "tags": ["1.0.0", "1.2.0", "latest"] }),
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/empty/tags/list"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "name": "empty", "tags": null })),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Client::new("http://localhost:4646");
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        let inventory = client
+            .registry_inventory(&registry_url)
+            .await
+            .expect("Listing Inventory");
+
+        assert_eq!(
+            vec![
+                RepositoryVersion {
+                    repository: "team/app".to_string(),
+                    newest: Some("1.2.0".to_string()),
+                },
+                RepositoryVersion {
+                    repository: "empty".to_string(),
+                    newest: None,
+                },
+            ],
+            inventory
+        );
+    }
+
+    #[tokio::test]
+    async fn push_after_check() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let nomad = MockServer::start().await;
+        mount_jobs(&nomad, Vec::new()).await;
+
+        let gateway = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/metrics/job/vmonitor/instance/test"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&gateway)
+            .await;
+
+        let client = Client::new(nomad.uri()).with_config(Config {
+            pushgateway: Some(PushgatewayConfig {
+                url: reqwest::Url::parse(&gateway.uri()).unwrap(),
+                job: "vmonitor".to_string(),
+                grouping: vec![("instance".to_string(), "test".to_string())],
+            }),
+            ..Default::default()
+        });
+
+        client.cycle().await;
+
+        gateway.verify().await;
+        assert!(client
+            .get_metrics()
+            .contains("pushgateway_push_failures_total 0"));
+    }
+
+    #[tokio::test]
+    async fn push_failure_counted() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let nomad = MockServer::start().await;
+        mount_jobs(&nomad, Vec::new()).await;
+
+        let gateway = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&gateway)
+            .await;
+
+        let client = Client::new(nomad.uri()).with_config(Config {
+            pushgateway: Some(PushgatewayConfig {
+                url: reqwest::Url::parse(&gateway.uri()).unwrap(),
+                job: "vmonitor".to_string(),
+                grouping: Vec::new(),
+            }),
+            ..Default::default()
+        });
+
+        client.cycle().await;
+
+        assert!(client
+            .get_metrics()
+            .contains("pushgateway_push_failures_total 1"));
+    }
+
+    #[tokio::test]
+    async fn read_jobs_concurrently() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let delay = Duration::from_millis(300);
+        let server = MockServer::start().await;
+        for name in ["a", "b", "c"] {
+            Mock::given(method("GET"))
+                .and(path(format!("/v1/job/{name}")))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(serde_json::json!({
+                            "Name": name,
+                            "ParentID": "",
+                            "TaskGroups": [],
+                        }))
+                        .set_delay(delay),
+                )
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/v1/job/broken"))
+            .respond_with(ResponseTemplate::new(500).set_delay(delay))
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri()).with_config(Config {
+            job_read_concurrency: 4,
+            ..Default::default()
+        });
+        let jobs = ["c", "broken", "a", "b"]
+            .into_iter()
+            .map(|id| nomad::JobListEntry {
+                id: id.to_string(),
+                ..Default::default()
+            })
+            .collect();
+
+        let start = std::time::Instant::now();
+        let results: Vec<_> = client.read_job_stream(jobs).collect().await;
+        let elapsed = start.elapsed();
+
+        let (mut names, mut failures) = (Vec::new(), Vec::new());
+        for result in results {
+            match result {
+                Ok(job) => names.push(job.name),
+                Err(id) => failures.push(id),
+            }
+        }
+        names.sort();
+        assert_eq!(vec!["a", "b", "c"], names);
+        assert_eq!(vec!["broken"], failures);
+        assert!(elapsed < delay * 3, "Reading took {:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn latest_compared_by_digest() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        Mock::given(method("HEAD"))
+            .and(path("/v2/user/app/manifests/latest"))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("docker-content-digest", "sha256:new"),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host.clone()],
+                ..Default::default()
+            });
+        let filter = select::CandidateFilter::default();
+        let check = |digest: &str| {
+            client.check_task(
+                &filter,
+                None,
+                nomad::ReadJobConfig::Docker {
+                    image: format!("{host}/user/app"),
+                    tag: Some("latest".to_string()),
+                    digest: Some(digest.to_string()),
+                },
+            )
+        };
+
+        assert_eq!(
+            Some(metrics::UpdatedVersion::OutOfDate {
+                current: "latest@sha256:old".to_string(),
+                newest: "latest@sha256:new".to_string(),
+                newer: 1,
+            }),
+            check("sha256:old").await.version
+        );
+        assert_eq!(
+            Some(metrics::UpdatedVersion::UpToDate {
+                version: "latest".to_string(),
+            }),
+            check("sha256:new").await.version
+        );
+
+        // Multi-platform Images have to resolve to the Digest of their Manifest List
+        let requests = server.received_requests().await.unwrap();
+        let accept = requests[0].headers.get(&"accept".into()).unwrap();
+        assert!(accept
+            .iter()
+            .any(|v| v.as_str() == "application/vnd.docker.distribution.manifest.list.v2+json"));
+    }
+
+    #[tokio::test]
+    async fn plain_latest_compared_across_checks() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        let jobs = |version: u64| {
+            Mock::given(method("GET"))
+                .and(path("/v1/jobs"))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                        "ID": "web",
+                        "Name": "web",
+                        "ParentID": "",
+                        "Version": version,
+                        "TaskGroups": [{
+                            "Name": "group",
+                            "Tasks": [{
+                                "Name": "app",
+                                "Driver": "docker",
+                                "Config": { "image": format!("{host}/user/app:latest") },
+                            }],
+                        }],
+                    }])),
+                )
+        };
+        let manifest = |digest: &str| {
+            Mock::given(method("HEAD"))
+                .and(path("/v2/user/app/manifests/latest"))
+                .respond_with(
+                    ResponseTemplate::new(200).insert_header("docker-content-digest", digest),
+                )
+        };
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host.clone()],
+                ..Default::default()
+            });
+        let state = || client.status()[0].state;
+
+        let listed = jobs(1).mount_as_scoped(&server).await;
+        let pushed = manifest("sha256:aaa").mount_as_scoped(&server).await;
+        client.check().await.expect("Running Check");
+        assert_eq!(TaskState::UpToDate, state());
+
+        drop(pushed);
+        let _pushed = manifest("sha256:bbb").mount_as_scoped(&server).await;
+        client.check().await.expect("Running Check");
+        assert_eq!(TaskState::OutOfDate, state());
+        assert_eq!("latest@sha256:bbb", client.status()[0].newest);
+
+        // Changing the Job redeploys it with the new Digest
+        drop(listed);
+        let _listed = jobs(2).mount_as_scoped(&server).await;
+        client.check().await.expect("Running Check");
+        assert_eq!(TaskState::UpToDate, state());
+    }
+
+    #[tokio::test]
+    async fn ignored_tags_excluded() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "user/app",
+                "tags": ["1.0.0", "1.2.3", "nightly", "sha-abc123"],
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host.clone()],
+                ignored_tags: vec!["nightly".to_string(), "sha-*".to_string()],
+                ..Default::default()
+            });
+        let filter = select::CandidateFilter::default();
+        let check = |tag: &str| {
+            client.check_task(
+                &filter,
+                None,
+                nomad::ReadJobConfig::Docker {
+                    image: format!("{host}/user/app:{tag}"),
+                    tag: None,
+                    digest: None,
+                },
+            )
+        };
+
+        let outcome = check("1.0.0").await;
+        assert_eq!(
+            Some(metrics::UpdatedVersion::OutOfDate {
+                current: "1.0.0".to_string(),
+                newest: "1.2.3".to_string(),
+                newer: 1,
+            }),
+            outcome.version
+        );
+        assert_eq!(2, outcome.candidates.unwrap().before);
+
+        assert_eq!(None, check("nightly").await.version);
+        assert_eq!(None, check("sha-def456").await.version);
+        assert!(client
+            .get_metrics()
+            .contains(r#"skipped_tasks_total{reason="ignored_tag"} 2"#));
+    }
+
+    #[tokio::test]
+    async fn unreachable_only_for_registry_failures() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        let task = |name: &str, image: String| {
+            serde_json::json!({
+                "Name": name,
+                "Driver": "docker",
+                "Config": { "image": image },
+            })
+        };
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                    "ID": "web",
+                    "Name": "web",
+                    "ParentID": "",
+                    "TaskGroups": [{
+                        "Name": "group",
+                        "Tasks": [
+                            task("down", format!("{host}/user/down:1.0.0")),
+                            task("templated", format!("{host}/user/app:${{VERSION}}")),
+                            task("unparsable", format!("{host}/user/app:not-a-version")),
+                        ],
+                    }],
+                }])),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/down/tags/list"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host],
+                needs_attention: vec![AttentionState::Unreachable],
+                ..Default::default()
+            });
+        client.check().await.expect("Running Check");
+
+        assert!(client.get_metrics().contains("needs_attention_total 1\n"));
+    }
+
+    #[tokio::test]
+    async fn current_missing_needs_attention() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        let task = |name: &str, image: String| {
+            serde_json::json!({
+                "Name": name,
+                "Driver": "docker",
+                "Config": { "image": image },
+            })
+        };
+        mount_jobs(
+            &server,
+            vec![serde_json::json!({
+                "ID": "web",
+                "Name": "web",
+                "ParentID": "",
+                "TaskGroups": [{
+                    "Name": "group",
+                    "Tasks": [
+                        task("listed", format!("{host}/user/app:1.1.0")),
+                        task("deleted", format!("{host}/user/app:1.0.5")),
+                    ],
+                }],
+            })],
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "user/app",
+                "tags": ["1.0.0", "1.1.0"],
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host],
+                needs_attention: vec![AttentionState::CurrentMissing],
+                ..Default::default()
+            });
+        client.check().await.expect("Running Check");
+
+        assert!(client.get_metrics().contains("needs_attention_total 1\n"));
+    }
+
+    #[tokio::test]
+    async fn podman_task_checked() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "user/app",
+                "tags": ["1.0.0", "1.1.0"],
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host.clone()],
+                ..Default::default()
+            });
+        let outcome = client
+            .check_task(
+                &select::CandidateFilter::default(),
+                None,
+                nomad::ReadJobConfig::Podman {
+                    image: format!("{host}/user/app:1.0.0"),
+                },
+            )
+            .await;
+
+        assert!(matches!(
+            outcome.version,
+            Some(metrics::UpdatedVersion::OutOfDate { current, newest, .. })
+                if current == "1.0.0" && newest == "1.1.0"
+        ));
+    }
+
+    #[tokio::test]
+    async fn dispatch_parents_skipped() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/job/backup"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Name": "backup",
+                "ParentID": "",
+                "Periodic": { "Enabled": true, "Spec": "@daily", "SpecType": "cron" },
+                "ParameterizedJob": null,
+                "TaskGroups": [],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/job/web"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Name": "web",
+                "ParentID": "",
+                "Periodic": null,
+                "ParameterizedJob": null,
+                "TaskGroups": [],
+            })))
+            .mount(&server)
+            .await;
+
+        let read = |skip_dispatch_parents: bool| {
+            let client = Client::new(server.uri()).with_config(Config {
+                skip_dispatch_parents,
+                ..Default::default()
+            });
+            async move {
+                let jobs = ["backup", "web"]
+                    .into_iter()
+                    .map(|id| nomad::JobListEntry {
+                        id: id.to_string(),
+                        ..Default::default()
+                    })
+                    .collect();
+                let mut names: Vec<_> = client
+                    .read_job_stream(jobs)
+                    .map(|job| job.expect("Reading Job").name)
+                    .collect()
+                    .await;
+                names.sort();
+                names
+            }
+        };
+
+        assert_eq!(vec!["backup", "web"], read(false).await);
+        assert_eq!(vec!["web"], read(true).await);
+    }
+
+    #[tokio::test]
+    async fn get_tags_failure_counted() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host.clone()],
+                ..Default::default()
+            });
+        let outcome = client
+            .check_task(
+                &select::CandidateFilter::default(),
+                None,
+                nomad::ReadJobConfig::Docker {
+                    image: format!("{host}/user/app:1.0.0"),
+                    tag: None,
+                    digest: None,
+                },
+            )
+            .await;
+
+        assert!(outcome.version.is_none());
+        let metrics = client.get_metrics();
+        assert!(metrics.contains(r#"check_errors_total{stage="get_tags"} 1"#));
+        assert!(!metrics.contains(r#"check_errors_total{stage="parse_version"}"#));
+    }
+
+    #[tokio::test]
+    async fn registry_timeout_counted() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "tags": ["1.0.0", "1.1.0"] }))
+                    .set_delay(Duration::from_secs(5)),
+            )
+            .mount(&server)
+            .await;
+
+        let http = HttpConfig {
+            timeout: Duration::from_millis(200),
+            ..Default::default()
+        };
+        let client = Client::with_http(server.uri(), &http)
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host.clone()],
+                ..Default::default()
+            });
+
+        let start = Instant::now();
+        let outcome = client
+            .check_task(
+                &select::CandidateFilter::default(),
+                None,
+                nomad::ReadJobConfig::Docker {
+                    image: format!("{host}/user/app:1.0.0"),
+                    tag: None,
+                    digest: None,
+                },
+            )
+            .await;
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(outcome.version.is_none());
+        assert!(client
+            .get_metrics()
+            .contains(r#"check_errors_total{stage="get_tags"} 1"#));
+    }
+
+    #[tokio::test]
+    async fn unparseable_version_skipped() {
+        let client = Client::with_registry("http://localhost:4646", Registry::new()).unwrap();
+        let docker = |image: &str| nomad::ReadJobConfig::Docker {
+            image: image.to_string(),
+            tag: None,
+            digest: None,
+        };
+
+        for image in ["user/app:not_a_version", "user/app:${VERSION}"] {
+            let outcome = client
+                .check_task(&select::CandidateFilter::default(), None, docker(image))
+                .await;
+            assert!(outcome.version.is_none());
+        }
+
+        let metrics = client.get_metrics();
+        assert!(metrics.contains(r#"skipped_tasks_total{reason="version_parse"} 1"#));
+        assert!(metrics.contains(r#"skipped_tasks_total{reason="templated_image"} 1"#));
+        assert!(!metrics.contains(r#"reason="image_parse""#));
+    }
+
+    #[tokio::test]
+    async fn tags_fetched_while_reading() {
+        use axum::{routing::get, Json};
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, Request, Respond, ResponseTemplate,
+        };
+
+        /// Signals that the Tags of the Image were requested
+        struct Signal(Arc<tokio::sync::Notify>, ResponseTemplate);
+        impl Respond for Signal {
+            fn respond(&self, _: &Request) -> ResponseTemplate {
+                self.0.notify_one();
+                self.1.clone()
+            }
+        }
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        let job = |name: &str| {
+            serde_json::json!({
+                "Name": name,
+                "ParentID": "",
+                "TaskGroups": [{
+                    "Name": "group",
+                    "Tasks": [{
+                        "Name": "app",
+                        "Driver": "docker",
+                        "Config": { "image": format!("{host}/user/{name}:1.0.0") },
+                    }],
+                }],
+            })
+        };
+        let tags = |name: &str| {
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": format!("user/{name}"),
+                "tags": ["1.0.0", "1.1.0"],
+            }))
+        };
+        let fast_tags = Arc::new(tokio::sync::Notify::new());
+        Mock::given(method("GET"))
+            .and(path("/v2/user/fast/tags/list"))
+            .respond_with(Signal(fast_tags.clone(), tags("fast")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/slow/tags/list"))
+            .respond_with(tags("slow"))
+            .mount(&server)
+            .await;
+
+        // Nomad only answers the Read of the slow Job once the Tags of the fast one were
+        // requested, so the Check can only complete if they are fetched while reading
+        let (fast, slow) = (job("fast"), job("slow"));
+        let waiting = fast_tags.clone();
+        let nomad = axum::Router::new()
+            .route(
+                "/v1/jobs",
+                get(|| async { Json(serde_json::json!([{ "ID": "fast" }, { "ID": "slow" }])) }),
+            )
+            .route("/v1/job/fast", get(move || async move { Json(fast) }))
+            .route(
+                "/v1/job/slow",
+                get(move || async move {
+                    waiting.notified().await;
+                    Json(slow)
+                }),
+            );
+        let nomad =
+            axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(nomad.into_make_service());
+        let nomad_url = format!("http://{}", nomad.local_addr());
+        tokio::spawn(nomad);
+
+        let client = Client::with_registry(nomad_url, Registry::new())
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host],
+                ..Default::default()
+            });
+
+        tokio::time::timeout(Duration::from_secs(10), client.check())
+            .await
+            .expect("Tags of the fast Job are fetched while reading the slow one")
+            .expect("Running Check");
+
+        assert_eq!(2, client.status().len());
+        assert!(client
+            .status()
+            .iter()
+            .all(|s| s.state == TaskState::OutOfDate));
+    }
+
+    #[tokio::test]
+    async fn monitored_tasks_counted() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        let task = |name: &str| {
+            serde_json::json!({
+                "Name": name,
+                "Driver": "docker",
+                "Config": { "image": format!("{host}/user/app:1.0.0") },
+            })
+        };
+        let job = |name: &str, tasks: &[&str]| {
+            serde_json::json!({
+                "Name": name,
+                "ParentID": "",
+                "TaskGroups": [{
+                    "Name": "group",
+                    "Tasks": tasks.iter().map(|t| task(t)).collect::<Vec<_>>(),
+                }],
+            })
+        };
+        mount_jobs(
+            &server,
+            vec![job("web", &["app", "proxy"]), job("api", &["app"])],
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "user/app",
+                "tags": ["1.0.0"],
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host],
+                ..Default::default()
+            });
+        client.check().await.expect("Running Check");
+
+        assert_eq!(3, client.status().len());
+        assert!(client.get_metrics().contains("monitored_tasks_total 3"));
+    }
+
+    #[tokio::test]
+    async fn tasks_checked_concurrently() {
+        use std::{
+            sync::{Arc, Mutex},
+            time::Instant,
+        };
+        use wiremock::{
+            matchers::{method, path_regex},
+            Mock, MockServer, Request, Respond, ResponseTemplate,
+        };
+
+        /// Records when every Tags Request arrived, a Request is in flight
+        /// until its delayed Response has been sent
+        struct Arrivals {
+            times: Arc<Mutex<Vec<Instant>>>,
+            delay: Duration,
+        }
+        impl Respond for Arrivals {
+            fn respond(&self, request: &Request) -> ResponseTemplate {
+                self.times.lock().unwrap().push(Instant::now());
+                let name = request.url.path().trim_start_matches("/v2/");
+                let name = name.trim_end_matches("/tags/list");
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({
+                        "name": name,
+                        "tags": ["1.0.0", "1.1.0"],
+                    }))
+                    .set_delay(self.delay)
+            }
+        }
+
+        let delay = Duration::from_millis(300);
+        let times = Arc::new(Mutex::new(Vec::new()));
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        let tasks: Vec<_> = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|name| {
+                serde_json::json!({
+                    "Name": name,
+                    "Driver": "docker",
+                    "Config": { "image": format!("{host}/user/{name}:1.0.0") },
+                })
+            })
+            .collect();
+        mount_jobs(
+            &server,
+            vec![serde_json::json!({
+                    "Name": "web",
+                    "ParentID": "",
+                    "TaskGroups": [{ "Name": "group", "Tasks": tasks }],
+            })],
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path_regex("^/v2/user/[a-d]/tags/list$"))
+            .respond_with(Arrivals {
+                times: times.clone(),
+                delay,
+            })
+            .expect(4)
+            .mount(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host],
+                check_concurrency: 2,
+                ..Default::default()
+            });
+
+        client.check().await.expect("Running Check");
+
+        assert_eq!(4, client.status().len());
+        server.verify().await;
+
+        // A Request can only start once an earlier one has been answered, so
+        // the overlap never exceeds the Budget regardless of Timing
+        let times = times.lock().unwrap();
+        let in_flight = times
+            .iter()
+            .map(|at| {
+                times
+                    .iter()
+                    .filter(|other| *other <= at && *at < **other + delay)
+                    .count()
+            })
+            .max();
+        assert_eq!(Some(2), in_flight);
+    }
+
+    #[tokio::test]
+    async fn nomad_version_out_of_date() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/agent/self"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "config": { "Version": { "Version": "1.6.1", "VersionPrerelease": "" } },
+                "member": {},
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/releases"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "version": "1.8.0-beta.1", "is_prerelease": true },
+                { "version": "1.7.2", "is_prerelease": false },
+                { "version": "1.6.1", "is_prerelease": false },
+            ])))
+            .mount(&server)
+            .await;
+
+        let releases_url = reqwest::Url::parse(&format!("{}/releases", server.uri())).unwrap();
+        let client = Client::new(server.uri()).with_config(Config {
+            nomad_releases_url: Some(releases_url.clone()),
+            ..Default::default()
+        });
+
+        client.check_nomad_version(&releases_url).await;
+
+        assert!(client
+            .get_metrics()
+            .contains(r#"nomad_out_of_date{current="1.6.1",newest="1.7.2"} 1"#));
+    }
+
+    #[tokio::test]
+    async fn jobs_from_list() {
+        use wiremock::{
+            matchers::{method, path, query_param},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .and(query_param("meta", "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {
+                    "ID": "full",
+                    "Name": "full",
+                    "ParentID": "",
+                    "Meta": { "vmonitor_track_tag": "1.x" },
+                    "TaskGroups": [],
+                },
+                { "ID": "stub", "Name": "stub", "ParentID": "" },
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/job/full"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/job/stub"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Name": "stub",
+                "ParentID": "",
+                "TaskGroups": [],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::new(server.uri());
+        let list = nomad::list_jobs(
+            &client.client,
+            &client.nomad_url,
+            None,
+            None,
+            &client.nomad_metrics,
+        )
+        .await
+        .expect("Listing Jobs");
+        let mut jobs: Vec<_> = client
+            .read_job_stream(list)
+            .map(|job| job.expect("Reading Job"))
+            .collect()
+            .await;
+        jobs.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(
+            vec!["full", "stub"],
+            jobs.iter().map(|j| j.name.as_str()).collect::<Vec<_>>()
+        );
+        assert!(jobs[0].meta.is_some());
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn raw_exec_not_reported() {
+        use wiremock::MockServer;
+
+        let server = MockServer::start().await;
+        mount_jobs(
+            &server,
+            vec![serde_json::json!({
+                    "Name": "cron",
+                    "ParentID": "",
+                    "TaskGroups": [{
+                        "Name": "group",
+                        "Tasks": [{ "Name": "script", "Driver": "raw_exec", "Config": {} }],
+                    }],
+            })],
+        )
+        .await;
+
+        let client = Client::new(server.uri());
+        client.check().await.expect("Running Check");
+
+        assert!(!client.get_metrics().contains(r#"task="script""#));
+        assert!(client.status().is_empty());
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                raw_exec: RawExecBehaviour::NotTrackable,
+                ..Default::default()
+            });
+        client.check().await.expect("Running Check");
+
+        let metrics = client.get_metrics();
+        assert!(
+            metrics.contains(r#"not_trackable{group="group",job="cron",namespace="default",owner="",task="script"} 1"#)
+        );
+        assert!(metrics.contains(
+            r#"up_to_date{group="group",job="cron",namespace="default",owner="",task="script"} 0"#
+        ));
+        assert_eq!(TaskState::NotTrackable, client.status()[0].state);
+    }
+
+    #[tokio::test]
+    async fn dispatched_children_aggregated() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let child = |id: &str| {
+            serde_json::json!({
+                "ID": id,
+                "Name": id,
+                "ParentID": "batch",
+                "TaskGroups": [{
+                    "Name": "group",
+                    "Tasks": [{ "Name": "script", "Driver": "raw_exec", "Config": {} }],
+                }],
+            })
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {
+                    "ID": "batch",
+                    "Name": "batch",
+                    "ParentID": "",
+                    "ParameterizedJob": { "Payload": "optional" },
+                    "TaskGroups": [{
+                        "Name": "group",
+                        "Tasks": [{ "Name": "template", "Driver": "raw_exec", "Config": {} }],
+                    }],
+                },
+                child("batch/dispatch-1"),
+                child("batch/dispatch-2"),
+                child("batch/dispatch-3"),
+            ])))
+            .mount(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                raw_exec: RawExecBehaviour::NotTrackable,
+                ..Default::default()
+            });
+        client.check().await.expect("Running Check");
+        let status = client.status();
+        assert_eq!(1, status.len());
+        assert_eq!("template", status[0].task);
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                raw_exec: RawExecBehaviour::NotTrackable,
+                aggregate_dispatched: true,
+                ..Default::default()
+            });
+        client.check().await.expect("Running Check");
+
+        let metrics = client.get_metrics();
+        assert_eq!(1, metrics.matches("not_trackable{").count());
+        assert!(metrics
+            .contains(r#"not_trackable{group="group",job="batch",namespace="default",owner="",task="script"} 1"#));
+        assert!(!metrics.contains("dispatch"));
+        assert_eq!(1, client.status().len());
+    }
+
+    #[tokio::test]
+    async fn failed_list_keeps_running() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let client = Arc::new(
+            Client::with_registry(server.uri(), Registry::new())
+                .unwrap()
+                .with_config(Config {
+                    check_interval: Duration::from_millis(50),
+                    ..Default::default()
+                }),
+        );
+        assert!(matches!(
+            client.check().await,
+            Err(CheckError::ListJobs(nomad::NomadError::Status(
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR
+            )))
+        ));
+
+        let runner = tokio::spawn(client.run());
+
+        // Every Check after the failed one lists the Jobs again
+        let lists = || async {
+            server
+                .received_requests()
+                .await
+                .unwrap()
+                .iter()
+                .filter(|r| r.url.path() == "/v1/jobs")
+                .count()
+        };
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while lists().await < 3 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("Listing the Jobs in the following Checks");
+        assert!(!runner.is_finished());
+        runner.abort();
+    }
+
+    #[tokio::test]
+    async fn events_trigger_check() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        /// Waits for the Check triggered by the Event of the Topic, following the initial one
+        async fn triggered_check(topic: &str) {
+            let server = MockServer::start().await;
+            mount_jobs(&server, Vec::new()).await;
+            let event = serde_json::json!({
+                "Index": 10,
+                "Events": [{
+                    "Index": 10,
+                    "Key": "web",
+                    "Namespace": "default",
+                    "Payload": {},
+                    "Topic": topic,
+                    "Type": "JobRegistered",
+                }],
+            });
+            Mock::given(method("GET"))
+                .and(path("/v1/event/stream"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(format!("{event}\n")))
+                .mount(&server)
+                .await;
+
+            let client = Arc::new(
+                Client::with_registry(server.uri(), Registry::new())
+                    .unwrap()
+                    .with_config(Config {
+                        check_interval: Duration::from_secs(3600),
+                        empty_retry_interval: Duration::from_secs(3600),
+                        event_debounce: Duration::from_millis(50),
+                        ..Default::default()
+                    }),
+            );
+            let runner = tokio::spawn(client.run());
+
+            let checks = || async {
+                server
+                    .received_requests()
+                    .await
+                    .unwrap()
+                    .iter()
+                    .filter(|r| r.url.path() == "/v1/jobs")
+                    .count()
+            };
+            tokio::time::timeout(Duration::from_secs(5), async {
+                while checks().await < 2 {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .unwrap_or_else(|_| panic!("Check triggered by the {topic} Event"));
+            runner.abort();
+        }
+
+        // Which Topics are irrelevant is covered by the Event-Stream itself, as there is no
+        // point in time after which the absence of a Check would be certain
+        triggered_check("Job").await;
+        triggered_check("Allocation").await;
+    }
+
+    // The Clock only advances once every Task waits, so the Timing is exact
+    #[tokio::test(start_paused = true)]
+    async fn notifications_debounced() {
+        let notify = Arc::new(tokio::sync::Notify::new());
+        let quiet = Duration::from_millis(100);
+
+        let sender = notify.clone();
+        let burst = tokio::spawn(async move {
+            for _ in 0..10 {
+                sender.notify_one();
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+
+        notify.notified().await;
+        let start = tokio::time::Instant::now();
+        wait_quiet(&notify, quiet, Duration::from_secs(10)).await;
+        burst.await.unwrap();
+
+        // The whole burst is covered, without a Notification left for another Check
+        assert_eq!(Duration::from_millis(180) + quiet, start.elapsed());
+        assert!(tokio::time::timeout(quiet, notify.notified())
+            .await
+            .is_err());
+
+        // A constant stream of Notifications only delays the Check up to the maximum
+        let sender = notify.clone();
+        let constant = tokio::spawn(async move {
+            loop {
+                sender.notify_one();
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        });
+        let start = tokio::time::Instant::now();
+        wait_quiet(&notify, quiet, Duration::from_millis(300)).await;
+        constant.abort();
+        assert_eq!(Duration::from_millis(300), start.elapsed());
+    }
+
+    #[test]
+    fn default_check_interval() {
+        let client = Client::new("http://localhost:4646");
+        *client.found_jobs.write().unwrap() = true;
+
+        assert_eq!(Duration::from_secs(15 * 60), client.next_interval());
+    }
+
+    #[test]
+    fn webhook_configured_outside_runtime() {
+        let client = Client::new("http://localhost:4646").with_config(Config {
+            webhook: Some(WebhookConfig {
+                url: reqwest::Url::parse("http://localhost:8080/hook").unwrap(),
+                drain_timeout: Duration::from_secs(1),
+            }),
+            ..Default::default()
+        });
+
+        assert!(client.notifier.is_some());
+    }
+
+    #[test]
+    fn configured_check_interval() {
+        let client = Client::new("http://localhost:4646").with_interval(Duration::from_secs(300));
+        *client.found_jobs.write().unwrap() = true;
+
+        assert_eq!(Duration::from_secs(300), client.config.check_interval);
+        assert_eq!(Duration::from_secs(300), client.next_interval());
+    }
+
+    #[tokio::test]
+    async fn empty_check_retries_sooner() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let jobs = Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .mount_as_scoped(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                check_interval: Duration::from_secs(600),
+                empty_retry_interval: Duration::from_secs(5),
+                ..Default::default()
+            });
+        client.check().await.expect("Running Check");
+        assert_eq!(Duration::from_secs(5), client.next_interval());
+
+        drop(jobs);
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                    "ID": "cron",
+                    "Name": "cron",
+                    "ParentID": "",
+                    "TaskGroups": [],
+                }])),
+            )
+            .mount(&server)
+            .await;
+        client.check().await.expect("Running Check");
+        assert_eq!(Duration::from_secs(600), client.next_interval());
+    }
+
+    #[tokio::test]
+    async fn paused_skips_checks() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let jobs = Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .expect(0)
+            .mount_as_scoped(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new()).unwrap();
+        client.pause(None);
+        client.cycle().await;
+        assert!(client.get_metrics().contains("monitoring_paused 1"));
+        drop(jobs);
+
+        let jobs = Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .expect(1)
+            .mount_as_scoped(&server)
+            .await;
+        client.resume();
+        client.cycle().await;
+        assert!(client.get_metrics().contains("monitoring_paused 0"));
+        drop(jobs);
+
+        client.pause(Some(Duration::ZERO));
+        assert!(!client.is_paused(), "Resumes once the Duration passed");
+    }
+
+    #[tokio::test]
+    async fn owner_labels() {
+        use wiremock::MockServer;
+
+        let server = MockServer::start().await;
+        let jobs = [
+            ("cron", serde_json::json!({ "vmonitor_owner": "team-a" })),
+            ("backup", serde_json::json!(null)),
+        ]
+        .into_iter()
+        .map(|(name, meta)| {
+            serde_json::json!({
+                "Name": name,
+                "ParentID": "",
+                "Meta": meta,
+                "TaskGroups": [{
+                    "Name": "group",
+                    "Tasks": [{ "Name": "script", "Driver": "raw_exec", "Config": {} }],
+                }],
+            })
+        })
+        .collect();
+        mount_jobs(&server, jobs).await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                raw_exec: RawExecBehaviour::NotTrackable,
+                owners: [
+                    ("cron".to_string(), "team-b".to_string()),
+                    ("backup".to_string(), "team-c".to_string()),
+                ]
+                .into_iter()
+                .collect(),
+                ..Default::default()
+            });
+        client.check().await.expect("Running Check");
+
+        let metrics = client.get_metrics();
+        assert!(metrics
+            .contains(r#"not_trackable{group="group",job="cron",namespace="default",owner="team-a",task="script"} 1"#));
+        assert!(metrics.contains(
+            r#"not_trackable{group="group",job="backup",namespace="default",owner="team-c",task="script"} 1"#
+        ));
+        assert!(metrics.contains(
+            r#"update_urgency_score{group="group",job="cron",namespace="default",owner="team-a",task="script"} 0"#
+        ));
+    }
+
+    #[tokio::test]
+    async fn owners_of_same_job_in_namespaces() {
+        use wiremock::{
+            matchers::{method, path, query_param},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "ID": "web", "Namespace": "team-a" },
+                { "ID": "web", "Namespace": "team-b" },
+            ])))
+            .mount(&server)
+            .await;
+        for (namespace, meta) in [
+            ("team-a", serde_json::json!({ "vmonitor_owner": "alice" })),
+            ("team-b", serde_json::json!(null)),
+        ] {
+            Mock::given(method("GET"))
+                .and(path("/v1/job/web"))
+                .and(query_param("namespace", namespace))
+                .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "Name": "web",
+                    "Namespace": namespace,
+                    "ParentID": "",
+                    "Meta": meta,
+                    "TaskGroups": [{
+                        "Name": "group",
+                        "Tasks": [{ "Name": "script", "Driver": "raw_exec", "Config": {} }],
+                    }],
+                })))
+                .mount(&server)
+                .await;
+        }
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                namespace: Some("*".to_string()),
+                raw_exec: RawExecBehaviour::NotTrackable,
+                owners: [
+                    ("team-b/web".to_string(), "bob".to_string()),
+                    ("web".to_string(), "carol".to_string()),
+                ]
+                .into_iter()
+                .collect(),
+                ..Default::default()
+            });
+        client.check().await.expect("Running Check");
+
+        let metrics = client.get_metrics();
+        assert!(metrics.contains(
+            r#"not_trackable{group="group",job="web",namespace="team-a",owner="alice",task="script"} 1"#
+        ));
+        assert!(metrics.contains(
+            r#"not_trackable{group="group",job="web",namespace="team-b",owner="bob",task="script"} 1"#
+        ));
+        assert!(metrics.contains(
+            r#"update_urgency_score{group="group",job="web",namespace="team-b",owner="bob",task="script"} 0"#
+        ));
+    }
+
+    #[tokio::test]
+    async fn job_read_failed() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!([{ "ID": "cron" }, { "ID": "broken" }])),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/job/cron"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Name": "cron",
+                "ParentID": "",
+                "TaskGroups": [{
+                    "Name": "group",
+                    "Tasks": [{ "Name": "script", "Driver": "raw_exec", "Config": {} }],
+                }],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/job/broken"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                raw_exec: RawExecBehaviour::NotTrackable,
+                ..Default::default()
+            });
+        client.check().await.expect("Running Check");
+
+        let metrics = client.get_metrics();
+        assert!(metrics.contains(r#"job_read_failed{job="broken"} 1"#));
+        assert!(!metrics.contains(r#"job_read_failed{job="cron"}"#));
+        assert!(
+            metrics.contains(r#"not_trackable{group="group",job="cron",namespace="default",owner="",task="script"} 1"#)
+        );
+    }
+
+    #[test]
+    fn const_labels() {
+        let client = Client::with_registry("http://localhost:4646", Registry::new())
+            .unwrap()
+            .with_config(Config {
+                const_labels: vec![("env".to_string(), "prod".to_string())],
+                ..Default::default()
+            });
+        client.general.set_fingerprint("abc");
+
+        let metrics = client.get_metrics();
+        assert!(metrics.contains(r#"update_urgency_score_total{env="prod"} 0"#));
+        assert!(metrics.contains(r#"monitored_fingerprint{env="prod",fingerprint="abc"} 1"#));
+
+        let client = Client::with_registry("http://localhost:4646", Registry::new()).unwrap();
+        assert!(client
+            .get_metrics()
+            .contains("update_urgency_score_total 0"));
+    }
+
+    #[tokio::test]
+    async fn approved_feed() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        mount_jobs(
+            &server,
+            vec![serde_json::json!({
+                    "Name": "web",
+                    "ParentID": "",
+                    "TaskGroups": [{
+                        "Name": "group",
+                        "Tasks": [{
+                            "Name": "app",
+                            "Driver": "docker",
+                            "Config": { "image": "user/app:1.0.0" },
+                        }],
+                    }],
+            })],
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path("/approved/app"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("1.1.0\n"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let feed_url = reqwest::Url::parse(&format!("{}/approved/app", server.uri())).unwrap();
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                approved_feeds: [("user/app".to_string(), feed_url)].into_iter().collect(),
+                ..Default::default()
+            });
+        client.check().await.expect("Running Check");
+
+        let status = client.status();
+        assert_eq!(1, status.len());
+        assert_eq!(TaskState::OutOfDate, status[0].state);
+        assert_eq!("1.0.0", status[0].current);
+        assert_eq!("1.1.0", status[0].newest);
+        let details = client.task_detail("web", "app");
+        assert_eq!(1, details.len());
+        assert_eq!(vec!["1.1.0".to_string()], details[0].newer);
+        assert!(client.task_detail("web", "other").is_empty());
+        let metrics = client.get_metrics();
+        assert!(metrics.contains(
+            r#"candidates_before_filter{group="group",image="user/app",job="web",namespace="default",task="app"} 1"#
+        ));
+        assert!(metrics.contains(
+            r#"candidates_after_filter{group="group",image="user/app",job="web",namespace="default",task="app"} 1"#
+        ));
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn mirror_tags_fetched_once() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        mount_jobs(
+            &server,
+            vec![serde_json::json!({
+                    "Name": "web",
+                    "ParentID": "",
+                    "Meta": { "vmonitor_upstream_image": format!("{host}/upstream/app") },
+                    "TaskGroups": [{
+                        "Name": "group",
+                        "Tasks": [{
+                            "Name": "app",
+                            "Driver": "docker",
+                            "Config": { "image": format!("{host}/mirror/app:1.0.0") },
+                        }],
+                    }],
+            })],
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/mirror/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "mirror/app",
+                "tags": ["1.0.0", "1.1.0"],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/upstream/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "upstream/app",
+                "tags": ["1.0.0", "1.1.0", "1.2.0"],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host],
+                tag_cache_ttl: Duration::ZERO,
+                ..Default::default()
+            });
+        client.check().await.expect("Running Check");
+
+        let status = client.status();
+        assert_eq!(1, status.len());
+        assert_eq!("1.1.0", status[0].newest);
+        assert!(client.get_metrics().contains(
+            r#"mirror_lagging{group="group",job="web",namespace="default",task="app"} 1"#
+        ));
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn registry_newest_regressed() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        mount_jobs(
+            &server,
+            vec![serde_json::json!({
+                    "Name": "web",
+                    "ParentID": "",
+                    "TaskGroups": [{
+                        "Name": "group",
+                        "Tasks": [
+                            {
+                                "Name": "app",
+                                "Driver": "docker",
+                                "Config": { "image": format!("{host}/user/app:1.0.0") },
+                            },
+                            {
+                                "Name": "sidecar",
+                                "Driver": "docker",
+                                "Config": { "image": format!("{host}/user/app:1.0.0") },
+                            },
+                        ],
+                    }],
+            })],
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "user/app",
+                "tags": ["latest", "1.0.0", "1.2.0"],
+            })))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "user/app",
+                "tags": ["latest", "1.0.0", "1.1.0"],
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::with_registry(server.uri(), Registry::new())
+            .unwrap()
+            .with_config(Config {
+                insecure_registries: vec![host.clone()],
+                tag_cache_ttl: Duration::ZERO,
+                ..Default::default()
+            });
+        let regressed = format!(r#"registry_newest_regressed{{repository="{host}/user/app"}}"#);
+
+        client.check().await.expect("Running Check");
+        assert!(client.get_metrics().contains(&format!("{regressed} 0")));
+
+        client.check().await.expect("Running Check");
+        assert!(client.get_metrics().contains(&format!("{regressed} 1")));
+
+        client.check().await.expect("Running Check");
+        assert!(client.get_metrics().contains(&format!("{regressed} 0")));
+    }
+
+    #[tokio::test]
+    async fn deployed_digest_changes() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/v2/library/app/manifests/1.2.3"))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("Docker-Content-Digest", "sha256:aaa"),
+            )
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/v2/library/app/manifests/1.2.3"))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("Docker-Content-Digest", "sha256:bbb"),
+            )
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let registry_url = reqwest::Url::parse(&server.uri()).unwrap();
+        let key = state::TaskKey {
+            namespace: "default".to_string(),
+            job: "job".to_string(),
+            group: "group".to_string(),
+            task: "task".to_string(),
+        };
+        let mut state = state::CycleState::default();
+
+        let mut changes = Vec::new();
+        for _ in 0..3 {
+            let digest =
+                docker::get_digest(&client, &registry_url, "library/app", "1.2.3", 3, None)
+                    .await
+                    .expect("Resolving Digest")
+                    .expect("Registry returns a Digest");
+
+            changes.push(state.record_digest(key.clone(), "1.2.3".to_string(), digest));
+        }
+
+        assert_eq!(vec![false, false, true], changes);
+        assert_eq!("sha256:bbb", state.digests()[&key].digest);
+    }
+
+    #[tokio::test]
+    async fn floating_tag() {
+        let client = Client::new("http://localhost:4646");
+
+        let result = client
+            .check_docker_image("user/app:main".to_string(), &Default::default(), None)
+            .await
+            .version;
+
+        assert_eq!(
+            Some(metrics::UpdatedVersion::Floating {
+                tag: "main".to_string()
+            }),
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn floating_tag_configurable() {
+        let client = Client::new("http://localhost:4646").with_config(Config {
+            floating_tags: vec!["trunk".to_string()],
+            ..Default::default()
+        });
+
+        let result = client
+            .check_docker_image("user/app:trunk".to_string(), &Default::default(), None)
+            .await
+            .version;
+
+        assert_eq!(
+            Some(metrics::UpdatedVersion::Floating {
+                tag: "trunk".to_string()
+            }),
+            result
+        );
     }
 }
@@ -1,13 +1,33 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{collections::HashMap, future::Future, net::SocketAddr, sync::Arc};
 
-use axum::{extract::State, response::IntoResponse, routing::get, Router};
-use nomad_vmonitor::Client;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use nomad_vmonitor::{
+    AttentionState, Client, Config, HttpConfig, PushgatewayConfig, RawExecBehaviour,
+    RegistryCredentials, StatusFormat, UrgencyWeights, VersionOrdering, WebhookConfig,
+};
+use opentelemetry_otlp::WithExportConfig;
+use serde::Deserialize;
 use tracing::instrument;
 use tracing_subscriber::{prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt};
 
+/// The Path the Metrics are served on, unless configured otherwise
+const DEFAULT_METRICS_PATH: &str = "/metrics";
+
+/// The Address the HTTP-Server listens on, unless configured otherwise
+const DEFAULT_LISTEN_ADDR: &str = "0.0.0.0:3000";
+
 #[derive(Debug)]
 struct AppState {
     client: Arc<Client>,
+    /// The Bearer-Token required by the Endpoints changing the State of the Monitor, which are
+    /// open to everyone without one
+    admin_token: Option<String>,
 }
 
 #[tokio::main]
@@ -21,28 +41,1025 @@ async fn main() {
         )
         .with((machine_log).then(|| tracing_subscriber::fmt::layer().json()))
         .with((!machine_log).then(|| tracing_subscriber::fmt::layer().pretty()))
+        .with(otlp_tracer().map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)))
         .init();
 
     let address = std::env::var("NOMAD_ADDR").unwrap_or_else(|_| "localhost".to_string());
     let port = std::env::var("NOMAD_PORT").unwrap_or_else(|_| "4646".to_string());
 
-    let client = Arc::new(Client::new(format!("http://{address}:{port}")));
+    let mut config = Config::default();
+    if let Ok(namespace) = std::env::var("NOMAD_NAMESPACE") {
+        config.namespace = Some(namespace);
+    }
+    if let Some(tags) = env_list("VMONITOR_FLOATING_TAGS") {
+        config.floating_tags = tags;
+    }
+    if let Some(patterns) = env_list("VMONITOR_IGNORED_TAGS") {
+        config.ignored_tags = patterns;
+    }
+    if let Some(registries) = env_list("VMONITOR_INSECURE_REGISTRIES") {
+        config.insecure_registries = registries;
+    }
+    if let Some(channels) = env_list("VMONITOR_CHANNEL_ORDER") {
+        config.channel_order = channels;
+    }
+    if let Ok(raw_ordering) = std::env::var("VMONITOR_VERSION_ORDERING") {
+        config.version_ordering = match raw_ordering.as_str() {
+            "strict" => VersionOrdering::Strict,
+            "missing_as_zero" => VersionOrdering::MissingAsZero,
+            other => {
+                panic!("VMONITOR_VERSION_ORDERING is `strict` or `missing_as_zero`, got {other:?}")
+            }
+        };
+    }
+    if std::env::var("VMONITOR_IGNORE_TAG_CASE").is_ok() {
+        config.ignore_tag_case = true;
+    }
+    if std::env::var("VMONITOR_IGNORE_DESCRIBE_SUFFIX").is_ok() {
+        config.ignore_describe_suffix = true;
+    }
+    if let Ok(raw_budget) = std::env::var("VMONITOR_FRESHNESS_BUDGET") {
+        let budget = raw_budget
+            .parse()
+            .expect("VMONITOR_FRESHNESS_BUDGET is a number of seconds");
+        config.freshness_budget = Some(std::time::Duration::from_secs(budget));
+    }
+    if let Ok(raw_cycles) = std::env::var("VMONITOR_OUT_OF_DATE_CYCLES") {
+        config.out_of_date_cycles = raw_cycles
+            .parse()
+            .expect("VMONITOR_OUT_OF_DATE_CYCLES is a number");
+    }
+    if let Some(weights) = env_list("VMONITOR_URGENCY_WEIGHTS") {
+        let weights: Vec<f64> = weights
+            .iter()
+            .map(|w| {
+                w.parse()
+                    .expect("VMONITOR_URGENCY_WEIGHTS contains numbers")
+            })
+            .collect();
+        config.urgency_weights = match weights.as_slice() {
+            [major, minor, patch] => UrgencyWeights {
+                major: *major,
+                minor: *minor,
+                patch: *patch,
+            },
+            _ => panic!("VMONITOR_URGENCY_WEIGHTS is `major,minor,patch`"),
+        };
+    }
+    if let Ok(raw_interval) = std::env::var("VMONITOR_CHECK_INTERVAL") {
+        let interval = raw_interval
+            .parse()
+            .expect("VMONITOR_CHECK_INTERVAL is a number of seconds");
+        config.check_interval = std::time::Duration::from_secs(interval);
+    }
+    if let Ok(raw_interval) = std::env::var("VMONITOR_EMPTY_RETRY_INTERVAL") {
+        let interval = raw_interval
+            .parse()
+            .expect("VMONITOR_EMPTY_RETRY_INTERVAL is a number of seconds");
+        config.empty_retry_interval = std::time::Duration::from_secs(interval);
+    }
+    if let Ok(raw_debounce) = std::env::var("VMONITOR_EVENT_DEBOUNCE") {
+        let debounce = raw_debounce
+            .parse()
+            .expect("VMONITOR_EVENT_DEBOUNCE is a number of seconds");
+        config.event_debounce = std::time::Duration::from_secs(debounce);
+    }
+    if let Ok(raw_max) = std::env::var("VMONITOR_MAX_TAG_PAGES") {
+        config.max_tag_pages = raw_max.parse().expect("VMONITOR_MAX_TAG_PAGES is a number");
+    }
+    config.registry_credentials = registry_credentials(std::env::vars());
+    if let Ok(raw_ttl) = std::env::var("VMONITOR_TAG_CACHE_TTL") {
+        let ttl = raw_ttl
+            .parse()
+            .expect("VMONITOR_TAG_CACHE_TTL is a number of seconds");
+        config.tag_cache_ttl = std::time::Duration::from_secs(ttl);
+    }
+    if let Ok(raw_retries) = std::env::var("VMONITOR_RATE_LIMIT_RETRIES") {
+        config.rate_limit_retries = raw_retries
+            .parse()
+            .expect("VMONITOR_RATE_LIMIT_RETRIES is a number");
+    }
+    if let Ok(raw_max) = std::env::var("VMONITOR_MAX_TASKS_PER_CYCLE") {
+        let max = raw_max
+            .parse()
+            .expect("VMONITOR_MAX_TASKS_PER_CYCLE is a number");
+        if max == 0 {
+            panic!(
+                "VMONITOR_MAX_TASKS_PER_CYCLE has to be at least 1, unset it to check every Task"
+            );
+        }
+        config.max_tasks_per_cycle = Some(max);
+    }
+    if let Ok(raw_concurrency) = std::env::var("VMONITOR_JOB_READ_CONCURRENCY") {
+        config.job_read_concurrency = raw_concurrency
+            .parse()
+            .expect("VMONITOR_JOB_READ_CONCURRENCY is a number");
+    }
+    if let Ok(raw_concurrency) = std::env::var("VMONITOR_CHECK_CONCURRENCY") {
+        config.check_concurrency = raw_concurrency
+            .parse()
+            .expect("VMONITOR_CHECK_CONCURRENCY is a number");
+    }
+    if std::env::var("VMONITOR_RESOLVE_DIGESTS").is_ok() {
+        config.resolve_digests = true;
+    }
+    if let Ok(raw_exec) = std::env::var("VMONITOR_RAW_EXEC") {
+        config.raw_exec = match raw_exec.as_str() {
+            "skip" => RawExecBehaviour::Skip,
+            "not_trackable" => RawExecBehaviour::NotTrackable,
+            other => panic!("VMONITOR_RAW_EXEC is `skip` or `not_trackable`, got {other:?}"),
+        };
+    }
+    if let Some(states) = env_list("VMONITOR_NEEDS_ATTENTION") {
+        config.needs_attention = states
+            .iter()
+            .map(|raw| match AttentionState::parse(raw) {
+                Some(s) => s,
+                None => panic!("VMONITOR_NEEDS_ATTENTION contains unknown State {raw:?}"),
+            })
+            .collect();
+    }
+    if std::env::var("VMONITOR_CHECK_NOMAD_VERSION").is_ok() {
+        let raw_url = std::env::var("VMONITOR_NOMAD_RELEASES_URL")
+            .unwrap_or_else(|_| nomad_vmonitor::NOMAD_RELEASES_URL.to_string());
+        config.nomad_releases_url = Some(
+            reqwest::Url::parse(&raw_url).expect("VMONITOR_NOMAD_RELEASES_URL is a valid URL"),
+        );
+    }
+    if let Some(owners) = env_list("VMONITOR_OWNERS") {
+        config.owners = owners
+            .into_iter()
+            .map(|pair| match pair.split_once('=') {
+                Some((job, owner)) => (job.to_string(), owner.to_string()),
+                None => panic!("VMONITOR_OWNERS entries are `job=owner` or `namespace/job=owner`"),
+            })
+            .collect();
+    }
+    if let Ok(raw_tracks) = std::env::var("VMONITOR_UPDATE_TRACKS") {
+        config.update_tracks = serde_json::from_str(&raw_tracks)
+            .expect("VMONITOR_UPDATE_TRACKS is a JSON Object of Job Names to `major` or `minor`");
+    }
+    if let Some(labels) = env_list("VMONITOR_CONST_LABELS") {
+        config.const_labels = labels
+            .into_iter()
+            .map(|pair| match pair.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => panic!("VMONITOR_CONST_LABELS entries are `name=value`"),
+            })
+            .collect();
+    }
+    if let Some(feeds) = env_list("VMONITOR_APPROVED_FEEDS") {
+        config.approved_feeds = feeds
+            .into_iter()
+            .map(|pair| match pair.split_once('=') {
+                Some((image, raw_url)) => (
+                    image.to_string(),
+                    reqwest::Url::parse(raw_url)
+                        .expect("VMONITOR_APPROVED_FEEDS contains valid URLs"),
+                ),
+                None => panic!("VMONITOR_APPROVED_FEEDS entries are `image=url`"),
+            })
+            .collect();
+    }
+    if std::env::var("VMONITOR_AGGREGATE_DISPATCHED").is_ok() {
+        config.aggregate_dispatched = true;
+    }
+    if std::env::var("VMONITOR_SKIP_DISPATCH_PARENTS").is_ok() {
+        config.skip_dispatch_parents = true;
+    }
+    if std::env::var("VMONITOR_TRACK_ALLOCATIONS").is_ok() {
+        config.track_allocations = true;
+    }
+    if let Ok(raw_url) = std::env::var("VMONITOR_PUSHGATEWAY_URL") {
+        let url = reqwest::Url::parse(&raw_url).expect("VMONITOR_PUSHGATEWAY_URL is a valid URL");
+        let job = std::env::var("VMONITOR_PUSHGATEWAY_JOB")
+            .unwrap_or_else(|_| "nomad-vmonitor".to_string());
+        if job.is_empty() {
+            panic!("VMONITOR_PUSHGATEWAY_JOB can not be empty");
+        }
+        let grouping = env_list("VMONITOR_PUSHGATEWAY_GROUPING")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|pair| match pair.split_once('=') {
+                Some((name, value)) if !name.is_empty() && !value.is_empty() => {
+                    (name.to_string(), value.to_string())
+                }
+                _ => panic!("VMONITOR_PUSHGATEWAY_GROUPING entries are `name=value`, got {pair:?}"),
+            })
+            .collect();
+
+        config.pushgateway = Some(PushgatewayConfig { url, job, grouping });
+    }
+
+    if let Ok(raw_url) = std::env::var("VMONITOR_WEBHOOK_URL") {
+        let url = reqwest::Url::parse(&raw_url).expect("VMONITOR_WEBHOOK_URL is a valid URL");
+        let drain_timeout = match std::env::var("VMONITOR_WEBHOOK_DRAIN_TIMEOUT") {
+            Ok(raw) => raw
+                .parse()
+                .expect("VMONITOR_WEBHOOK_DRAIN_TIMEOUT is a number of seconds"),
+            Err(_) => 10,
+        };
+
+        config.webhook = Some(WebhookConfig {
+            url,
+            drain_timeout: std::time::Duration::from_secs(drain_timeout),
+        });
+    }
+
+    let mut http = HttpConfig {
+        ca_cert: std::env::var_os("VMONITOR_CA_CERT").map(std::path::PathBuf::from),
+        accept_invalid_certs: std::env::var("VMONITOR_TLS_ACCEPT_INVALID_CERTS").is_ok(),
+        ..Default::default()
+    };
+    if let Ok(raw_timeout) = std::env::var("VMONITOR_HTTP_TIMEOUT") {
+        let timeout = raw_timeout
+            .parse()
+            .expect("VMONITOR_HTTP_TIMEOUT is a number of seconds");
+        if timeout == 0 {
+            panic!(
+                "VMONITOR_HTTP_TIMEOUT has to be at least 1 second, as every Request would fail"
+            );
+        }
+        http.timeout = std::time::Duration::from_secs(timeout);
+    }
+    if http.accept_invalid_certs {
+        tracing::warn!(
+            "Accepting invalid TLS Certificates, this should only be used for development"
+        );
+    }
+
+    let mut client = match Client::with_http(format!("http://{address}:{port}"), &http) {
+        Ok(c) => c,
+        Err(e) => panic!("{e}"),
+    };
+    if let Ok(token) = std::env::var("NOMAD_TOKEN") {
+        client = client.with_nomad_token(token);
+    }
+    let client = Arc::new(client.with_config(config));
+
+    let args: Vec<String> = std::env::args().collect();
+    if let Some("catalog") = args.get(1).map(String::as_str) {
+        let code = catalog(&client, args.get(2).map(String::as_str)).await;
+        std::process::exit(code);
+    }
+
+    let metrics_path = match metrics_path(std::env::var("VMONITOR_METRICS_PATH").ok()) {
+        Ok(p) => p,
+        Err(e) => panic!("{e}"),
+    };
 
-    tokio::spawn(client.clone().run());
+    let addr = match listen_addr(std::env::var("VMONITOR_LISTEN_ADDR").ok()) {
+        Ok(a) => a,
+        Err(e) => panic!("{e}"),
+    };
 
-    let app = Router::new()
-        .route("/metrics", get(metrics))
-        .with_state(Arc::new(AppState { client }));
+    let admin_token = std::env::var("VMONITOR_ADMIN_TOKEN")
+        .ok()
+        .filter(|t| !t.is_empty());
+    if admin_token.is_none() {
+        tracing::info!("VMONITOR_ADMIN_TOKEN is not set, the admin Endpoints are not protected");
+    }
+
+    let runner = tokio::spawn(client.clone().run());
+
+    let app = router(client.clone(), &metrics_path, admin_token);
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
     tracing::debug!("listening on {}", addr);
+    serve_until(addr, app, shutdown_signal()).await;
+
+    // Stops the Checks, cancelling a running one, before sending the pending Notifications
+    runner.abort();
+    let _ = runner.await;
+    client.shutdown().await;
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// Sets up exporting the Spans to the OTLP Endpoint, using HTTP, from `VMONITOR_OTLP_ENDPOINT`,
+/// if it is configured
+fn otlp_tracer() -> Option<opentelemetry_sdk::trace::Tracer> {
+    let endpoint = std::env::var("VMONITOR_OTLP_ENDPOINT").ok()?;
+    let service_name = std::env::var("VMONITOR_OTLP_SERVICE_NAME")
+        .unwrap_or_else(|_| "nomad-vmonitor".to_string());
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(opentelemetry_sdk::trace::config().with_resource(
+            opentelemetry_sdk::Resource::new([opentelemetry::KeyValue::new(
+                "service.name",
+                service_name,
+            )]),
+        ))
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+        .expect("Installing the OTLP Exporter");
+
+    Some(tracer)
+}
+
+/// Loads the Path the Metrics are served on, which has to start with a `/`
+fn metrics_path(raw: Option<String>) -> Result<String, String> {
+    match raw {
+        None => Ok(DEFAULT_METRICS_PATH.to_string()),
+        Some(path) if path.starts_with('/') => Ok(path),
+        Some(path) => Err(format!(
+            "VMONITOR_METRICS_PATH has to start with `/`, got {path:?}"
+        )),
+    }
+}
+
+/// Loads the Address the HTTP-Server listens on, like `127.0.0.1:8080`
+fn listen_addr(raw: Option<String>) -> Result<SocketAddr, String> {
+    let raw = raw.as_deref().unwrap_or(DEFAULT_LISTEN_ADDR);
+
+    raw.parse().map_err(|e| {
+        format!("VMONITOR_LISTEN_ADDR has to be an Address like `0.0.0.0:3000`, got {raw:?}: {e}")
+    })
+}
+
+fn router(client: Arc<Client>, metrics_path: &str, admin_token: Option<String>) -> Router {
+    let state = Arc::new(AppState {
+        client,
+        admin_token,
+    });
+
+    // The Endpoints changing the State of the Monitor require the Admin-Token, if one is set
+    let admin = Router::new()
+        .route("/check", post(trigger_check))
+        .route("/cache/clear", post(clear_cache))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            require_admin_token,
+        ));
+
+    Router::new()
+        .route(metrics_path, get(metrics))
+        .route("/status", get(status))
+        .route("/status/:job/:task", get(task_detail))
+        .route("/fingerprint", get(fingerprint))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .merge(admin)
+        .with_state(state)
+}
+
+/// Rejects Requests without the configured Admin-Token as their Bearer-Token, all Requests are
+/// let through if no Admin-Token is configured
+async fn require_admin_token<B>(
+    State(state): State<Arc<AppState>>,
+    request: axum::http::Request<B>,
+    next: axum::middleware::Next<B>,
+) -> Response {
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match (provided, state.admin_token.as_deref()) {
+        (_, None) => next.run(request).await,
+        (Some(provided), Some(expected)) if constant_time_eq(provided, expected) => {
+            next.run(request).await
+        }
+        _ => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+/// Compares the Tokens without returning early, so the Time taken does not reveal how much of
+/// them matched
+fn constant_time_eq(first: &str, second: &str) -> bool {
+    first.len() == second.len()
+        && first
+            .bytes()
+            .zip(second.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Resolves once the process receives SIGINT or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Installing SIGINT handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Installing SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("Shutting down");
+}
+
+/// Serves the App until the `shutdown` Future resolves, still completing the Requests that are
+/// in flight by then, like a running Scrape
+async fn serve_until(addr: SocketAddr, app: Router, shutdown: impl Future<Output = ()>) {
     axum::Server::bind(&addr)
         .serve(app.into_make_service())
+        .with_graceful_shutdown(shutdown)
         .await
-        .unwrap();
+        .expect("Serving HTTP");
+}
+
+/// Prints the newest Version of every Repository in the given Registry and returns the exit
+/// code for the process
+async fn catalog(client: &Client, registry: Option<&str>) -> i32 {
+    let registry_url = match registry.map(reqwest::Url::parse) {
+        Some(Ok(u)) => u,
+        Some(Err(e)) => {
+            eprintln!("Invalid Registry URL: {e}");
+            return 2;
+        }
+        None => {
+            eprintln!("Usage: nomad-vmonitor catalog <registry-url>");
+            return 2;
+        }
+    };
+
+    match client.registry_inventory(&registry_url).await {
+        Ok(inventory) => {
+            for entry in inventory {
+                println!(
+                    "{} {}",
+                    entry.repository,
+                    entry.newest.as_deref().unwrap_or("-")
+                );
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Listing Catalog: {e}");
+            1
+        }
+    }
+}
+
+/// Loads a comma-separated list from the given Environment Variable
+fn env_list(name: &str) -> Option<Vec<String>> {
+    let raw = std::env::var(name).ok()?;
+
+    Some(
+        raw.split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(String::from)
+            .collect(),
+    )
+}
+
+/// Loads the Credentials of private Registries from Environment Variables like
+/// `VMONITOR_REGISTRY_GHCR_IO_USER` and `_PASS`, or `_TOKEN` for a Bearer Token, keyed by the
+/// Host in the Form used in the Variables, like `GHCR_IO`
+fn registry_credentials<I>(vars: I) -> HashMap<String, RegistryCredentials>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    #[derive(Default)]
+    struct Parts {
+        user: Option<String>,
+        pass: Option<String>,
+        token: Option<String>,
+    }
+
+    let mut hosts: HashMap<String, Parts> = HashMap::new();
+    for (name, value) in vars {
+        let rest = match name.strip_prefix("VMONITOR_REGISTRY_") {
+            Some(r) => r,
+            None => continue,
+        };
+
+        if let Some(host) = rest.strip_suffix("_USER") {
+            hosts.entry(host.to_string()).or_default().user = Some(value);
+        } else if let Some(host) = rest.strip_suffix("_PASS") {
+            hosts.entry(host.to_string()).or_default().pass = Some(value);
+        } else if let Some(host) = rest.strip_suffix("_TOKEN") {
+            hosts.entry(host.to_string()).or_default().token = Some(value);
+        }
+    }
+
+    hosts
+        .into_iter()
+        .map(|(host, parts)| {
+            let credentials = match parts {
+                Parts {
+                    user: Some(username),
+                    pass: Some(password),
+                    ..
+                } => RegistryCredentials::Basic { username, password },
+                Parts {
+                    user: None,
+                    pass: None,
+                    token: Some(token),
+                } => RegistryCredentials::Token(token),
+                _ => panic!(
+                    "VMONITOR_REGISTRY_{host}_USER and _PASS have to be set together, or only _TOKEN"
+                ),
+            };
+            (host, credentials)
+        })
+        .collect()
+}
+
+#[instrument(skip(state))]
+async fn metrics(State(state): State<Arc<AppState>>) -> Response {
+    if state.client.is_stale() {
+        tracing::warn!("Refusing to serve stale Metrics");
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "The last completed Check is older than the freshness budget",
+        )
+            .into_response();
+    }
+
+    match state.client.encode_metrics() {
+        Ok(body) => ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body).into_response(),
+        Err(e) => {
+            tracing::error!("Encoding Metrics: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Encoding the Metrics failed",
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn healthz() -> StatusCode {
+    StatusCode::OK
+}
+
+#[instrument(skip(state))]
+async fn readyz(State(state): State<Arc<AppState>>) -> Response {
+    if !state.client.is_ready() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The first Check has not completed yet",
+        )
+            .into_response();
+    }
+
+    StatusCode::OK.into_response()
 }
 
 #[instrument(skip(state))]
-async fn metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    state.client.get_metrics()
+async fn fingerprint(State(state): State<Arc<AppState>>) -> String {
+    state.client.fingerprint()
+}
+
+#[instrument(skip(state))]
+async fn clear_cache(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let cleared = state.client.clear_cache();
+
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        serde_json::to_string(&cleared).expect("Summary is always serializable"),
+    )
+}
+
+#[instrument(skip(state))]
+async fn trigger_check(State(state): State<Arc<AppState>>) -> StatusCode {
+    // A triggered Check would only be skipped while paused
+    if state.client.is_paused() {
+        return StatusCode::CONFLICT;
+    }
+
+    state.client.trigger_check();
+
+    StatusCode::ACCEPTED
+}
+
+#[derive(Debug, Deserialize)]
+struct PauseQuery {
+    /// After how many Seconds the Checks resume on their own
+    resume_after: Option<u64>,
+}
+
+#[instrument(skip(state))]
+async fn pause(State(state): State<Arc<AppState>>, Query(query): Query<PauseQuery>) -> StatusCode {
+    state
+        .client
+        .pause(query.resume_after.map(std::time::Duration::from_secs));
+
+    StatusCode::NO_CONTENT
+}
+
+#[instrument(skip(state))]
+async fn resume(State(state): State<Arc<AppState>>) -> StatusCode {
+    state.client.resume();
+
+    StatusCode::NO_CONTENT
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusQuery {
+    #[serde(default)]
+    format: StatusFormat,
+}
+
+#[instrument(skip(state))]
+async fn status(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<StatusQuery>,
+) -> impl IntoResponse {
+    let statuses = state.client.status();
+
+    (
+        [(header::CONTENT_TYPE, query.format.content_type())],
+        query.format.render(&statuses),
+    )
+}
+
+#[instrument(skip(state))]
+async fn task_detail(
+    State(state): State<Arc<AppState>>,
+    Path((job, task)): Path<(String, String)>,
+) -> Response {
+    let details = state.client.task_detail(&job, &task);
+    if details.is_empty() {
+        return (StatusCode::NOT_FOUND, "Unknown Task").into_response();
+    }
+
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        serde_json::to_string(&details).expect("Details are always serializable"),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serves the Router on a random local Port and returns its Base-URL
+    fn serve(app: Router) -> String {
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(app.into_make_service());
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        url
+    }
+
+    /// Waits for the Condition to hold, failing the Test after 5 Seconds
+    async fn wait_until(condition: impl Fn() -> bool) {
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while !condition() {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("Waiting for the Condition");
+    }
+
+    /// Lets Nomad list the Jobs and return each of them when it is read by its Name
+    async fn mount_jobs(server: &wiremock::MockServer, jobs: Vec<serde_json::Value>) {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, ResponseTemplate,
+        };
+
+        let list: Vec<_> = jobs
+            .iter()
+            .map(|job| serde_json::json!({ "ID": job["Name"] }))
+            .collect();
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(list))
+            .mount(server)
+            .await;
+        for job in jobs {
+            let name = job["Name"].as_str().expect("Jobs have a Name").to_string();
+            Mock::given(method("GET"))
+                .and(path(format!("/v1/job/{name}")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(job))
+                .mount(server)
+                .await;
+        }
+    }
+
+    #[test]
+    fn credentials_from_env() {
+        let vars = [
+            ("VMONITOR_REGISTRY_GHCR_IO_USER", "user"),
+            ("VMONITOR_REGISTRY_GHCR_IO_PASS", "secret"),
+            ("VMONITOR_REGISTRY_REGISTRY_5000_TOKEN", "token"),
+            ("VMONITOR_REGISTRY_URL", "unrelated"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+
+        let config = Config {
+            registry_credentials: registry_credentials(vars),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Some(&RegistryCredentials::Basic {
+                username: "user".to_string(),
+                password: "secret".to_string(),
+            }),
+            config.credentials_for("ghcr.io")
+        );
+        assert_eq!(
+            Some(&RegistryCredentials::Token("token".to_string())),
+            config.credentials_for("registry:5000")
+        );
+        assert_eq!(None, config.credentials_for("quay.io"));
+    }
+
+    #[tokio::test]
+    async fn metrics_failures() {
+        /// Collects a Metric-Family without a Name, which can not be encoded
+        struct Nameless;
+
+        impl prometheus::core::Collector for Nameless {
+            fn desc(&self) -> Vec<&prometheus::core::Desc> {
+                Vec::new()
+            }
+
+            fn collect(&self) -> Vec<prometheus::proto::MetricFamily> {
+                let mut family = prometheus::proto::MetricFamily::new();
+                family.mut_metric().push(prometheus::proto::Metric::new());
+                vec![family]
+            }
+        }
+
+        let status = |client: Client| {
+            let url = serve(router(Arc::new(client), DEFAULT_METRICS_PATH, None));
+            async move {
+                reqwest::get(format!("{url}/metrics"))
+                    .await
+                    .unwrap()
+                    .status()
+            }
+        };
+
+        assert_eq!(StatusCode::OK, status(Client::new("http://nomad")).await);
+
+        let stale = Client::new("http://nomad").with_config(Config {
+            freshness_budget: Some(std::time::Duration::ZERO),
+            ..Default::default()
+        });
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, status(stale).await);
+
+        let registry = prometheus::Registry::new();
+        registry.register(Box::new(Nameless)).unwrap();
+        let broken = Client::with_registry("http://nomad", registry).unwrap();
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, status(broken).await);
+    }
+
+    #[tokio::test]
+    async fn health_and_readiness() {
+        use wiremock::MockServer;
+
+        let nomad = MockServer::start().await;
+        mount_jobs(&nomad, Vec::new()).await;
+
+        let client = Arc::new(Client::new(nomad.uri()));
+        let url = serve(router(client.clone(), DEFAULT_METRICS_PATH, None));
+        let status = |endpoint: &'static str| {
+            let url = format!("{url}{endpoint}");
+            async move { reqwest::get(url).await.unwrap().status() }
+        };
+
+        assert_eq!(StatusCode::OK, status("/healthz").await);
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status("/readyz").await);
+
+        let runner = tokio::spawn(client.clone().run());
+        wait_until(|| client.is_ready()).await;
+        runner.abort();
+
+        assert_eq!(StatusCode::OK, status("/healthz").await);
+        assert_eq!(StatusCode::OK, status("/readyz").await);
+    }
+
+    #[tokio::test]
+    async fn triggered_check() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let nomad = MockServer::start().await;
+        mount_jobs(&nomad, Vec::new()).await;
+
+        let client = Arc::new(Client::new(nomad.uri()).with_config(Config {
+            check_interval: std::time::Duration::from_secs(3600),
+            empty_retry_interval: std::time::Duration::from_secs(3600),
+            raw_exec: RawExecBehaviour::NotTrackable,
+            ..Default::default()
+        }));
+        let url = serve(router(
+            client.clone(),
+            DEFAULT_METRICS_PATH,
+            Some("secret".to_string()),
+        ));
+        let runner = tokio::spawn(client.clone().run());
+        wait_until(|| client.is_ready()).await;
+
+        // The Job is only deployed after the first Check
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                    "ID": "script",
+                    "Name": "script",
+                    "TaskGroups": [{
+                        "Name": "group",
+                        "Tasks": [{ "Name": "task", "Driver": "raw_exec", "Config": {} }],
+                    }],
+                }])),
+            )
+            .with_priority(1)
+            .mount(&nomad)
+            .await;
+
+        let trigger = |token: Option<&'static str>| {
+            let mut req = reqwest::Client::new().post(format!("{url}/check"));
+            if let Some(token) = token {
+                req = req.bearer_auth(token);
+            }
+            async move { req.send().await.unwrap().status() }
+        };
+
+        assert_eq!(StatusCode::UNAUTHORIZED, trigger(None).await);
+        assert_eq!(StatusCode::UNAUTHORIZED, trigger(Some("wrong")).await);
+        assert_eq!(StatusCode::ACCEPTED, trigger(Some("secret")).await);
+
+        wait_until(|| client.get_metrics().contains(r#"job="script""#)).await;
+
+        // The Check would not run while paused
+        client.pause(None);
+        assert_eq!(StatusCode::CONFLICT, trigger(Some("secret")).await);
+        runner.abort();
+
+        // Without an Admin-Token the Endpoints are open to everyone
+        client.resume();
+        let open = serve(router(client, DEFAULT_METRICS_PATH, None));
+        let resp = reqwest::Client::new()
+            .post(format!("{open}/check"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::ACCEPTED, resp.status());
+    }
+
+    #[tokio::test]
+    async fn status_after_check() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        let host = server.address().to_string();
+        mount_jobs(
+            &server,
+            vec![serde_json::json!({
+                "Name": "web",
+                "ParentID": "",
+                "TaskGroups": [{
+                    "Name": "group",
+                    "Tasks": [{
+                        "Name": "app",
+                        "Driver": "docker",
+                        "Config": { "image": format!("{host}/user/app:1.0.0") },
+                    }],
+                }],
+            })],
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/user/app/tags/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "name": "user/app",
+                "tags": ["1.0.0", "1.1.0"],
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Arc::new(Client::new(server.uri()).with_config(Config {
+            insecure_registries: vec![host],
+            ..Default::default()
+        }));
+        let url = serve(router(client.clone(), DEFAULT_METRICS_PATH, None));
+        let runner = tokio::spawn(client.clone().run());
+        wait_until(|| client.is_ready()).await;
+        runner.abort();
+
+        let body = reqwest::get(format!("{url}/status"))
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        let statuses: Vec<serde_json::Value> =
+            serde_json::from_slice(&body).expect("Status is a JSON Array");
+        assert_eq!(
+            vec![serde_json::json!({
+                "namespace": "default",
+                "job": "web",
+                "group": "group",
+                "task": "app",
+                "up_to_date": false,
+                "current": "1.0.0",
+                "newest": "1.1.0",
+                "state": "out_of_date",
+            })],
+            statuses
+        );
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_server() {
+        let client = Arc::new(Client::new("http://localhost:4646"));
+        let (trigger, shutdown) = tokio::sync::oneshot::channel::<()>();
+
+        let server = tokio::spawn(serve_until(
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+            router(client, DEFAULT_METRICS_PATH, None),
+            async {
+                let _ = shutdown.await;
+            },
+        ));
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!server.is_finished());
+
+        trigger.send(()).unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .expect("Server stops after the Shutdown")
+            .unwrap();
+    }
+
+    #[test]
+    fn configured_listen_addr() {
+        assert_eq!(
+            SocketAddr::from(([0, 0, 0, 0], 3000)),
+            listen_addr(None).unwrap()
+        );
+        assert_eq!(
+            SocketAddr::from(([127, 0, 0, 1], 8080)),
+            listen_addr(Some("127.0.0.1:8080".to_string())).unwrap()
+        );
+        assert_eq!(
+            "[::1]:9000".parse::<SocketAddr>().unwrap(),
+            listen_addr(Some("[::1]:9000".to_string())).unwrap()
+        );
+
+        for invalid in [
+            "",
+            "localhost:3000",
+            "0.0.0.0",
+            "0.0.0.0:http",
+            "0.0.0.0:70000",
+        ] {
+            assert!(
+                listen_addr(Some(invalid.to_string())).is_err(),
+                "{invalid:?} was accepted"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn configured_metrics_path() {
+        let client = Arc::new(Client::new("http://localhost:4646"));
+
+        let default_url = serve(router(client.clone(), &metrics_path(None).unwrap(), None));
+        let resp = reqwest::get(format!("{default_url}/metrics"))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(
+            "text/plain; version=0.0.4",
+            resp.headers()[header::CONTENT_TYPE].to_str().unwrap()
+        );
+
+        let path = metrics_path(Some("/nomad-vmonitor/metrics".to_string())).unwrap();
+        let custom_url = serve(router(client, &path, None));
+        let resp = reqwest::get(format!("{custom_url}/nomad-vmonitor/metrics"))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = reqwest::get(format!("{custom_url}/metrics")).await.unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+        assert!(metrics_path(Some("metrics".to_string())).is_err());
+    }
 }
@@ -1,26 +1,92 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::Duration,
+};
+
+use crate::{
+    config::{AttentionState, UrgencyWeights},
+    docker::Version,
+    select::{CandidateCounts, MirrorStatus},
+    state::{ComparedVersions, TaskKey},
+};
 
 #[derive(Debug)]
 pub struct Metrics {
     up_to_date: prometheus::GaugeVec,
     out_of_date: prometheus::GaugeVec,
-    versions: prometheus::GaugeVec,
+    floating: prometheus::GaugeVec,
+    digest_pinned: prometheus::GaugeVec,
+    not_trackable: prometheus::GaugeVec,
+    deployed_digest: prometheus::GaugeVec,
+    digest_changed: prometheus::GaugeVec,
+    mirror_newest: prometheus::GaugeVec,
+    mirror_lagging: prometheus::GaugeVec,
+    newest_regressed: prometheus::GaugeVec,
+    candidates_before: prometheus::GaugeVec,
+    candidates_after: prometheus::GaugeVec,
+    newer_versions: prometheus::GaugeVec,
+    version_info: prometheus::GaugeVec,
+    needs_attention: prometheus::IntGauge,
+    monitored_tasks: prometheus::IntGauge,
+    urgency: prometheus::GaugeVec,
+    urgency_total: prometheus::Gauge,
+    fingerprint: prometheus::GaugeVec,
+    nomad_out_of_date: prometheus::GaugeVec,
+    job_read_failed: prometheus::GaugeVec,
+    push_failures: prometheus::Counter,
+    check_errors: prometheus::CounterVec,
+    check_duration: prometheus::Histogram,
+    skipped_tasks: prometheus::CounterVec,
+    paused: prometheus::IntGauge,
+    last_check: prometheus::Gauge,
+    registry_credentials: prometheus::GaugeVec,
 }
 
-#[derive(Debug)]
+/// Metrics about the Requests sent to the Nomad API
+#[derive(Debug, Clone)]
+pub struct NomadRequestMetrics {
+    duration: prometheus::HistogramVec,
+    responses: prometheus::IntCounterVec,
+}
+
+/// Metrics about the Images run by the individual Allocations
+#[derive(Debug, Clone)]
+pub struct AllocationMetrics {
+    images: prometheus::GaugeVec,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum UpdatedVersion {
-    UpToDate { version: String },
-    OutOfDate { current: String, newest: String },
+    UpToDate {
+        version: String,
+    },
+    OutOfDate {
+        current: String,
+        newest: String,
+        /// How many Versions newer than the current one are available
+        newer: usize,
+    },
+    Floating {
+        tag: String,
+    },
+    DigestPinned {
+        digest: String,
+        newest: String,
+    },
+    /// The Task has no Image whose Version could be compared
+    NotTrackable,
 }
 
 impl Metrics {
-    pub fn new(reg: &prometheus::Registry) -> Self {
+    /// Creates the Metrics and registers them in the given Registry, fails if any of them
+    /// are already registered
+    pub fn new(reg: &prometheus::Registry) -> Result<Self, prometheus::Error> {
         let uptodate = prometheus::GaugeVec::new(
             prometheus::Opts::new(
                 "up_to_date",
                 "The Jobs/Tasks that are up to date will be set to 1 others to 0",
             ),
-            &["job", "group", "task"],
+            &["namespace", "job", "group", "task", "owner"],
         )
         .unwrap();
 
@@ -29,40 +95,492 @@ impl Metrics {
                 "out_of_date",
                 "The Jobs/Tasks that are out of date will be set to 0 others to 1",
             ),
-            &["job", "group", "task"],
+            &["namespace", "job", "group", "task", "owner"],
+        )
+        .unwrap();
+
+        let floating = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "floating",
+                "The Jobs/Tasks that use a floating branch Tag will be set to 1 others to 0",
+            ),
+            &["namespace", "job", "group", "task", "owner", "tag"],
+        )
+        .unwrap();
+
+        let digest_pinned = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "digest_pinned",
+                "The Jobs/Tasks that are pinned by Digest with the newest Version in their tracked line",
+            ),
+            &["namespace", "job", "group", "task", "owner", "digest", "newest"],
+        )
+        .unwrap();
+
+        let not_trackable = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "not_trackable",
+                "The Jobs/Tasks without an Image whose Version could be compared will be set to 1 others to 0",
+            ),
+            &["namespace", "job", "group", "task", "owner"],
         )
         .unwrap();
 
-        let versions = prometheus::GaugeVec::new(
+        let deployed_digest = prometheus::GaugeVec::new(
             prometheus::Opts::new(
-                "versions",
-                "The current and newest possible Versions for the Jobs/Tasks",
+                "deployed_digest_info",
+                "The Digest the deployed Tag of the Jobs/Tasks currently resolves to",
             ),
-            &["job", "group", "task", "current", "newest"],
+            &["namespace", "job", "group", "task", "tag", "digest"],
         )
         .unwrap();
 
-        reg.register(Box::new(uptodate.clone())).unwrap();
-        reg.register(Box::new(out_of_date.clone())).unwrap();
-        reg.register(Box::new(versions.clone())).unwrap();
+        let digest_changed = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "deployed_digest_changed",
+                "The Jobs/Tasks whose deployed Tag resolved to a different Digest than in the previous Check will be set to 1 others to 0",
+            ),
+            &["namespace", "job", "group", "task"],
+        )
+        .unwrap();
 
-        Self {
+        let mirror_newest = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "mirror_newest",
+                "The newest Version of mirrored Jobs/Tasks available in the Mirror and Upstream",
+            ),
+            &["namespace", "job", "group", "task", "mirror", "upstream"],
+        )
+        .unwrap();
+
+        let mirror_lagging = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "mirror_lagging",
+                "The mirrored Jobs/Tasks whose Upstream has a newer Version than the Mirror will be set to 1 others to 0",
+            ),
+            &["namespace", "job", "group", "task"],
+        )
+        .unwrap();
+
+        let newest_regressed = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "registry_newest_regressed",
+                "The Repositories whose newest available Version is older than in the previous Check, like when a Tag was deleted upstream, will be set to 1 others to 0",
+            ),
+            &["repository"],
+        )
+        .unwrap();
+
+        let candidates_before = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "candidates_before_filter",
+                "The Number of Tags of the Image of the Jobs/Tasks before applying the Filter",
+            ),
+            &["namespace", "job", "group", "task", "image"],
+        )
+        .unwrap();
+
+        let candidates_after = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "candidates_after_filter",
+                "The Number of Tags of the Image of the Jobs/Tasks that are left as Candidates by the Filter",
+            ),
+            &["namespace", "job", "group", "task", "image"],
+        )
+        .unwrap();
+
+        let newer_versions = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "newer_versions_available",
+                "The Number of Versions newer than the current one of the Jobs/Tasks",
+            ),
+            &["namespace", "job", "group", "task", "owner"],
+        )
+        .unwrap();
+
+        // Follows the Info-Metric Convention, the Series is always 1 and only carries the
+        // Versions as Labels, whether the Task is out of date is exported in `out_of_date`
+        let version_info = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "task_version_info",
+                "The current and newest possible Versions for the Jobs/Tasks, always set to 1",
+            ),
+            &[
+                "namespace",
+                "job",
+                "group",
+                "task",
+                "owner",
+                "current",
+                "newest",
+            ],
+        )
+        .unwrap();
+
+        let push_failures = prometheus::Counter::new(
+            "pushgateway_push_failures_total",
+            "The Number of times pushing the Metrics to the Pushgateway failed",
+        )
+        .unwrap();
+
+        let needs_attention = prometheus::IntGauge::new(
+            "needs_attention_total",
+            "The Number of Tasks in one of the States configured as needing attention",
+        )
+        .unwrap();
+
+        let nomad_out_of_date = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "nomad_out_of_date",
+                "Set to 1 if a newer Nomad Release than the one running is available, 0 otherwise",
+            ),
+            &["current", "newest"],
+        )
+        .unwrap();
+
+        let job_read_failed = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "job_read_failed",
+                "The Jobs that could not be read from Nomad in the last Check will be set to 1",
+            ),
+            &["job"],
+        )
+        .unwrap();
+
+        let urgency = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "update_urgency_score",
+                "How urgently the Jobs/Tasks should be updated, weighted by how far they are behind",
+            ),
+            &["namespace", "job", "group", "task", "owner"],
+        )
+        .unwrap();
+        let urgency_total = prometheus::Gauge::new(
+            "update_urgency_score_total",
+            "The sum of the update_urgency_score of all Tasks",
+        )
+        .unwrap();
+
+        let fingerprint = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "monitored_fingerprint",
+                "Set to 1 for the Hash over the Jobs/Tasks and their Images monitored in the last Check",
+            ),
+            &["fingerprint"],
+        )
+        .unwrap();
+
+        let monitored_tasks = prometheus::IntGauge::new(
+            "monitored_tasks_total",
+            "The Number of Tasks processed in the last Check",
+        )
+        .unwrap();
+
+        let paused = prometheus::IntGauge::new(
+            "monitoring_paused",
+            "Set to 1 while the Checks are paused, like during Maintenance",
+        )
+        .unwrap();
+
+        let check_errors = prometheus::CounterVec::new(
+            prometheus::Opts::new(
+                "check_errors_total",
+                "The Number of Failures during the Checks by the Stage they occurred in, like `list_jobs` or `get_tags`",
+            ),
+            &["stage"],
+        )
+        .unwrap();
+
+        let check_duration = prometheus::Histogram::with_opts(
+            prometheus::HistogramOpts::new(
+                "check_duration_seconds",
+                "The Duration of the Checks, from listing the Jobs until all Tasks were compared",
+            )
+            .buckets(vec![1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0]),
+        )
+        .unwrap();
+
+        let skipped_tasks = prometheus::CounterVec::new(
+            prometheus::Opts::new(
+                "skipped_tasks_total",
+                "The Number of times a Task could not be compared by the Reason, like `version_parse` or `no_valid_tags`",
+            ),
+            &["reason"],
+        )
+        .unwrap();
+
+        let last_check = prometheus::Gauge::new(
+            "last_check_timestamp_seconds",
+            "The Unix Timestamp at which the last successful Check completed",
+        )
+        .unwrap();
+
+        let registry_credentials = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "registry_credentials_configured",
+                "Set to 1 for every Registry that Credentials are configured for, without exposing them",
+            ),
+            &["registry"],
+        )
+        .unwrap();
+
+        reg.register(Box::new(uptodate.clone()))?;
+        reg.register(Box::new(out_of_date.clone()))?;
+        reg.register(Box::new(floating.clone()))?;
+        reg.register(Box::new(digest_pinned.clone()))?;
+        reg.register(Box::new(not_trackable.clone()))?;
+        reg.register(Box::new(deployed_digest.clone()))?;
+        reg.register(Box::new(digest_changed.clone()))?;
+        reg.register(Box::new(mirror_newest.clone()))?;
+        reg.register(Box::new(mirror_lagging.clone()))?;
+        reg.register(Box::new(newest_regressed.clone()))?;
+        reg.register(Box::new(candidates_before.clone()))?;
+        reg.register(Box::new(candidates_after.clone()))?;
+        reg.register(Box::new(newer_versions.clone()))?;
+        reg.register(Box::new(version_info.clone()))?;
+        reg.register(Box::new(nomad_out_of_date.clone()))?;
+        reg.register(Box::new(job_read_failed.clone()))?;
+        reg.register(Box::new(needs_attention.clone()))?;
+        reg.register(Box::new(urgency.clone()))?;
+        reg.register(Box::new(urgency_total.clone()))?;
+        reg.register(Box::new(fingerprint.clone()))?;
+        reg.register(Box::new(push_failures.clone()))?;
+        reg.register(Box::new(monitored_tasks.clone()))?;
+        reg.register(Box::new(paused.clone()))?;
+        reg.register(Box::new(check_errors.clone()))?;
+        reg.register(Box::new(check_duration.clone()))?;
+        reg.register(Box::new(skipped_tasks.clone()))?;
+        reg.register(Box::new(last_check.clone()))?;
+        reg.register(Box::new(registry_credentials.clone()))?;
+
+        Ok(Self {
             up_to_date: uptodate,
             out_of_date,
-            versions,
-        }
+            floating,
+            digest_pinned,
+            not_trackable,
+            deployed_digest,
+            digest_changed,
+            mirror_newest,
+            mirror_lagging,
+            newest_regressed,
+            candidates_before,
+            candidates_after,
+            newer_versions,
+            version_info,
+            needs_attention,
+            monitored_tasks,
+            urgency,
+            urgency_total,
+            fingerprint,
+            nomad_out_of_date,
+            job_read_failed,
+            push_failures,
+            check_errors,
+            check_duration,
+            skipped_tasks,
+            paused,
+            last_check,
+            registry_credentials,
+        })
     }
 
+    /// Resets the Metrics that are recomputed in every Check, the Metrics of the Tasks are only
+    /// changed through [`set`](Self::set) and [`remove`](Self::remove)
     pub fn clear(&self) {
-        self.out_of_date.reset();
-        self.up_to_date.reset();
-        self.versions.reset();
+        self.deployed_digest.reset();
+        self.digest_changed.reset();
+        self.mirror_newest.reset();
+        self.mirror_lagging.reset();
+        self.newest_regressed.reset();
+        self.candidates_before.reset();
+        self.candidates_after.reset();
+        self.urgency.reset();
+        self.job_read_failed.reset();
+    }
+
+    pub fn push_failed(&self) {
+        self.push_failures.inc();
+    }
+
+    /// Counts a Failure during a Check in the given Stage, like `get_tags`
+    pub fn check_failed(&self, stage: &str) {
+        self.check_errors.with_label_values(&[stage]).inc();
+    }
+
+    pub fn update_nomad(&self, current: &str, newest: &str, out_of_date: bool) {
+        self.nomad_out_of_date.reset();
+        self.nomad_out_of_date
+            .with_label_values(&[current, newest])
+            .set(if out_of_date { 1.0 } else { 0.0 });
+    }
+
+    /// Marks the Jobs, by their ID, that could not be read in the last Check
+    pub fn set_read_failures(&self, jobs: &[String]) {
+        for job in jobs {
+            self.job_read_failed.with_label_values(&[job]).set(1.0);
+        }
+    }
+
+    pub fn set_needs_attention(&self, count: usize) {
+        self.needs_attention.set(count as i64);
+    }
+
+    /// Sets the Number of Tasks processed in the last Check, which is not reset by
+    /// [`clear`](Self::clear) so it doesn't drop to 0 during a Check
+    pub fn set_monitored_tasks(&self, count: usize) {
+        self.monitored_tasks.set(count as i64);
+    }
+
+    pub fn set_urgency(&self, key: &TaskKey, owner: &str, score: f64) {
+        self.urgency
+            .with_label_values(&[&key.namespace, &key.job, &key.group, &key.task, owner])
+            .set(score);
+    }
+
+    pub fn set_fingerprint(&self, fingerprint: &str) {
+        self.fingerprint.reset();
+        self.fingerprint.with_label_values(&[fingerprint]).set(1.0);
+    }
+
+    /// Marks the Registries, by the Host they are configured with, that have Credentials
+    pub fn set_registry_credentials<'a, I>(&self, registries: I)
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        self.registry_credentials.reset();
+        for registry in registries {
+            self.registry_credentials
+                .with_label_values(&[registry])
+                .set(1.0);
+        }
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.set(i64::from(paused));
+    }
+
+    /// Counts a Task that could not be compared for the given Reason, like `templated_image`
+    pub fn task_skipped(&self, reason: &str) {
+        self.skipped_tasks.with_label_values(&[reason]).inc();
+    }
+
+    pub fn observe_check(&self, duration: std::time::Duration) {
+        self.check_duration.observe(duration.as_secs_f64());
+    }
+
+    /// Records that a Check completed successfully just now
+    pub fn set_last_check(&self) {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        self.last_check.set(now.as_secs_f64());
+    }
+
+    pub fn set_urgency_total(&self, score: f64) {
+        self.urgency_total.set(score);
+    }
+
+    pub fn update_digest(&self, key: &TaskKey, tag: &str, digest: &str, changed: bool) {
+        self.deployed_digest
+            .with_label_values(&[&key.namespace, &key.job, &key.group, &key.task, tag, digest])
+            .set(1.0);
+        self.digest_changed
+            .with_label_values(&[&key.namespace, &key.job, &key.group, &key.task])
+            .set(if changed { 1.0 } else { 0.0 });
     }
 
-    pub fn update(&self, job: &str, group: &str, task: &str, version: UpdatedVersion) {
-        let labels = [("job", job), ("group", group), ("task", task)]
-            .into_iter()
-            .collect::<HashMap<&str, &str>>();
+    pub fn set_newest_regressed(&self, repository: &str, regressed: bool) {
+        self.newest_regressed
+            .with_label_values(&[repository])
+            .set(if regressed { 1.0 } else { 0.0 });
+    }
+
+    pub fn update_candidates(&self, key: &TaskKey, counts: &CandidateCounts) {
+        let labels = [
+            key.namespace.as_str(),
+            &key.job,
+            &key.group,
+            &key.task,
+            &counts.image,
+        ];
+
+        self.candidates_before
+            .with_label_values(&labels)
+            .set(counts.before as f64);
+        self.candidates_after
+            .with_label_values(&labels)
+            .set(counts.after as f64);
+    }
+
+    pub fn update_mirror(&self, key: &TaskKey, status: &MirrorStatus) {
+        let format = |v: &Option<crate::docker::Version>| match v {
+            Some(v) => format!("{v}"),
+            None => String::new(),
+        };
+
+        self.mirror_newest
+            .with_label_values(&[
+                &key.namespace,
+                &key.job,
+                &key.group,
+                &key.task,
+                &format(&status.mirror_newest),
+                &format(&status.upstream_newest),
+            ])
+            .set(1.0);
+        self.mirror_lagging
+            .with_label_values(&[&key.namespace, &key.job, &key.group, &key.task])
+            .set(if status.lagging() { 1.0 } else { 0.0 });
+    }
+
+    /// Removes all the Series that [`set`](Self::set) created for the Version of the Task
+    pub fn remove(&self, key: &TaskKey, owner: &str, version: &UpdatedVersion) {
+        let (namespace, job, group, task): (&str, &str, &str, &str) =
+            (&key.namespace, &key.job, &key.group, &key.task);
+        let labels = [namespace, job, group, task, owner];
+        // The Series might not exist, in which case there is nothing to remove
+        let _ = self.up_to_date.remove_label_values(&labels);
+        let _ = self.out_of_date.remove_label_values(&labels);
+
+        let _ = match version {
+            UpdatedVersion::UpToDate { version } => {
+                let _ = self.newer_versions.remove_label_values(&labels);
+                self.version_info
+                    .remove_label_values(&[namespace, job, group, task, owner, version, version])
+            }
+            UpdatedVersion::OutOfDate {
+                current, newest, ..
+            } => {
+                let _ = self.newer_versions.remove_label_values(&labels);
+                self.version_info
+                    .remove_label_values(&[namespace, job, group, task, owner, current, newest])
+            }
+            UpdatedVersion::Floating { tag } => self
+                .floating
+                .remove_label_values(&[namespace, job, group, task, owner, tag]),
+            UpdatedVersion::DigestPinned { digest, newest } => self
+                .digest_pinned
+                .remove_label_values(&[namespace, job, group, task, owner, digest, newest]),
+            UpdatedVersion::NotTrackable => self.not_trackable.remove_label_values(&labels),
+        };
+    }
+
+    /// Sets the Metrics for the Task, labeled with the `owner` responsible for its Job which is
+    /// empty if there is none. The Series of a previous Version have to be
+    /// [`remove`](Self::remove)d first
+    pub fn set(&self, key: &TaskKey, owner: &str, version: UpdatedVersion) {
+        let (namespace, job, group, task): (&str, &str, &str, &str) =
+            (&key.namespace, &key.job, &key.group, &key.task);
+        let labels = [
+            ("namespace", namespace),
+            ("job", job),
+            ("group", group),
+            ("task", task),
+            ("owner", owner),
+        ]
+        .into_iter()
+        .collect::<HashMap<&str, &str>>();
 
         let uptodate_metric = self.up_to_date.get_metric_with(&labels).unwrap();
         let outofdate_metric = self.out_of_date.get_metric_with(&labels).unwrap();
@@ -71,13 +589,19 @@ impl Metrics {
             UpdatedVersion::UpToDate { version } => {
                 uptodate_metric.set(1.0);
                 outofdate_metric.set(0.0);
+                self.newer_versions
+                    .get_metric_with(&labels)
+                    .unwrap()
+                    .set(0.0);
 
-                self.versions
+                self.version_info
                     .get_metric_with(
                         &[
+                            ("namespace", namespace),
                             ("job", job),
                             ("group", group),
                             ("task", task),
+                            ("owner", owner),
                             ("current", &version),
                             ("newest", &version),
                         ]
@@ -87,16 +611,26 @@ impl Metrics {
                     .unwrap()
                     .set(1.0);
             }
-            UpdatedVersion::OutOfDate { current, newest } => {
+            UpdatedVersion::OutOfDate {
+                current,
+                newest,
+                newer,
+            } => {
                 uptodate_metric.set(0.0);
                 outofdate_metric.set(1.0);
+                self.newer_versions
+                    .get_metric_with(&labels)
+                    .unwrap()
+                    .set(newer as f64);
 
-                self.versions
+                self.version_info
                     .get_metric_with(
                         &[
+                            ("namespace", namespace),
                             ("job", job),
                             ("group", group),
                             ("task", task),
+                            ("owner", owner),
                             ("current", &current),
                             ("newest", &newest),
                         ]
@@ -106,6 +640,448 @@ impl Metrics {
                     .unwrap()
                     .set(1.0);
             }
+            UpdatedVersion::Floating { tag } => {
+                uptodate_metric.set(0.0);
+                outofdate_metric.set(0.0);
+
+                self.floating
+                    .get_metric_with(
+                        &[
+                            ("namespace", namespace),
+                            ("job", job),
+                            ("group", group),
+                            ("task", task),
+                            ("owner", owner),
+                            ("tag", &tag),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    )
+                    .unwrap()
+                    .set(1.0);
+            }
+            UpdatedVersion::DigestPinned { digest, newest } => {
+                uptodate_metric.set(0.0);
+                outofdate_metric.set(0.0);
+
+                self.digest_pinned
+                    .get_metric_with(
+                        &[
+                            ("namespace", namespace),
+                            ("job", job),
+                            ("group", group),
+                            ("task", task),
+                            ("owner", owner),
+                            ("digest", &digest),
+                            ("newest", &newest),
+                        ]
+                        .into_iter()
+                        .collect(),
+                    )
+                    .unwrap()
+                    .set(1.0);
+            }
+            UpdatedVersion::NotTrackable => {
+                uptodate_metric.set(0.0);
+                outofdate_metric.set(0.0);
+
+                self.not_trackable
+                    .get_metric_with(&labels)
+                    .unwrap()
+                    .set(1.0);
+            }
         };
     }
 }
+
+/// Scores how urgently an out of date Task should be updated, based on the most significant part
+/// of the Version it is behind by, with `newer` being the number of Versions it is behind.
+///
+/// The Score is linear per Component: being behind by two minor Versions scores twice as much as
+/// one. The Components of calendar based Versions are weighted like semantic ones, the year like
+/// the major Version, the next one like the minor Version and all further ones like the patch.
+pub fn urgency_score(weights: &UrgencyWeights, compared: &ComparedVersions, newer: usize) -> f64 {
+    let components = |version: &Version| match version {
+        Version::Semantic {
+            major,
+            minor,
+            patch,
+            ..
+        } => Some(vec![*major, minor.unwrap_or(0), patch.unwrap_or(0)]),
+        Version::CalVer { components, .. } => Some(components.clone()),
+        Version::Latest | Version::Named(_) => None,
+    };
+
+    match (&compared.current, &compared.newest) {
+        (Version::Semantic { .. }, Version::Semantic { .. })
+        | (Version::CalVer { .. }, Version::CalVer { .. }) => {
+            let current = components(&compared.current).unwrap_or_default();
+            let newest = components(&compared.newest).unwrap_or_default();
+            let part = |parts: &[usize], index: usize| parts.get(index).copied().unwrap_or(0);
+            let weight = |index: usize| match index {
+                0 => weights.major,
+                1 => weights.minor,
+                _ => weights.patch,
+            };
+
+            // Versions that only differ in their Pre-Release are behind by at least a patch
+            (0..current.len().max(newest.len()))
+                .find(|index| part(&current, *index) != part(&newest, *index))
+                .filter(|index| part(&newest, *index) > part(&current, *index))
+                .map(|index| weight(index) * (part(&newest, index) - part(&current, index)) as f64)
+                .unwrap_or(weights.patch)
+        }
+        // Named Versions have no distance, so every newer one counts like a patch
+        _ => weights.patch * newer as f64,
+    }
+}
+
+/// Adds the constant Labels, like `env="prod"`, to every Series of the gathered Metrics, Labels
+/// that a Series already has are kept as they are
+pub fn add_const_labels(
+    families: &mut [prometheus::proto::MetricFamily],
+    labels: &[(String, String)],
+) {
+    if labels.is_empty() {
+        return;
+    }
+
+    for metric in families.iter_mut().flat_map(|f| f.mut_metric().iter_mut()) {
+        for (name, value) in labels {
+            if metric.get_label().iter().any(|l| l.get_name() == name) {
+                continue;
+            }
+
+            let mut pair = prometheus::proto::LabelPair::new();
+            pair.set_name(name.clone());
+            pair.set_value(value.clone());
+            metric.mut_label().push(pair);
+        }
+
+        metric
+            .mut_label()
+            .sort_by(|a, b| a.get_name().cmp(b.get_name()));
+    }
+}
+
+/// Counts the Tasks that are in one of the given States, `current_missing` being the Tasks whose
+/// deployed Tag is no longer listed by the Registry and `unreachable` the Number of Tasks that
+/// could not be checked
+pub fn count_needs_attention<'a>(
+    states: &[AttentionState],
+    results: impl IntoIterator<Item = (&'a TaskKey, &'a UpdatedVersion)>,
+    current_missing: &BTreeSet<TaskKey>,
+    unreachable: usize,
+) -> usize {
+    let missing = states.contains(&AttentionState::CurrentMissing);
+    let checked = results
+        .into_iter()
+        .filter(|(key, version)| {
+            states.iter().any(|s| s.matches(version)) || (missing && current_missing.contains(key))
+        })
+        .count();
+
+    if states.contains(&AttentionState::Unreachable) {
+        checked + unreachable
+    } else {
+        checked
+    }
+}
+
+impl NomadRequestMetrics {
+    pub fn new(reg: &prometheus::Registry) -> Result<Self, prometheus::Error> {
+        let duration = prometheus::HistogramVec::new(
+            prometheus::HistogramOpts::new(
+                "nomad_request_duration_seconds",
+                "The Duration of Requests to the Nomad API until a Response was received",
+            ),
+            &["endpoint"],
+        )
+        .unwrap();
+
+        let responses = prometheus::IntCounterVec::new(
+            prometheus::Opts::new(
+                "nomad_requests_total",
+                "The Requests to the Nomad API by their Status-Code, `error` if no Response was received",
+            ),
+            &["endpoint", "status"],
+        )
+        .unwrap();
+
+        reg.register(Box::new(duration.clone()))?;
+        reg.register(Box::new(responses.clone()))?;
+
+        Ok(Self {
+            duration,
+            responses,
+        })
+    }
+
+    /// Records a Request to the given endpoint
+    pub fn observe(&self, endpoint: &str, elapsed: Duration, status: Option<reqwest::StatusCode>) {
+        self.duration
+            .with_label_values(&[endpoint])
+            .observe(elapsed.as_secs_f64());
+
+        let status = match status {
+            Some(s) => s.as_u16().to_string(),
+            None => "error".to_string(),
+        };
+        self.responses.with_label_values(&[endpoint, &status]).inc();
+    }
+}
+
+impl AllocationMetrics {
+    pub fn new(reg: &prometheus::Registry) -> Result<Self, prometheus::Error> {
+        let images = prometheus::GaugeVec::new(
+            prometheus::Opts::new(
+                "allocation_image",
+                "The Image run by the Tasks of every running Allocation will be set to 1",
+            ),
+            &["alloc", "job", "group", "task", "image"],
+        )
+        .unwrap();
+
+        reg.register(Box::new(images.clone()))?;
+
+        Ok(Self { images })
+    }
+
+    pub fn set(&self, alloc: &str, job: &str, group: &str, task: &str, image: &str) {
+        self.images
+            .with_label_values(&[alloc, job, group, task, image])
+            .set(1.0);
+    }
+
+    pub fn remove(&self, alloc: &str, job: &str, group: &str, task: &str, image: &str) {
+        let _ = self
+            .images
+            .remove_label_values(&[alloc, job, group, task, image]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::core::Collector;
+    use std::collections::BTreeMap;
+
+    fn key() -> TaskKey {
+        TaskKey {
+            namespace: "default".to_string(),
+            job: "job".to_string(),
+            group: "group".to_string(),
+            task: "task".to_string(),
+        }
+    }
+
+    #[test]
+    fn duplicate_registration() {
+        let reg = prometheus::Registry::new();
+
+        Metrics::new(&reg).expect("First Registration works");
+
+        assert!(matches!(
+            Metrics::new(&reg),
+            Err(prometheus::Error::AlreadyReg)
+        ));
+    }
+
+    #[test]
+    fn last_check_timestamp() {
+        let reg = prometheus::Registry::new();
+        let metrics = Metrics::new(&reg).unwrap();
+
+        metrics.set_last_check();
+        let first = metrics.last_check.get();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        metrics.set_last_check();
+        metrics.clear();
+        let second = metrics.last_check.get();
+
+        assert!(first > 1_600_000_000.0, "{first}");
+        assert!(second > first, "{second} <= {first}");
+    }
+
+    #[test]
+    fn version_info_exposition() {
+        use prometheus::Encoder;
+
+        let reg = prometheus::Registry::new();
+        let metrics = Metrics::new(&reg).unwrap();
+        metrics.set(
+            &key(),
+            "team",
+            UpdatedVersion::OutOfDate {
+                current: "1.0.0".to_string(),
+                newest: "1.1.0".to_string(),
+                newer: 1,
+            },
+        );
+
+        let mut buffer = Vec::new();
+        prometheus::TextEncoder::new()
+            .encode(&reg.gather(), &mut buffer)
+            .unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+
+        assert!(rendered.contains("# TYPE task_version_info gauge"));
+        assert!(rendered.contains(
+            r#"task_version_info{current="1.0.0",group="group",job="job",namespace="default",newest="1.1.0",owner="team",task="task"} 1"#
+        ), "{rendered}");
+        assert!(rendered.contains(
+            r#"out_of_date{group="group",job="job",namespace="default",owner="team",task="task"} 1"#
+        ));
+        assert!(rendered.contains(
+            r#"up_to_date{group="group",job="job",namespace="default",owner="team",task="task"} 0"#
+        ));
+    }
+
+    #[test]
+    fn same_job_in_namespaces() {
+        let reg = prometheus::Registry::new();
+        let metrics = Metrics::new(&reg).unwrap();
+        let other = TaskKey {
+            namespace: "team-a".to_string(),
+            ..key()
+        };
+
+        let out_of_date = UpdatedVersion::OutOfDate {
+            current: "1.0.0".to_string(),
+            newest: "1.1.0".to_string(),
+            newer: 1,
+        };
+        metrics.set(
+            &key(),
+            "",
+            UpdatedVersion::UpToDate {
+                version: "1.1.0".to_string(),
+            },
+        );
+        metrics.set(&other, "", out_of_date.clone());
+        assert_eq!(2, metrics.out_of_date.collect()[0].get_metric().len());
+
+        metrics.remove(&other, "", &out_of_date);
+        let remaining = metrics.out_of_date.collect();
+        assert_eq!(1, remaining[0].get_metric().len());
+        assert_eq!(0.0, remaining[0].get_metric()[0].get_gauge().get_value());
+    }
+
+    #[test]
+    fn urgency_by_distance() {
+        let weights = UrgencyWeights::default();
+        let parse = |raw: &str| {
+            crate::docker::RawTag::new(raw)
+                .parse_version_ignore_case()
+                .unwrap()
+        };
+        let behind = |current: &str, newest: &str| ComparedVersions {
+            current: parse(current),
+            newest: parse(newest),
+        };
+
+        let majors = urgency_score(&weights, &behind("1.4.2", "4.0.0"), 1);
+        let patch = urgency_score(&weights, &behind("1.4.2", "1.4.3"), 1);
+        assert!(majors > patch, "{majors} > {patch}");
+        assert_eq!(300.0, majors);
+        assert_eq!(1.0, patch);
+        assert_eq!(20.0, urgency_score(&weights, &behind("1.4.2", "1.6.0"), 1));
+        assert_eq!(
+            20.0,
+            urgency_score(&weights, &behind("V1.4.2", "v1.6.0"), 1),
+            "Independent of how the Versions are written"
+        );
+
+        // Calendar based Versions are scored by the Component they are behind by
+        assert_eq!(
+            200.0,
+            urgency_score(&weights, &behind("2022.05", "2024.01"), 1)
+        );
+        assert_eq!(
+            30.0,
+            urgency_score(&weights, &behind("2024.01", "2024.04"), 1)
+        );
+        assert_eq!(
+            14.0,
+            urgency_score(&weights, &behind("2024.04.01", "2024.04.15"), 1)
+        );
+
+        let channels = ComparedVersions {
+            current: Version::Named("stable".to_string()),
+            newest: Version::Named("beta".to_string()),
+        };
+        assert_eq!(1.0, urgency_score(&weights, &channels, 1));
+    }
+
+    #[test]
+    fn needs_attention_mixed_states() {
+        let task = |name: &str| TaskKey {
+            task: name.to_string(),
+            ..key()
+        };
+        let results = BTreeMap::from([
+            (
+                task("up-to-date"),
+                UpdatedVersion::UpToDate {
+                    version: "1.2.3".to_string(),
+                },
+            ),
+            (
+                task("latest"),
+                UpdatedVersion::UpToDate {
+                    version: "latest".to_string(),
+                },
+            ),
+            (
+                task("out-of-date"),
+                UpdatedVersion::OutOfDate {
+                    current: "1.0.0".to_string(),
+                    newest: "1.1.0".to_string(),
+                    newer: 1,
+                },
+            ),
+            (
+                task("floating"),
+                UpdatedVersion::Floating {
+                    tag: "main".to_string(),
+                },
+            ),
+            (
+                task("digest-pinned"),
+                UpdatedVersion::DigestPinned {
+                    digest: "sha256:abc".to_string(),
+                    newest: "2.0.0".to_string(),
+                },
+            ),
+            (task("not-trackable"), UpdatedVersion::NotTrackable),
+            (
+                task("current-missing"),
+                UpdatedVersion::UpToDate {
+                    version: "1.4.0".to_string(),
+                },
+            ),
+        ]);
+        // A Task that is also out of date is only counted once
+        let current_missing = BTreeSet::from([task("current-missing"), task("out-of-date")]);
+
+        let defaults = crate::Config::default().needs_attention;
+        assert_eq!(
+            6,
+            count_needs_attention(&defaults, &results, &current_missing, 2)
+        );
+
+        let states = [AttentionState::OutOfDate, AttentionState::DigestPinned];
+        assert_eq!(
+            2,
+            count_needs_attention(&states, &results, &current_missing, 2)
+        );
+
+        let states = [AttentionState::CurrentMissing];
+        assert_eq!(
+            2,
+            count_needs_attention(&states, &results, &current_missing, 2)
+        );
+
+        assert_eq!(0, count_needs_attention(&[], &results, &current_missing, 2));
+    }
+}
@@ -1,39 +1,341 @@
-use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Display,
+};
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::metrics::NomadRequestMetrics;
+
+/// The maximum number of characters of a Response included in a [`DeserializeError`]
+const SNIPPET_LEN: usize = 160;
+
+/// How many Chunks of a streamed Response are received ahead of the Deserializer
+const STREAM_QUEUE_LEN: usize = 4;
+
+/// The Header carrying the ACL Token of the Requests to Nomad
+pub(crate) const TOKEN_HEADER: &str = "X-Nomad-Token";
+
+/// The Drivers whose Config is deserialized, the Tasks of all other Drivers have no Image
+const KNOWN_DRIVERS: [&str; 3] = ["docker", "podman", "raw_exec"];
+
+/// The HashiCorp Releases API listing the newest Releases of Nomad
+pub const RELEASES_URL: &str = "https://api.releases.hashicorp.com/v1/releases/nomad?limit=20";
+
+#[derive(Debug)]
+pub enum NomadError {
+    Url(url::ParseError),
+    Request(reqwest::Error),
+    Status(reqwest::StatusCode),
+    /// The Response could not be decoded, which keeps the [`serde_json::Error`] together with
+    /// where in the Response it occurred
+    Deserialize(DeserializeError),
+}
+
+/// Describes where a Response from Nomad could not be deserialized
+#[derive(Debug)]
+pub struct DeserializeError {
+    /// The path to the offending field, like `TaskGroups[0].Name`
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    /// The part of the Response around the offending position
+    pub snippet: String,
+    pub source: serde_json::Error,
+}
+
+impl Display for NomadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Url(e) => write!(f, "building nomad url: {e}"),
+            Self::Request(e) => write!(f, "sending request to nomad: {e}"),
+            Self::Status(s) => write!(f, "nomad returned {s}"),
+            Self::Deserialize(e) => write!(
+                f,
+                "deserializing field {:?} at {}:{}: {} (near {:?})",
+                e.path, e.line, e.column, e.source, e.snippet
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NomadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Url(e) => Some(e),
+            Self::Request(e) => Some(e),
+            Self::Status(_) => None,
+            Self::Deserialize(e) => Some(&e.source),
+        }
+    }
+}
+
+fn deserialize<T>(raw_content: &[u8]) -> Result<T, NomadError>
+where
+    T: DeserializeOwned,
+{
+    let mut de = serde_json::Deserializer::from_slice(raw_content);
+
+    serde_path_to_error::deserialize(&mut de)
+        .map_err(|e| deserialize_error(e, |line, column| snippet(raw_content, line, column)))
+}
+
+/// Deserializes the Body of the Response while it is still being received, so only a few
+/// Chunks of it are held in memory at once instead of the entire Body
+async fn deserialize_stream<T>(mut resp: reqwest::Response) -> Result<T, NomadError>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let (sender, receiver) = tokio::sync::mpsc::channel(STREAM_QUEUE_LEN);
+
+    let parser = tokio::task::spawn_blocking(move || {
+        let mut reader = ChunkReader {
+            receiver,
+            current: bytes::Bytes::new(),
+            recent: VecDeque::with_capacity(SNIPPET_LEN),
+        };
+        let mut de = serde_json::Deserializer::from_reader(&mut reader);
+
+        serde_path_to_error::deserialize(&mut de)
+            .map_err(|e| deserialize_error(e, |_, _| reader.recent_line()))
+    });
+
+    let feed = async move {
+        while let Some(chunk) = resp.chunk().await? {
+            if sender.send(chunk).await.is_err() {
+                // The Deserializer already stopped, so the rest of the Body is not needed
+                break;
+            }
+        }
+        Ok(())
+    };
+
+    let (fed, parsed) = tokio::join!(feed, parser);
+    fed.map_err(NomadError::Request)?;
+    parsed.expect("Deserializing does not panic")
+}
+
+fn deserialize_error<F>(e: serde_path_to_error::Error<serde_json::Error>, snippet: F) -> NomadError
+where
+    F: FnOnce(usize, usize) -> String,
+{
+    let path = e.path().to_string();
+    let source = e.into_inner();
+
+    NomadError::Deserialize(DeserializeError {
+        snippet: snippet(source.line(), source.column()),
+        path,
+        line: source.line(),
+        column: source.column(),
+        source,
+    })
+}
+
+/// Reads the Chunks of a Response as they arrive, from a blocking Context
+struct ChunkReader {
+    receiver: tokio::sync::mpsc::Receiver<bytes::Bytes>,
+    current: bytes::Bytes,
+    /// The last [`SNIPPET_LEN`] Bytes that were read, to build the Snippet for Errors
+    recent: VecDeque<u8>,
+}
+
+impl ChunkReader {
+    /// The part of the current line that was read last
+    fn recent_line(&self) -> String {
+        let recent: Vec<u8> = self.recent.iter().copied().collect();
+        let content = String::from_utf8_lossy(&recent);
+
+        match content.rsplit_once('\n') {
+            Some((_, line)) => line.to_string(),
+            None => content.into_owned(),
+        }
+    }
+}
+
+impl std::io::Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.current.is_empty() {
+            match self.receiver.blocking_recv() {
+                Some(chunk) => self.current = chunk,
+                None => return Ok(0),
+            }
+        }
+
+        let read = core::cmp::min(buf.len(), self.current.len());
+        let data = self.current.split_to(read);
+        buf[..read].copy_from_slice(&data);
+
+        for byte in data.iter().skip(read.saturating_sub(SNIPPET_LEN)) {
+            if self.recent.len() == SNIPPET_LEN {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(*byte);
+        }
+
+        Ok(read)
+    }
+}
+
+/// Extracts the part of the content around the given line and column
+fn snippet(raw_content: &[u8], line: usize, column: usize) -> String {
+    let content = String::from_utf8_lossy(raw_content);
+    let line = content
+        .lines()
+        .nth(line.saturating_sub(1))
+        .unwrap_or_default();
+
+    line.chars()
+        .skip(column.saturating_sub(SNIPPET_LEN / 2))
+        .take(SNIPPET_LEN)
+        .collect()
+}
 
 pub type JobListResponse = Vec<JobListEntry>;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct JobListEntry {
     #[serde(rename = "ID")]
     pub id: String,
-    #[serde(rename = "ParentID")]
-    parentID: String,
-    #[serde(rename = "Name")]
-    name: String,
-    #[serde(rename = "Type")]
-    type_: String,
-    #[serde(rename = "Priority")]
-    priority: usize,
+    #[serde(rename = "Name", default)]
+    pub name: Option<String>,
+    #[serde(rename = "ParentID", default)]
+    pub parent_id: Option<String>,
+    /// The Namespace the Job belongs to, which is needed to read it when listing all of them
+    #[serde(rename = "Namespace", default)]
+    pub namespace: Option<String>,
+    /// When the Job was submitted, in Nanoseconds since the Unix Epoch
+    #[serde(rename = "SubmitTime", default)]
+    pub submit_time: Option<i64>,
+    #[serde(rename = "Version", default)]
+    pub version: Option<u64>,
+    #[serde(rename = "Meta", default)]
+    pub meta: Option<HashMap<String, String>>,
+    /// The Periodic Configuration of a full Job, or whether the Job is periodic for a Stub
+    #[serde(rename = "Periodic", default)]
+    pub periodic: Option<serde_json::Value>,
+    /// The Parameterized Configuration of a full Job, or whether the Job is parameterized for a
+    /// Stub
+    #[serde(rename = "ParameterizedJob", default)]
+    pub parameterized: Option<serde_json::Value>,
+    /// Only included by Nomad versions whose List contains the full Job
+    #[serde(rename = "TaskGroups", default)]
+    pub task_groups: Option<Vec<ReadJobTaskGroup>>,
+}
+
+impl JobListEntry {
+    /// Turns the Entry into the full Job, if the List contained everything that is needed,
+    /// otherwise returns the ID of the Job, which then has to be read on its own
+    pub fn into_job(self) -> Result<ReadJobResponse, String> {
+        match self {
+            Self {
+                name: Some(name),
+                task_groups: Some(task_groups),
+                parent_id,
+                namespace,
+                version,
+                meta,
+                periodic,
+                parameterized,
+                ..
+            } => Ok(ReadJobResponse {
+                name,
+                namespace: namespace.unwrap_or_else(default_namespace),
+                version: version.unwrap_or_default(),
+                parent_id: parent_id.unwrap_or_default(),
+                meta,
+                periodic,
+                parameterized,
+                task_groups,
+            }),
+            other => Err(other.id),
+        }
+    }
+}
+
+/// Keeps only the newest of the Jobs dispatched from the same Parent, as they share its Images,
+/// in place of the Parent itself, which only runs its Tasks through them
+pub fn collapse_dispatched(mut jobs: JobListResponse) -> JobListResponse {
+    // Newest first, the IDs of dispatched Jobs end with the Time they were dispatched at
+    jobs.sort_by(|a, b| {
+        b.submit_time
+            .cmp(&a.submit_time)
+            .then_with(|| b.id.cmp(&a.id))
+    });
+
+    let mut parents = HashSet::new();
+    jobs.retain(|job| match job.parent_id.as_deref() {
+        Some(parent) if !parent.is_empty() => {
+            parents.insert((job.namespace.clone(), parent.to_string()))
+        }
+        _ => true,
+    });
+    jobs.retain(|job| {
+        job.parent_id.as_deref().is_some_and(|p| !p.is_empty())
+            || !parents.contains(&(job.namespace.clone(), job.id.clone()))
+    });
+
+    jobs.sort_by(|a, b| a.id.cmp(&b.id));
+    jobs
 }
 
+pub type AllocationListResponse = Vec<AllocationListEntry>;
+
 #[derive(Debug, Deserialize)]
-pub struct ReadJobResponse {
+pub struct AllocationListEntry {
     #[serde(rename = "ID")]
-    id: String,
+    pub id: String,
+    #[serde(rename = "ClientStatus")]
+    pub client_status: String,
+    #[serde(rename = "DesiredStatus")]
+    pub desired_status: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadJobResponse {
     #[serde(rename = "Name")]
     pub name: String,
+    /// The Namespace the Job belongs to, Nomad versions without Namespaces run everything in
+    /// the `default` one
+    #[serde(rename = "Namespace", default = "default_namespace")]
+    pub namespace: String,
+    /// Increases whenever the Job is changed
+    #[serde(rename = "Version", default)]
+    pub version: u64,
     #[serde(rename = "ParentID")]
     pub parent_id: String,
+    #[serde(rename = "Meta", default)]
+    pub meta: Option<HashMap<String, String>>,
+    #[serde(rename = "Periodic", default)]
+    pub periodic: Option<serde_json::Value>,
+    #[serde(rename = "ParameterizedJob", default)]
+    pub parameterized: Option<serde_json::Value>,
     #[serde(rename = "TaskGroups")]
     pub task_groups: Vec<ReadJobTaskGroup>,
 }
 
+fn default_namespace() -> String {
+    "default".to_string()
+}
+
+impl ReadJobResponse {
+    /// Whether the Job is periodic or parameterized, and therefore only dispatches other Jobs
+    /// instead of running its Tasks itself
+    pub fn dispatches(&self) -> bool {
+        let set = |value: &Option<serde_json::Value>| {
+            !matches!(
+                value,
+                None | Some(serde_json::Value::Null) | Some(serde_json::Value::Bool(false))
+            )
+        };
+
+        set(&self.periodic) || set(&self.parameterized)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ReadJobTaskGroup {
     #[serde(rename = "Name")]
     pub name: String,
-    #[serde(rename = "Count")]
-    count: usize,
     #[serde(rename = "Tasks")]
     pub tasks: Vec<ReadJobTask>,
 }
@@ -42,54 +344,639 @@ pub struct ReadJobTaskGroup {
 pub struct ReadJobTask {
     #[serde(rename = "Name")]
     pub name: String,
-    #[serde(flatten)]
+    #[serde(flatten, deserialize_with = "task_config")]
     pub config: ReadJobConfig,
 }
 
+/// Deserializes the Config of Drivers without an Image, like `exec` or `java`, as
+/// [`ReadJobConfig::Other`] instead of failing to read the entire Job
+fn task_config<'de, D>(deserializer: D) -> Result<ReadJobConfig, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct RawConfig {
+        #[serde(rename = "Driver")]
+        driver: String,
+        #[serde(rename = "Config", default)]
+        config: serde_json::Value,
+    }
+
+    let raw = RawConfig::deserialize(deserializer)?;
+    if !KNOWN_DRIVERS.contains(&raw.driver.as_str()) {
+        return Ok(ReadJobConfig::Other);
+    }
+
+    let tagged = serde_json::json!({ "Driver": raw.driver, "Config": raw.config });
+    ReadJobConfig::deserialize(tagged).map_err(serde::de::Error::custom)
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(tag = "Driver", content = "Config")]
 pub enum ReadJobConfig {
     #[serde(rename = "docker")]
-    Docker { image: String },
+    Docker {
+        image: String,
+        /// Some Jobs specify the Tag separately instead of as part of the Image
+        #[serde(default)]
+        tag: Option<String>,
+        /// Some Jobs specify the Digest separately instead of as part of the Image
+        #[serde(default)]
+        digest: Option<String>,
+    },
+    /// Uses an Image in the same format as the `docker` Driver
+    #[serde(rename = "podman")]
+    Podman { image: String },
     #[serde(rename = "raw_exec")]
     RawExec {},
+    /// Drivers without an Image, like `exec` or `java`
+    #[serde(skip)]
+    Other,
 }
 
-pub async fn list_jobs(
+impl ReadJobConfig {
+    /// The full Reference of the Image, combining a separate Tag or Digest with the Image if
+    /// it does not contain one already
+    pub fn image_reference(&self) -> Option<String> {
+        let (image, tag, digest) = match self {
+            Self::Docker { image, tag, digest } => (image, tag, digest),
+            Self::Podman { image } => return Some(image.clone()),
+            Self::RawExec {} | Self::Other => return None,
+        };
+
+        let mut reference = image.clone();
+        let name = image.rsplit('/').next().unwrap_or_default();
+        if let Some(tag) = tag.as_ref().filter(|_| !name.contains([':', '@'])) {
+            reference.push(':');
+            reference.push_str(tag);
+        }
+        if let Some(digest) = digest.as_ref().filter(|_| !name.contains('@')) {
+            reference.push('@');
+            reference.push_str(digest);
+        }
+
+        Some(reference)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AgentSelfResponse {
+    config: AgentConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct AgentConfig {
+    #[serde(rename = "Version")]
+    version: AgentVersion,
+}
+
+#[derive(Debug, Deserialize)]
+struct AgentVersion {
+    #[serde(rename = "Version")]
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    version: String,
+    #[serde(default)]
+    is_prerelease: bool,
+}
+
+/// Loads the Body of the URL, recording the Request in the Metrics under the given endpoint
+async fn get(
     client: &reqwest::Client,
-    base_url: &reqwest::Url,
-) -> Result<JobListResponse, ()> {
-    let target_url = base_url.join("v1/jobs").map_err(|e| ())?;
+    target_url: reqwest::Url,
+    endpoint: &str,
+    token: Option<&str>,
+    metrics: &NomadRequestMetrics,
+) -> Result<bytes::Bytes, NomadError> {
+    let resp = send(client, target_url, endpoint, token, metrics).await?;
+
+    resp.bytes().await.map_err(NomadError::Request)
+}
+
+/// Sends the Request to the URL, recording it in the Metrics under the given endpoint, and
+/// returns the Response without loading its Body. The ACL Token is sent along, if set
+async fn send(
+    client: &reqwest::Client,
+    target_url: reqwest::Url,
+    endpoint: &str,
+    token: Option<&str>,
+    metrics: &NomadRequestMetrics,
+) -> Result<reqwest::Response, NomadError> {
+    let mut req = client.get(target_url);
+    if let Some(token) = token {
+        req = req.header(TOKEN_HEADER, token);
+    }
 
-    let resp = client.get(target_url).send().await.map_err(|e| ())?;
+    let start = std::time::Instant::now();
+    let result = req.send().await;
+    metrics.observe(
+        endpoint,
+        start.elapsed(),
+        result.as_ref().ok().map(|r| r.status()),
+    );
+
+    let resp = result.map_err(NomadError::Request)?;
 
     if !resp.status().is_success() {
-        return Err(());
+        return Err(NomadError::Status(resp.status()));
     }
 
-    let raw_content = resp.bytes().await.map_err(|e| ())?;
+    Ok(resp)
+}
 
-    serde_json::from_slice(&raw_content).map_err(|e| ())
+/// Lists the Jobs of the given Namespace, `*` for all of them, or of the `default` Namespace
+#[tracing::instrument(skip_all)]
+pub async fn list_jobs(
+    client: &reqwest::Client,
+    base_url: &reqwest::Url,
+    namespace: Option<&str>,
+    token: Option<&str>,
+    metrics: &NomadRequestMetrics,
+) -> Result<JobListResponse, NomadError> {
+    let mut target_url = base_url.join("v1/jobs").map_err(NomadError::Url)?;
+    {
+        let mut query = target_url.query_pairs_mut();
+        query.append_pair("meta", "true");
+        if let Some(namespace) = namespace {
+            query.append_pair("namespace", namespace);
+        }
+    }
+
+    let raw_content = get(client, target_url, "list", token, metrics).await?;
+
+    deserialize(&raw_content)
 }
 
+/// Reads the Job with the ID from its Namespace, or from the `default` Namespace
+#[tracing::instrument(skip(client, base_url, token, metrics))]
 pub async fn read_job(
     client: &reqwest::Client,
     base_url: &reqwest::Url,
     job_id: &str,
-) -> Result<ReadJobResponse, ()> {
-    let target_url = base_url.join(&format!("v1/job/{job_id}")).map_err(|e| ())?;
+    namespace: Option<&str>,
+    token: Option<&str>,
+    metrics: &NomadRequestMetrics,
+) -> Result<ReadJobResponse, NomadError> {
+    let mut target_url = base_url
+        .join(&format!("v1/job/{job_id}"))
+        .map_err(NomadError::Url)?;
+    if let Some(namespace) = namespace {
+        target_url
+            .query_pairs_mut()
+            .append_pair("namespace", namespace);
+    }
+
+    let resp = send(client, target_url, "read", token, metrics).await?;
+
+    deserialize_stream(resp).await.inspect_err(|e| {
+        if let NomadError::Deserialize(details) = e {
+            tracing::error!(
+                job = job_id,
+                path = %details.path,
+                line = details.line,
+                column = details.column,
+                snippet = %details.snippet,
+                "Deserializing Job: {}",
+                details.source
+            );
+        }
+    })
+}
+
+/// Lists the Allocations of the given Namespace, `*` for all of them, or of the `default`
+/// Namespace
+#[tracing::instrument(skip_all)]
+pub async fn list_allocations(
+    client: &reqwest::Client,
+    base_url: &reqwest::Url,
+    namespace: Option<&str>,
+    token: Option<&str>,
+    metrics: &NomadRequestMetrics,
+) -> Result<AllocationListResponse, NomadError> {
+    let mut target_url = base_url.join("v1/allocations").map_err(NomadError::Url)?;
+    if let Some(namespace) = namespace {
+        target_url
+            .query_pairs_mut()
+            .append_pair("namespace", namespace);
+    }
 
-    let resp = client.get(target_url).send().await.map_err(|e| ())?;
+    let raw_content = get(client, target_url, "allocations", token, metrics).await?;
 
+    deserialize(&raw_content)
+}
+
+/// Loads the Version of the Nomad Agent the Monitor talks to
+pub async fn agent_version(
+    client: &reqwest::Client,
+    base_url: &reqwest::Url,
+    token: Option<&str>,
+    metrics: &NomadRequestMetrics,
+) -> Result<String, NomadError> {
+    let target_url = base_url.join("v1/agent/self").map_err(NomadError::Url)?;
+
+    let raw_content = get(client, target_url, "agent", token, metrics).await?;
+
+    let content: AgentSelfResponse = deserialize(&raw_content)?;
+    Ok(content.config.version.version)
+}
+
+/// Lists the Versions of the stable Nomad Releases from the HashiCorp Releases API
+pub async fn list_releases(
+    client: &reqwest::Client,
+    releases_url: &reqwest::Url,
+) -> Result<Vec<String>, NomadError> {
+    let resp = client
+        .get(releases_url.clone())
+        .send()
+        .await
+        .map_err(NomadError::Request)?;
     if !resp.status().is_success() {
-        return Err(());
+        return Err(NomadError::Status(resp.status()));
     }
+    let raw_content = resp.bytes().await.map_err(NomadError::Request)?;
 
-    let raw_content = resp.bytes().await.map_err(|e| ())?;
+    let releases: Vec<Release> = deserialize(&raw_content)?;
+    Ok(releases
+        .into_iter()
+        .filter(|r| !r.is_prerelease)
+        .map(|r| r.version)
+        .collect())
+}
 
-    serde_json::from_slice(&raw_content).map_err(|e| {
-        println!("{}", std::str::from_utf8(&raw_content).unwrap());
-        dbg!(e);
-        ()
-    })
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collapse_keeps_newest_child() {
+        let jobs: JobListResponse = serde_json::from_value(serde_json::json!([
+            { "ID": "batch", "Namespace": "default", "SubmitTime": 1 },
+            { "ID": "batch/dispatch-1700000000-aaaa", "ParentID": "batch", "Namespace": "default", "SubmitTime": 3 },
+            { "ID": "batch/dispatch-1700000100-bbbb", "ParentID": "batch", "Namespace": "default", "SubmitTime": 5 },
+            { "ID": "batch/dispatch-1700000050-cccc", "ParentID": "batch", "Namespace": "default", "SubmitTime": 4 },
+            { "ID": "batch", "Namespace": "team-a", "SubmitTime": 2 },
+            { "ID": "cron", "Namespace": "default", "SubmitTime": 2 },
+        ]))
+        .unwrap();
+
+        let collapsed: Vec<_> = collapse_dispatched(jobs)
+            .into_iter()
+            .map(|j| (j.namespace.unwrap_or_default(), j.id))
+            .collect();
+        assert_eq!(
+            vec![
+                ("team-a".to_string(), "batch".to_string()),
+                (
+                    "default".to_string(),
+                    "batch/dispatch-1700000100-bbbb".to_string()
+                ),
+                ("default".to_string(), "cron".to_string()),
+            ],
+            collapsed
+        );
+    }
+
+    #[tokio::test]
+    async fn request_metrics() {
+        use prometheus::Encoder;
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/job/missing"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let reg = prometheus::Registry::new();
+        let metrics = NomadRequestMetrics::new(&reg).unwrap();
+        let client = reqwest::Client::new();
+        let base_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        list_jobs(&client, &base_url, None, None, &metrics)
+            .await
+            .expect("Listing Jobs");
+        assert!(matches!(
+            read_job(&client, &base_url, "missing", None, None, &metrics).await,
+            Err(NomadError::Status(reqwest::StatusCode::NOT_FOUND))
+        ));
+
+        let mut buffer = Vec::new();
+        prometheus::TextEncoder::new()
+            .encode(&reg.gather(), &mut buffer)
+            .unwrap();
+        let rendered = String::from_utf8(buffer).unwrap();
+
+        assert!(rendered.contains(r#"nomad_request_duration_seconds_count{endpoint="list"} 1"#));
+        assert!(rendered.contains(r#"nomad_request_duration_seconds_count{endpoint="read"} 1"#));
+        assert!(rendered.contains(r#"nomad_requests_total{endpoint="list",status="200"} 1"#));
+        assert!(rendered.contains(r#"nomad_requests_total{endpoint="read",status="404"} 1"#));
+    }
+
+    #[tokio::test]
+    async fn token_header() {
+        use wiremock::{
+            matchers::{header, method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .and(header(TOKEN_HEADER, "secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+
+        let metrics = NomadRequestMetrics::new(&prometheus::Registry::new()).unwrap();
+        let client = reqwest::Client::new();
+        let base_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        list_jobs(&client, &base_url, None, Some("secret"), &metrics)
+            .await
+            .expect("Listing Jobs with Token");
+        assert!(matches!(
+            list_jobs(&client, &base_url, None, None, &metrics).await,
+            Err(NomadError::Status(reqwest::StatusCode::FORBIDDEN))
+        ));
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(!requests[1].headers.contains_key(&TOKEN_HEADER.into()));
+    }
+
+    #[tokio::test]
+    async fn jobs_in_namespaces() {
+        use wiremock::{
+            matchers::{method, path, query_param},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .and(query_param("namespace", "*"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "ID": "web", "Namespace": "default" },
+                { "ID": "api", "Namespace": "team-a" },
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/jobs"))
+            .and(query_param("namespace", "team-a"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "ID": "api", "Namespace": "team-a" },
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/job/api"))
+            .and(query_param("namespace", "team-a"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "Name": "api",
+                "ParentID": "",
+                "TaskGroups": [],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let reg = prometheus::Registry::new();
+        let metrics = NomadRequestMetrics::new(&reg).unwrap();
+        let client = reqwest::Client::new();
+        let base_url = reqwest::Url::parse(&server.uri()).unwrap();
+
+        let all = list_jobs(&client, &base_url, Some("*"), None, &metrics)
+            .await
+            .expect("Listing all Namespaces");
+        assert_eq!(
+            vec![Some("default"), Some("team-a")],
+            all.iter()
+                .map(|j| j.namespace.as_deref())
+                .collect::<Vec<_>>()
+        );
+
+        let named = list_jobs(&client, &base_url, Some("team-a"), None, &metrics)
+            .await
+            .expect("Listing a single Namespace");
+        assert_eq!(1, named.len());
+
+        let job = read_job(
+            &client,
+            &base_url,
+            "api",
+            named[0].namespace.as_deref(),
+            None,
+            &metrics,
+        )
+        .await
+        .expect("Reading Job from its Namespace");
+        assert_eq!("api", job.name);
+        server.verify().await;
+    }
+
+    #[test]
+    fn docker_separate_tag() {
+        let raw = r#"{"Name": "web", "Driver": "docker", "Config": {"image": "ghcr.io/user/app", "tag": "1.2.3"}}"#;
+        let task: ReadJobTask = serde_json::from_str(raw).expect("Valid Task");
+
+        assert_eq!(
+            Some("ghcr.io/user/app:1.2.3".to_string()),
+            task.config.image_reference()
+        );
+
+        let config = ReadJobConfig::Docker {
+            image: "user/app:1.0.0".to_string(),
+            tag: Some("1.2.3".to_string()),
+            digest: Some("sha256:abc".to_string()),
+        };
+        assert_eq!(
+            Some("user/app:1.0.0@sha256:abc".to_string()),
+            config.image_reference()
+        );
+    }
+
+    #[test]
+    fn podman_driver() {
+        let raw = r#"{"Name": "app", "Driver": "podman", "Config": {"image": "docker.io/user/app:1.2.3", "ports": ["http"]}}"#;
+
+        let task: ReadJobTask = serde_json::from_str(raw).unwrap();
+
+        assert_eq!(
+            Some("docker.io/user/app:1.2.3".to_string()),
+            task.config.image_reference()
+        );
+
+        // Podman always qualifies Images from Docker Hub, which still have to be queried using
+        // the API Host of Docker Hub
+        let image = crate::docker::Image::parse(task.config.image_reference().unwrap()).unwrap();
+        assert_eq!(
+            "https://registry.hub.docker.com/",
+            image.registry_url(&[]).unwrap().as_str()
+        );
+        assert_eq!("user/app", image.repository());
+    }
+
+    #[test]
+    fn unknown_driver() {
+        let raw =
+            r#"{"Name": "worker", "Driver": "java", "Config": {"jar_path": "local/app.jar"}}"#;
+        let task: ReadJobTask = serde_json::from_str(raw).expect("Tasks of unknown Drivers");
+        assert!(matches!(task.config, ReadJobConfig::Other));
+        assert_eq!(None, task.config.image_reference());
+
+        let raw = r#"{"Name": "web", "Driver": "docker", "Config": {"tag": "1.2.3"}}"#;
+        assert!(serde_json::from_str::<ReadJobTask>(raw).is_err());
+    }
+
+    #[tokio::test]
+    async fn request_errors() {
+        let metrics = NomadRequestMetrics::new(&prometheus::Registry::new()).unwrap();
+        let client = reqwest::Client::new();
+
+        let opaque = reqwest::Url::parse("data:text/plain,nomad").unwrap();
+        assert!(matches!(
+            list_jobs(&client, &opaque, None, None, &metrics).await,
+            Err(NomadError::Url(_))
+        ));
+
+        // Nothing listens on the Port anymore once the Listener is dropped
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed =
+            reqwest::Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        drop(listener);
+        assert!(matches!(
+            read_job(&client, &closed, "web", None, None, &metrics).await,
+            Err(NomadError::Request(_))
+        ));
+    }
+
+    #[test]
+    fn deserialize_error_path() {
+        let raw = r#"{"Name": "web", "ParentID": "", "TaskGroups": [{"Name": 5, "Tasks": []}]}"#;
+
+        let err = match deserialize::<ReadJobResponse>(raw.as_bytes()) {
+            Err(NomadError::Deserialize(e)) => e,
+            other => panic!("Expected Deserialize Error, got {:?}", other),
+        };
+
+        assert_eq!("TaskGroups[0].Name", err.path);
+        assert!(err.source.is_data());
+        assert_eq!(1, err.line);
+        assert_eq!(57, err.column);
+        assert!(err.snippet.contains(r#""Name": 5"#), "{:?}", err.snippet);
+    }
+
+    #[test]
+    fn deserialize_error_source() {
+        use std::error::Error;
+
+        let err = deserialize::<ReadJobResponse>(b"{").unwrap_err();
+
+        let source = err.source().expect("Decoding Error as Source");
+        assert!(source
+            .downcast_ref::<serde_json::Error>()
+            .is_some_and(serde_json::Error::is_eof));
+    }
+
+    #[test]
+    fn deserialize_error_snippet_truncated() {
+        let raw = format!(r#"{{"Name": "{}", "ParentID": 5}}"#, "a".repeat(1000));
+
+        let err = match deserialize::<ReadJobResponse>(raw.as_bytes()) {
+            Err(NomadError::Deserialize(e)) => e,
+            other => panic!("Expected Deserialize Error, got {:?}", other),
+        };
+
+        assert_eq!("ParentID", err.path);
+        assert!(err.snippet.chars().count() <= SNIPPET_LEN);
+        assert!(
+            err.snippet.ends_with(r#""ParentID": 5}"#),
+            "{:?}",
+            err.snippet
+        );
+    }
+
+    #[tokio::test]
+    async fn read_job_streamed() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        // Every Task carries a large Environment, like Jobs with lots of templated Config
+        let env: serde_json::Map<String, serde_json::Value> = (0..2_000)
+            .map(|i| (format!("VAR_{i}"), "x".repeat(256).into()))
+            .collect();
+        let tasks: Vec<_> = (0..16)
+            .map(|i| {
+                serde_json::json!({
+                    "Name": format!("task-{i}"),
+                    "Driver": "docker",
+                    "Config": { "image": "user/app:1.2.3", "env": env },
+                })
+            })
+            .collect();
+        let body = serde_json::to_vec(&serde_json::json!({
+            "Name": "big",
+            "ParentID": "",
+            "TaskGroups": [{ "Name": "group", "Tasks": tasks }],
+        }))
+        .unwrap();
+        assert!(body.len() > 8 * 1024 * 1024);
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/job/big"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/job/broken"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"Name": "broken", "ParentID": "", "TaskGroups": [{"Name": 5, "Tasks": []}]}"#,
+            ))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let base_url = reqwest::Url::parse(&server.uri()).unwrap();
+        let metrics = NomadRequestMetrics::new(&prometheus::Registry::new()).unwrap();
+
+        let job = read_job(&client, &base_url, "big", None, None, &metrics)
+            .await
+            .unwrap();
+        assert_eq!("big", job.name);
+        assert_eq!(16, job.task_groups[0].tasks.len());
+        assert_eq!(
+            Some("user/app:1.2.3".to_string()),
+            job.task_groups[0].tasks[15].config.image_reference()
+        );
+
+        let err = match read_job(&client, &base_url, "broken", None, None, &metrics).await {
+            Err(NomadError::Deserialize(e)) => e,
+            other => panic!("Expected Deserialize Error, got {:?}", other),
+        };
+        assert_eq!("TaskGroups[0].Name", err.path);
+        assert!(err.snippet.contains(r#""Name": 5"#), "{:?}", err.snippet);
+    }
 }
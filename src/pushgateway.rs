@@ -0,0 +1,119 @@
+use std::fmt::Display;
+
+use base64::Engine;
+
+/// Where the Metrics are pushed to after every Check
+#[derive(Debug, Clone)]
+pub struct PushgatewayConfig {
+    /// The Base-URL of the Pushgateway
+    pub url: reqwest::Url,
+    /// The value of the `job` grouping label
+    pub job: String,
+    /// Additional grouping labels
+    pub grouping: Vec<(String, String)>,
+}
+
+#[derive(Debug)]
+pub enum PushError {
+    InvalidUrl,
+    EmptyLabel(String),
+    Request(reqwest::Error),
+    Status(reqwest::StatusCode),
+}
+
+impl Display for PushError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidUrl => write!(f, "pushgateway url can not be a base"),
+            Self::EmptyLabel(name) => write!(f, "grouping label {name:?} has an empty value"),
+            Self::Request(e) => write!(f, "sending metrics to pushgateway: {e}"),
+            Self::Status(s) => write!(f, "pushgateway returned {s}"),
+        }
+    }
+}
+
+impl std::error::Error for PushError {}
+
+impl PushgatewayConfig {
+    /// The URL identifying the Group of this Monitor on the Pushgateway
+    fn group_url(&self) -> Result<reqwest::Url, PushError> {
+        let mut url = self.url.clone();
+
+        {
+            let mut segments = url.path_segments_mut().map_err(|_| PushError::InvalidUrl)?;
+            segments.pop_if_empty().push("metrics");
+
+            let labels = std::iter::once(("job", self.job.as_str()))
+                .chain(self.grouping.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+            for (name, value) in labels {
+                // The Pushgateway treats an empty value like a missing label, which would
+                // silently merge this Group with another one
+                if value.is_empty() {
+                    return Err(PushError::EmptyLabel(name.to_string()));
+                }
+                // The Pushgateway can only handle values with a `/` if they are base64 encoded
+                if value.contains('/') {
+                    let encoded = base64::engine::general_purpose::URL_SAFE.encode(value);
+                    segments.push(&format!("{name}@base64")).push(&encoded);
+                } else {
+                    segments.push(name).push(value);
+                }
+            }
+        }
+
+        Ok(url)
+    }
+}
+
+/// Replaces the Metrics of this Monitor's Group on the Pushgateway
+pub async fn push(
+    client: &reqwest::Client,
+    conf: &PushgatewayConfig,
+    content_type: &str,
+    body: Vec<u8>,
+) -> Result<(), PushError> {
+    let resp = client
+        .put(conf.group_url()?)
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .body(body)
+        .send()
+        .await
+        .map_err(PushError::Request)?;
+
+    if !resp.status().is_success() {
+        return Err(PushError::Status(resp.status()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_url() {
+        let conf = PushgatewayConfig {
+            url: reqwest::Url::parse("http://pushgateway:9091/").unwrap(),
+            job: "nomad-vmonitor".to_string(),
+            grouping: vec![
+                ("instance".to_string(), "prod".to_string()),
+                ("path".to_string(), "a/b".to_string()),
+            ],
+        };
+
+        assert_eq!(
+            "http://pushgateway:9091/metrics/job/nomad-vmonitor/instance/prod/path@base64/YS9i",
+            conf.group_url().unwrap().as_str()
+        );
+
+        let empty = PushgatewayConfig {
+            grouping: vec![("instance".to_string(), String::new())],
+            ..conf
+        };
+        assert!(matches!(
+            empty.group_url(),
+            Err(PushError::EmptyLabel(name)) if name == "instance"
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    future::Future,
+    pin::Pin,
+};
+
+use futures::{
+    future::{Join, Ready},
+    stream::FuturesUnordered,
+    Stream, StreamExt,
+};
+
+/// The State of [`fair_buffered`] between the Items it yields
+struct Buffered<K, F: Future, S> {
+    /// The remaining Futures, `None` once they were all taken
+    items: Option<Pin<Box<S>>>,
+    /// The Futures of every group that were taken but not started yet
+    queued: BTreeMap<K, VecDeque<F>>,
+    running: FuturesUnordered<Join<Ready<K>, F>>,
+    /// How many Futures of every group are running
+    in_flight: BTreeMap<K, usize>,
+}
+
+impl<K, F, S> Buffered<K, F, S>
+where
+    K: Ord + Clone,
+    F: Future,
+{
+    /// Starts the queued Futures of every group until the group has used up its share of the
+    /// budget, which is split between the groups that are currently queued or running
+    fn start(&mut self, budget: usize) {
+        let active = self
+            .queued
+            .keys()
+            .filter(|key| !self.in_flight.contains_key(key))
+            .count()
+            + self.in_flight.len();
+        let share = core::cmp::max(1, budget / core::cmp::max(1, active));
+
+        for (key, futs) in self.queued.iter_mut() {
+            let running = self.in_flight.entry(key.clone()).or_default();
+            while *running < share {
+                let fut = match futs.pop_front() {
+                    Some(f) => f,
+                    None => break,
+                };
+
+                self.running.push(futures::future::join(
+                    futures::future::ready(key.clone()),
+                    fut,
+                ));
+                *running += 1;
+            }
+        }
+
+        self.queued.retain(|_, futs| !futs.is_empty());
+        self.in_flight.retain(|_, running| *running > 0);
+    }
+}
+
+/// Runs the Futures concurrently, splitting the concurrency budget evenly between their groups
+/// (like Registry-Hosts), so that slow Futures of one group can not take up the entire budget
+/// while the Futures of other groups are waiting. Futures are already started while the
+/// following ones are still being produced by the Stream.
+///
+/// As the groups are not known upfront, the budget is split between the groups that currently
+/// have Futures waiting or running, so a group gets more slots once the others are done. Every
+/// group gets at least one slot, so the actual concurrency can exceed the budget if there are
+/// more groups than slots.
+pub fn fair_buffered<K, F, S>(items: S, budget: usize) -> impl Stream<Item = F::Output>
+where
+    K: Ord + Clone,
+    F: Future,
+    S: Stream<Item = (K, F)>,
+{
+    let state = Buffered {
+        items: Some(Box::pin(items)),
+        queued: BTreeMap::new(),
+        running: FuturesUnordered::new(),
+        in_flight: BTreeMap::new(),
+    };
+
+    futures::stream::unfold(state, move |mut state| async move {
+        loop {
+            state.start(budget);
+            if state.items.is_none() && state.running.is_empty() {
+                return None;
+            }
+
+            let Buffered {
+                items,
+                queued,
+                running,
+                ..
+            } = &mut state;
+            let finished = tokio::select! {
+                item = async { items.as_mut().unwrap().next().await }, if items.is_some() => {
+                    match item {
+                        Some((key, fut)) => queued.entry(key).or_default().push_back(fut),
+                        None => *items = None,
+                    };
+                    None
+                }
+                Some(finished) = running.next(), if !running.is_empty() => Some(finished),
+            };
+
+            if let Some((key, output)) = finished {
+                if let Some(running) = state.in_flight.get_mut(&key) {
+                    *running -= 1;
+                }
+                return Some((output, state));
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn buffered_starts_early() {
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        let mut stream = Box::pin(fair_buffered(receiver, 2));
+
+        sender
+            .unbounded_send(("registry", futures::future::ready("first")))
+            .unwrap();
+        let first = tokio::time::timeout(Duration::from_millis(200), stream.next())
+            .await
+            .expect("Started before the Stream ended");
+        assert_eq!(Some("first"), first);
+
+        sender
+            .unbounded_send(("registry", futures::future::ready("second")))
+            .unwrap();
+        sender.close_channel();
+        let rest: Vec<_> = stream.collect().await;
+        assert_eq!(vec!["second"], rest);
+    }
+
+    #[tokio::test]
+    async fn slow_group_does_not_block() {
+        let task = |name: &'static str, millis: u64| async move {
+            tokio::time::sleep(Duration::from_millis(millis)).await;
+            name
+        };
+
+        let items = futures::stream::iter(vec![
+            ("slow.registry", task("huge-repo", 500)),
+            ("slow.registry", task("slow-small", 10)),
+            ("fast.registry", task("a", 10)),
+            ("fast.registry", task("b", 10)),
+            ("fast.registry", task("c", 10)),
+        ]);
+
+        let start = std::time::Instant::now();
+        let mut stream = Box::pin(fair_buffered(items, 2));
+
+        let mut fast = Vec::new();
+        while fast.len() < 3 {
+            match stream.next().await {
+                Some(name) if ["a", "b", "c"].contains(&name) => fast.push(name),
+                Some(_) => {}
+                None => panic!("Stream ended before all fast Tasks completed"),
+            }
+        }
+        assert!(
+            start.elapsed() < Duration::from_millis(400),
+            "Fast Tasks took {:?}",
+            start.elapsed()
+        );
+
+        // The slow Registry gets the freed slots once the fast one is done
+        let rest: Vec<_> = stream.collect().await;
+        assert_eq!(Some(&"huge-repo"), rest.last());
+    }
+}
@@ -0,0 +1,1044 @@
+use std::collections::HashMap;
+
+use crate::{
+    config::UpdateTrack,
+    docker::{RawTag, Version, VersionOrdering},
+    metrics::UpdatedVersion,
+};
+
+/// The Job-Meta key for the inclusive lower bound of candidate Versions
+const MIN_VERSION_META: &str = "vmonitor_min_version";
+/// The Job-Meta key for the Version line, like `1.x`, that candidates have to be part of
+const TRACK_TAG_META: &str = "vmonitor_track_tag";
+/// The Job-Meta key for the upstream Image, like `ghcr.io/user/app`, that the deployed Image is
+/// mirrored from
+const UPSTREAM_IMAGE_META: &str = "vmonitor_upstream_image";
+/// The Job-Meta key for the Owner, like the responsible Team, of the Job
+const OWNER_META: &str = "vmonitor_owner";
+/// The Job-Meta key for the Suffix, like `-prod`, that Tags need to have to be considered, which
+/// is removed before comparing them
+const TAG_SUFFIX_META: &str = "vmonitor_tag_suffix";
+
+/// Constraints on which Tags of a Registry are considered when looking for the newest Version
+#[derive(Debug, Clone, Default)]
+pub struct CandidateFilter {
+    /// Candidates below this Version are never considered
+    pub min_version: Option<Version>,
+    /// Only candidates in this Version line are considered
+    pub track: Option<TrackLine>,
+    /// The Track of the Job, which becomes the [`track`](Self::track) of the
+    /// [`deployed`](Self::deployed) Version unless the Job sets one itself
+    pub update_track: Option<UpdateTrack>,
+    /// The deployed Version, if it is semantic or calendar based, only candidates of the same
+    /// Scheme are considered
+    pub deployed: Option<Version>,
+    /// The Order used to compare named Versions
+    pub channels: ChannelOrder,
+    /// How Versions are compared
+    pub ordering: VersionOrdering,
+    /// Whether the case of Tags is ignored when parsing them, like `V1.2.3`
+    pub ignore_case: bool,
+    /// Whether a `git describe` suffix of Tags, like `-g1a2b3c4`, is ignored when parsing them
+    pub ignore_describe_suffix: bool,
+    /// The Suffix, like `-prod`, that Tags need to have, it is removed before parsing them
+    pub suffix: Option<String>,
+    /// The Variant, like `alpine`, that Candidates need to have, usually the one of the
+    /// deployed Version
+    pub variant: Option<String>,
+    /// Whether the Candidates come from an approved Feed, which were chosen deliberately, so
+    /// only their Variant and Scheme are checked
+    pub approved: bool,
+}
+
+/// An explicit Order of named Versions, like `stable < beta < canary`, from oldest to newest
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelOrder {
+    names: Vec<String>,
+}
+
+/// A Version line like `1.x` or `1.2.x`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackLine {
+    pub major: usize,
+    pub minor: Option<usize>,
+}
+
+/// The newest Versions available in a Mirror and in the Upstream it mirrors
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirrorStatus {
+    pub mirror_newest: Option<Version>,
+    pub upstream_newest: Option<Version>,
+    /// How the newest Versions were compared, so a Mirror is only lagging by the same Ordering
+    pub ordering: VersionOrdering,
+}
+
+/// What is known about the Version of a deployed Image
+#[derive(Debug, Clone, PartialEq)]
+pub enum Deployed {
+    Version(Version),
+    /// The Image is only pinned by its Digest, so its Version is unknown
+    Digest(String),
+}
+
+impl TrackLine {
+    /// Parses a line like `1.x`, `1.2.x` or `1`
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim().trim_start_matches('v');
+        let raw = raw
+            .strip_suffix(".x")
+            .or_else(|| raw.strip_suffix(".*"))
+            .unwrap_or(raw);
+
+        let mut parts = raw.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = match parts.next() {
+            Some(m) => Some(m.parse().ok()?),
+            None => None,
+        };
+
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(Self { major, minor })
+    }
+
+    pub fn contains(&self, version: &Version) -> bool {
+        match version {
+            Version::Latest | Version::Named(_) | Version::CalVer { .. } => false,
+            Version::Semantic { major, minor, .. } => {
+                *major == self.major && (self.minor.is_none() || *minor == self.minor)
+            }
+        }
+    }
+}
+
+impl ChannelOrder {
+    pub fn new(names: Vec<String>) -> Self {
+        Self { names }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|n| n == name)
+    }
+
+    /// Compares two names according to the Order, unknown names sort below known ones
+    pub fn compare(&self, first: &str, second: &str) -> std::cmp::Ordering {
+        let rank = |name: &str| {
+            self.names
+                .iter()
+                .position(|n| n == name)
+                .map(|p| p + 1)
+                .unwrap_or(0)
+        };
+
+        rank(first).cmp(&rank(second))
+    }
+
+    /// Counts the known names among the Tags that are newer than the current one
+    pub fn count_newer<T>(&self, current: &str, tags: &[T]) -> usize
+    where
+        T: AsRef<str>,
+    {
+        self.newer(current, tags).len()
+    }
+
+    /// Lists the known names among the Tags that are newer than the current one, oldest first
+    pub fn newer<T>(&self, current: &str, tags: &[T]) -> Vec<Version>
+    where
+        T: AsRef<str>,
+    {
+        self.names
+            .iter()
+            .filter(|name| self.compare(name, current) == std::cmp::Ordering::Greater)
+            .filter(|name| tags.iter().any(|t| t.as_ref() == name.as_str()))
+            .map(|name| Version::Named(name.clone()))
+            .collect()
+    }
+
+    /// Finds the newest known name among the Tags
+    pub fn newest<T>(&self, tags: &[T]) -> Option<Version>
+    where
+        T: AsRef<str>,
+    {
+        self.names
+            .iter()
+            .rev()
+            .find(|name| tags.iter().any(|t| t.as_ref() == name.as_str()))
+            .map(|name| Version::Named(name.clone()))
+    }
+}
+
+impl CandidateFilter {
+    /// Loads the Filter configured in the Meta of a Job
+    pub fn from_meta(meta: Option<&HashMap<String, String>>) -> Self {
+        let min_version = meta.and_then(|m| m.get(MIN_VERSION_META)).and_then(|raw| {
+            match RawTag::new(raw).parse_version() {
+                Ok(v) => Some(v),
+                Err(_) => {
+                    tracing::warn!("Ignoring invalid {}: {:?}", MIN_VERSION_META, raw);
+                    None
+                }
+            }
+        });
+
+        let track =
+            meta.and_then(|m| m.get(TRACK_TAG_META))
+                .and_then(|raw| match TrackLine::parse(raw) {
+                    Some(t) => Some(t),
+                    None => {
+                        tracing::warn!("Ignoring invalid {}: {:?}", TRACK_TAG_META, raw);
+                        None
+                    }
+                });
+
+        Self {
+            min_version,
+            track,
+            update_track: None,
+            deployed: None,
+            channels: ChannelOrder::default(),
+            ordering: VersionOrdering::default(),
+            ignore_case: false,
+            ignore_describe_suffix: false,
+            suffix: meta
+                .and_then(|m| m.get(TAG_SUFFIX_META))
+                .filter(|s| !s.is_empty())
+                .cloned(),
+            variant: None,
+            approved: false,
+        }
+    }
+
+    pub fn with_ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    /// Parses the Version of a Tag
+    pub fn parse(&self, tag: &RawTag) -> Result<Version, ()> {
+        let unsuffixed;
+        let tag = match self.suffix.as_ref() {
+            Some(suffix) => {
+                unsuffixed = RawTag::new(tag.as_str().strip_suffix(suffix.as_str()).ok_or(())?);
+                &unsuffixed
+            }
+            None => tag,
+        };
+
+        let stripped;
+        let tag = if self.ignore_describe_suffix {
+            stripped = tag.without_describe_suffix();
+            &stripped
+        } else {
+            tag
+        };
+
+        if self.ignore_case {
+            tag.parse_version_ignore_case()
+        } else {
+            tag.parse_version()
+        }
+    }
+
+    pub fn with_ignore_describe_suffix(mut self, ignore: bool) -> Self {
+        self.ignore_describe_suffix = ignore;
+        self
+    }
+
+    pub fn with_ordering(mut self, ordering: VersionOrdering) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    pub fn with_update_track(mut self, track: Option<UpdateTrack>) -> Self {
+        self.update_track = track;
+        self
+    }
+
+    /// Sets the deployed Version the [`update_track`](Self::update_track) is relative to, only
+    /// semantic and calendar based Versions are kept
+    pub fn with_deployed(mut self, deployed: Option<&Version>) -> Self {
+        self.deployed = deployed
+            .filter(|v| matches!(v, Version::Semantic { .. } | Version::CalVer { .. }))
+            .cloned();
+        if self.track.is_none() {
+            self.track = match (&self.update_track, &self.deployed) {
+                (Some(track), Some(deployed)) => track.line_of(deployed),
+                _ => None,
+            };
+        }
+        self
+    }
+
+    /// Only considers Candidates of the given Variant, like `alpine`
+    pub fn with_variant(mut self, variant: Option<String>) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    pub fn with_approved(mut self, approved: bool) -> Self {
+        self.approved = approved;
+        self
+    }
+
+    pub fn with_channels(mut self, channels: ChannelOrder) -> Self {
+        self.channels = channels;
+        self
+    }
+
+    /// Whether the given Version can be considered as a Candidate
+    pub fn matches(&self, version: &Version) -> bool {
+        if version.variant() != self.variant.as_deref() {
+            return false;
+        }
+
+        if let Some(deployed) = &self.deployed {
+            if deployed.is_calver() != version.is_calver() {
+                return false;
+            }
+        }
+
+        if self.approved {
+            return true;
+        }
+
+        if self.ordering == VersionOrdering::Strict && !version.fully_qualified() {
+            return false;
+        }
+
+        if let Some(track) = &self.track {
+            if !track.contains(version) {
+                return false;
+            }
+        }
+
+        // Pre-Releases are only considered for Tasks that already run one
+        let deployed_pre_release = self.deployed.as_ref().is_some_and(Version::is_pre_release);
+        if version.is_pre_release() && !deployed_pre_release {
+            return false;
+        }
+
+        match &self.min_version {
+            Some(min) => self.ordering.compare(version, min) != std::cmp::Ordering::Less,
+            None => true,
+        }
+    }
+
+    /// Counts the Tags that pass the Filter and can therefore be selected as the newest Version
+    pub fn count_candidates<T>(&self, tags: &[T]) -> usize
+    where
+        T: AsRef<str>,
+    {
+        tags.iter()
+            .filter_map(|tag| self.parse(&RawTag::new(tag.as_ref())).ok())
+            .filter(|v| self.matches(v))
+            .count()
+    }
+
+    /// Counts the distinct Versions among the Tags that pass the Filter and are newer than the
+    /// current one
+    pub fn count_newer<T>(&self, current: &Version, tags: &[T]) -> usize
+    where
+        T: AsRef<str>,
+    {
+        self.newer_versions(current, tags).len()
+    }
+
+    /// Lists the distinct Versions among the Tags that pass the Filter and are newer than the
+    /// current one, oldest first. Only Versions of the same Scheme as the current one are
+    /// listed, named Versions by their [`ChannelOrder`]
+    pub fn newer_versions<T>(&self, current: &Version, tags: &[T]) -> Vec<Version>
+    where
+        T: AsRef<str>,
+    {
+        if let Version::Named(current) = current {
+            return self.channels.newer(current, tags);
+        }
+
+        let mut newer: Vec<_> = tags
+            .iter()
+            .filter_map(|tag| self.parse(&RawTag::new(tag.as_ref())).ok())
+            .filter(|v| std::mem::discriminant(v) == std::mem::discriminant(current))
+            .filter(|v| {
+                self.matches(v) && self.ordering.compare(v, current) == std::cmp::Ordering::Greater
+            })
+            .collect();
+        newer.sort_by(|a, b| self.ordering.compare(a, b));
+        newer.dedup_by(|a, b| self.ordering.compare(a, b) == std::cmp::Ordering::Equal);
+
+        newer
+    }
+
+    /// Finds the newest Version among the Tags that passes the Filter
+    pub fn newest<T>(&self, tags: &[T]) -> Option<Version>
+    where
+        T: AsRef<str>,
+    {
+        tags.iter()
+            .filter_map(|tag| self.parse(&RawTag::new(tag.as_ref())).ok())
+            .filter(|v| self.matches(v))
+            .max_by(|a, b| self.ordering.compare(a, b))
+    }
+
+    /// Finds the newest concrete Version among the Tags that passes the Filter, ignoring
+    /// `latest` and named Versions as they say nothing about what was released
+    pub fn newest_release<T>(&self, tags: &[T]) -> Option<Version>
+    where
+        T: AsRef<str>,
+    {
+        tags.iter()
+            .filter_map(|tag| self.parse(&RawTag::new(tag.as_ref())).ok())
+            .filter(|v| !matches!(v, Version::Latest | Version::Named(_)) && self.matches(v))
+            .max_by(|a, b| self.ordering.compare(a, b))
+    }
+}
+
+/// How many of the Tags of an Image were left as Candidates after applying the Filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateCounts {
+    pub image: String,
+    pub before: usize,
+    pub after: usize,
+}
+
+/// Loads the upstream Image configured in the Meta of a Job
+pub fn upstream_from_meta(meta: Option<&HashMap<String, String>>) -> Option<String> {
+    meta.and_then(|m| m.get(UPSTREAM_IMAGE_META)).cloned()
+}
+
+/// Loads the Owner configured in the Meta of a Job, falling back to the Owner configured for
+/// the Job in its Namespace, like `team-a/web`, and then for the Name of the Job alone
+pub fn owner_from_meta(
+    meta: Option<&HashMap<String, String>>,
+    namespace: &str,
+    job: &str,
+    owners: &HashMap<String, String>,
+) -> Option<String> {
+    meta.and_then(|m| m.get(OWNER_META))
+        .or_else(|| owners.get(&format!("{namespace}/{job}")))
+        .or_else(|| owners.get(job))
+        .cloned()
+}
+
+impl MirrorStatus {
+    /// Compares the Tags available in the Mirror with those available upstream
+    pub fn compare<M, U>(mirror_tags: &[M], upstream_tags: &[U], filter: &CandidateFilter) -> Self
+    where
+        M: AsRef<str>,
+        U: AsRef<str>,
+    {
+        Self {
+            mirror_newest: filter.newest(mirror_tags),
+            upstream_newest: filter.newest(upstream_tags),
+            ordering: filter.ordering,
+        }
+    }
+
+    /// Whether upstream has a newer Version that has not been mirrored yet
+    pub fn lagging(&self) -> bool {
+        match (&self.mirror_newest, &self.upstream_newest) {
+            (Some(mirror), Some(upstream)) => {
+                self.ordering.compare(upstream, mirror) == std::cmp::Ordering::Greater
+            }
+            (None, Some(_)) => true,
+            (_, None) => false,
+        }
+    }
+}
+
+/// Compares the deployed Image against the newest candidate among the Tags
+pub fn evaluate<T>(
+    deployed: &Deployed,
+    tags: &[T],
+    filter: &CandidateFilter,
+) -> Option<UpdatedVersion>
+where
+    T: AsRef<str>,
+{
+    if let Deployed::Version(Version::Named(current)) = deployed {
+        let newest = filter.channels.newest(tags)?;
+
+        return match filter.channels.compare(&format!("{newest}"), current) {
+            std::cmp::Ordering::Greater => Some(UpdatedVersion::OutOfDate {
+                current: current.clone(),
+                newest: format!("{newest}"),
+                newer: filter.channels.count_newer(current, tags),
+            }),
+            _ => Some(UpdatedVersion::UpToDate {
+                version: current.clone(),
+            }),
+        };
+    }
+
+    let newest = filter.newest(tags)?;
+
+    match deployed {
+        Deployed::Digest(digest) => Some(UpdatedVersion::DigestPinned {
+            digest: digest.clone(),
+            newest: format!("{newest}"),
+        }),
+        Deployed::Version(current)
+            if filter.ordering.compare(&newest, current) == std::cmp::Ordering::Greater =>
+        {
+            Some(UpdatedVersion::OutOfDate {
+                current: format!("{current}"),
+                newest: format!("{newest}"),
+                newer: filter.count_newer(current, tags),
+            })
+        }
+        Deployed::Version(current) => Some(UpdatedVersion::UpToDate {
+            version: format!("{current}"),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn semantic(major: usize, minor: usize, patch: usize) -> Version {
+        Version::Semantic {
+            v_prefix: false,
+            major,
+            minor: Some(minor),
+            patch: Some(patch),
+            revision: None,
+            pre_release: None,
+            variant: None,
+            build: None,
+            written: None,
+        }
+    }
+
+    #[test]
+    fn min_version_from_meta() {
+        let meta: HashMap<String, String> = [(MIN_VERSION_META.to_string(), "2.0.0".to_string())]
+            .into_iter()
+            .collect();
+
+        let filter = CandidateFilter::from_meta(Some(&meta));
+
+        assert_eq!(Some(semantic(2, 0, 0)), filter.min_version);
+    }
+
+    #[test]
+    fn min_version_excludes_older() {
+        let filter = CandidateFilter {
+            min_version: Some(semantic(2, 0, 0)),
+            ..Default::default()
+        };
+
+        assert_eq!(None, filter.newest(&["1.9.0", "1.10.3"]));
+        assert_eq!(
+            Some(semantic(2, 0, 0)),
+            filter.newest(&["1.9.0", "2.0.0", "1.10.3"])
+        );
+    }
+
+    #[test]
+    fn no_min_version() {
+        let filter = CandidateFilter::from_meta(None);
+
+        assert_eq!(
+            Some(semantic(1, 10, 3)),
+            filter.newest(&["1.9.0", "1.10.3", "latest"])
+        );
+    }
+
+    #[test]
+    fn parse_track_line() {
+        assert_eq!(
+            Some(TrackLine {
+                major: 1,
+                minor: None
+            }),
+            TrackLine::parse("1.x")
+        );
+        assert_eq!(
+            Some(TrackLine {
+                major: 1,
+                minor: Some(2)
+            }),
+            TrackLine::parse("1.2.x")
+        );
+        assert_eq!(None, TrackLine::parse("1.2.3.x"));
+        assert_eq!(None, TrackLine::parse("stable"));
+    }
+
+    fn channels() -> ChannelOrder {
+        ChannelOrder::new(vec![
+            "stable".to_string(),
+            "beta".to_string(),
+            "canary".to_string(),
+        ])
+    }
+
+    #[test]
+    fn newest_ignoring_case() {
+        let tags = ["v1.2.0", "V1.3.0"];
+
+        assert_eq!(
+            Some("v1.2.0".to_string()),
+            CandidateFilter::default()
+                .newest(&tags)
+                .map(|v| format!("{v}"))
+        );
+        // The Tag is reported as it is written, so it can be found in the Registry
+        assert_eq!(
+            Some("V1.3.0".to_string()),
+            CandidateFilter::default()
+                .with_ignore_case(true)
+                .newest(&tags)
+                .map(|v| format!("{v}"))
+        );
+    }
+
+    #[test]
+    fn newest_of_same_variant() {
+        let tags = [
+            "1.22.0",
+            "1.21.0-alpine",
+            "1.22.0-alpine",
+            "1.23.0-bullseye",
+        ];
+
+        let newest = |variant: Option<&str>| {
+            CandidateFilter::default()
+                .with_variant(variant.map(String::from))
+                .newest(&tags)
+                .map(|v| format!("{v}"))
+        };
+        assert_eq!(Some("1.22.0-alpine".to_string()), newest(Some("alpine")));
+        assert_eq!(
+            Some("1.23.0-bullseye".to_string()),
+            newest(Some("bullseye"))
+        );
+        assert_eq!(Some("1.22.0".to_string()), newest(None));
+    }
+
+    #[test]
+    fn newest_release_ignores_names() {
+        let filter = CandidateFilter::default();
+
+        assert_eq!(
+            Some(semantic(1, 2, 0)),
+            filter.newest_release(&["latest", "stable", "1.0.0", "1.2.0"])
+        );
+        assert_eq!(None, filter.newest_release(&["latest", "stable"]));
+    }
+
+    #[test]
+    fn newest_with_build_metadata() {
+        let tags = ["1.2.3+b", "1.2.4+20240115", "1.2.2"];
+
+        let newest = CandidateFilter::default().newest(&tags);
+        assert_eq!(
+            Some("1.2.4+20240115".to_string()),
+            newest.map(|v| format!("{v}"))
+        );
+    }
+
+    #[test]
+    fn describe_suffix_ignored() {
+        let filter = CandidateFilter::default().with_ignore_describe_suffix(true);
+        let current = filter
+            .parse(&RawTag::new("1.4.2-g1a2b3c"))
+            .expect("Valid Version");
+
+        assert_eq!(semantic(1, 4, 2), current);
+        assert_eq!(
+            Some(UpdatedVersion::UpToDate {
+                version: "1.4.2".to_string()
+            }),
+            evaluate(&Deployed::Version(current), &["1.4.1", "1.4.2"], &filter)
+        );
+
+        let current = CandidateFilter::default()
+            .parse(&RawTag::new("1.4.2-g1a2b3c"))
+            .expect("Valid Version");
+        assert_ne!(semantic(1, 4, 2), current);
+    }
+
+    #[test]
+    fn required_tag_suffix() {
+        let meta: HashMap<String, String> = [(TAG_SUFFIX_META.to_string(), "-prod".to_string())]
+            .into_iter()
+            .collect();
+        let filter = CandidateFilter::from_meta(Some(&meta));
+        let tags = [
+            "1.4.1-prod",
+            "1.4.2-prod",
+            "1.4.2-staging",
+            "1.5.0-staging",
+            "1.5.0",
+        ];
+
+        let current = filter
+            .parse(&RawTag::new("1.4.1-prod"))
+            .expect("Valid Version");
+        assert_eq!(semantic(1, 4, 1), current);
+        assert!(filter.parse(&RawTag::new("1.5.0-staging")).is_err());
+
+        assert_eq!(
+            Some(UpdatedVersion::OutOfDate {
+                current: "1.4.1".to_string(),
+                newest: "1.4.2".to_string(),
+                newer: 1,
+            }),
+            evaluate(&Deployed::Version(current), &tags, &filter)
+        );
+    }
+
+    #[test]
+    fn count_candidates_after_filter() {
+        let meta: HashMap<String, String> = [(MIN_VERSION_META.to_string(), "1.2.0".to_string())]
+            .into_iter()
+            .collect();
+        let filter = CandidateFilter::from_meta(Some(&meta));
+        let tags = ["1.1.0", "1.2.0", "1.3", "1.3.0", "main", "2.0.0"];
+
+        assert_eq!(3, filter.count_candidates(&tags));
+        assert_eq!(4, CandidateFilter::default().count_candidates(&tags));
+    }
+
+    #[test]
+    fn list_newer_versions() {
+        let tags = [
+            "2.0.0", "1.2.1", "1.1.0", "v1.2.1", "1.3.0", "1.2.0", "latest",
+        ];
+
+        let newer: Vec<_> = CandidateFilter::default()
+            .newer_versions(&semantic(1, 2, 0), &tags)
+            .iter()
+            .map(|v| format!("{v}"))
+            .collect();
+        assert_eq!(vec!["1.2.1", "1.3.0", "2.0.0"], newer);
+    }
+
+    #[test]
+    fn list_newer_versions_same_scheme() {
+        let tags = ["1.0.0", "2024.01", "2024.02", "beta", "stable", "latest"];
+        let list = |filter: &CandidateFilter, current: &Version| -> Vec<String> {
+            filter
+                .newer_versions(current, &tags)
+                .iter()
+                .map(|v| format!("{v}"))
+                .collect()
+        };
+
+        let filter = CandidateFilter::default().with_channels(ChannelOrder::new(vec![
+            "beta".to_string(),
+            "stable".to_string(),
+        ]));
+        assert_eq!(
+            vec!["stable"],
+            list(&filter, &Version::Named("beta".to_string()))
+        );
+        assert!(list(&filter, &Version::Named("stable".to_string())).is_empty());
+        assert_eq!(
+            vec!["2024.02"],
+            list(&filter, &RawTag::new("2024.01").parse_version().unwrap())
+        );
+        assert_eq!(vec!["1.0.0"], list(&filter, &semantic(0, 9, 0)));
+        assert!(list(&filter, &Version::Latest).is_empty());
+    }
+
+    #[test]
+    fn count_newer_versions() {
+        let tags = [
+            "1.1.0", "1.2.0", "1.2.1", "v1.2.1", "1.2.2", "1.3.0", "1.3", "2.0.0", "latest",
+        ];
+        let current = semantic(1, 2, 0);
+
+        assert_eq!(4, CandidateFilter::default().count_newer(&current, &tags));
+
+        let filter = CandidateFilter {
+            track: TrackLine::parse("1.2.x"),
+            ..Default::default()
+        };
+        assert_eq!(2, filter.count_newer(&current, &tags));
+
+        assert_eq!(
+            Some(UpdatedVersion::OutOfDate {
+                current: "1.2.0".to_string(),
+                newest: "1.2.2".to_string(),
+                newer: 2,
+            }),
+            evaluate(&Deployed::Version(current), &tags, &filter)
+        );
+    }
+
+    #[test]
+    fn pre_releases_only_for_pre_releases() {
+        let evaluate_deployed = |current: &str, tags: &[&str]| {
+            let current = RawTag::new(current).parse_version().unwrap();
+            let filter = CandidateFilter::default().with_deployed(Some(&current));
+            evaluate(&Deployed::Version(current.clone()), tags, &filter)
+        };
+
+        assert_eq!(
+            Some(UpdatedVersion::UpToDate {
+                version: "1.4.0".to_string(),
+            }),
+            evaluate_deployed("1.4.0", &["1.4.0", "1.5.0-rc1"])
+        );
+        assert_eq!(
+            Some(UpdatedVersion::OutOfDate {
+                current: "1.5.0-rc1".to_string(),
+                newest: "1.5.0-rc2".to_string(),
+                newer: 1,
+            }),
+            evaluate_deployed("1.5.0-rc1", &["1.4.0", "1.5.0-rc1", "1.5.0-rc2"])
+        );
+    }
+
+    #[test]
+    fn approved_only_checks_variant_and_scheme() {
+        let current = semantic(1, 4, 0);
+        let filter = CandidateFilter {
+            min_version: Some(semantic(2, 0, 0)),
+            track: TrackLine::parse("1.4.x"),
+            ..Default::default()
+        }
+        .with_deployed(Some(&current))
+        .with_approved(true);
+
+        assert_eq!(
+            Some(UpdatedVersion::OutOfDate {
+                current: "1.4.0".to_string(),
+                newest: "1.5.0-rc1".to_string(),
+                newer: 1,
+            }),
+            evaluate(&Deployed::Version(current.clone()), &["1.5.0-rc1"], &filter)
+        );
+        assert_eq!(
+            None,
+            evaluate(
+                &Deployed::Version(current.clone()),
+                &["1.5.0-alpine"],
+                &filter
+            )
+        );
+        assert_eq!(
+            None,
+            evaluate(&Deployed::Version(current), &["2024.01.1"], &filter)
+        );
+    }
+
+    #[test]
+    fn update_track_of_deployed() {
+        let current = semantic(1, 5, 0);
+        let filter = |track| {
+            CandidateFilter::default()
+                .with_update_track(Some(track))
+                .with_deployed(Some(&current))
+        };
+
+        assert_eq!(
+            Some(UpdatedVersion::UpToDate {
+                version: "1.5.0".to_string(),
+            }),
+            evaluate(
+                &Deployed::Version(current.clone()),
+                &["1.5.0", "2.0.0"],
+                &filter(UpdateTrack::Major)
+            )
+        );
+        assert_eq!(
+            Some(UpdatedVersion::OutOfDate {
+                current: "1.5.0".to_string(),
+                newest: "1.5.2".to_string(),
+                newer: 1,
+            }),
+            evaluate(
+                &Deployed::Version(current.clone()),
+                &["1.5.0", "1.5.2", "1.6.0", "2.0.0"],
+                &filter(UpdateTrack::Minor)
+            )
+        );
+        assert_eq!(
+            Some(semantic(2, 0, 0)),
+            CandidateFilter::default()
+                .with_update_track(Some(UpdateTrack::Major))
+                .newest(&["1.5.0", "2.0.0"])
+        );
+
+        // The Line set by the Job itself takes precedence
+        let pinned = CandidateFilter {
+            track: TrackLine::parse("2.x"),
+            ..Default::default()
+        }
+        .with_update_track(Some(UpdateTrack::Minor))
+        .with_deployed(Some(&current));
+        assert_eq!(Some(semantic(2, 0, 0)), pinned.newest(&["1.5.2", "2.0.0"]));
+
+        let tracks: HashMap<String, UpdateTrack> =
+            serde_json::from_str(r#"{"api": "major", "web": "minor"}"#).unwrap();
+        assert_eq!(Some(&UpdateTrack::Major), tracks.get("api"));
+        assert_eq!(Some(&UpdateTrack::Minor), tracks.get("web"));
+    }
+
+    #[test]
+    fn calendar_versions_not_mixed() {
+        let deployed = |raw: &str| RawTag::new(raw).parse_version().unwrap();
+        let evaluate_deployed = |current: &str, tags: &[&str]| {
+            let current = deployed(current);
+            let filter = CandidateFilter::default().with_deployed(Some(&current));
+            evaluate(&Deployed::Version(current.clone()), tags, &filter)
+        };
+
+        assert_eq!(
+            Some(UpdatedVersion::OutOfDate {
+                current: "2023.12".to_string(),
+                newest: "2024.01".to_string(),
+                newer: 1,
+            }),
+            evaluate_deployed("2023.12", &["2023.12", "2024.01", "3.0.0"])
+        );
+        assert_eq!(
+            Some(UpdatedVersion::UpToDate {
+                version: "1.2.3".to_string(),
+            }),
+            evaluate_deployed("1.2.3", &["1.2.3", "20240115"])
+        );
+
+        let semver = deployed("1.2.3");
+        assert_eq!(
+            Some(deployed("1.3.0")),
+            CandidateFilter::default()
+                .with_deployed(Some(&semver))
+                .newest_release(&["1.3.0", "2024.01"])
+        );
+    }
+
+    #[test]
+    fn missing_as_zero_min_version() {
+        let meta: HashMap<String, String> = [(MIN_VERSION_META.to_string(), "1.2".to_string())]
+            .into_iter()
+            .collect();
+        let tags = ["1.1.0", "1.2.0"];
+
+        let strict = CandidateFilter::from_meta(Some(&meta));
+        assert_eq!(None, strict.newest(&tags));
+
+        let lenient =
+            CandidateFilter::from_meta(Some(&meta)).with_ordering(VersionOrdering::MissingAsZero);
+        assert_eq!(Some(semantic(1, 2, 0)), lenient.newest(&tags));
+        assert_eq!(
+            Some(UpdatedVersion::UpToDate {
+                version: "1.2".to_string()
+            }),
+            evaluate(
+                &Deployed::Version(RawTag::new("1.2").parse_version().unwrap()),
+                &tags,
+                &lenient
+            )
+        );
+    }
+
+    #[test]
+    fn channel_order_compare() {
+        let order = channels();
+
+        assert_eq!(std::cmp::Ordering::Less, order.compare("stable", "canary"));
+        assert_eq!(std::cmp::Ordering::Greater, order.compare("beta", "stable"));
+        assert_eq!(std::cmp::Ordering::Less, order.compare("nightly", "stable"));
+        assert_eq!(std::cmp::Ordering::Equal, order.compare("nightly", "edge"));
+    }
+
+    #[test]
+    fn named_version_out_of_date() {
+        let filter = CandidateFilter::default().with_channels(channels());
+        let tags = ["stable", "beta", "canary", "1.0.0"];
+
+        assert_eq!(
+            Some(UpdatedVersion::OutOfDate {
+                current: "stable".to_string(),
+                newest: "canary".to_string(),
+                newer: 2,
+            }),
+            evaluate(
+                &Deployed::Version(Version::Named("stable".to_string())),
+                &tags,
+                &filter
+            )
+        );
+        assert_eq!(
+            Some(UpdatedVersion::UpToDate {
+                version: "canary".to_string(),
+            }),
+            evaluate(
+                &Deployed::Version(Version::Named("canary".to_string())),
+                &tags,
+                &filter
+            )
+        );
+        assert_eq!(
+            Some(UpdatedVersion::UpToDate {
+                version: "beta".to_string(),
+            }),
+            evaluate(
+                &Deployed::Version(Version::Named("beta".to_string())),
+                &["stable", "beta", "1.0.0"],
+                &filter
+            )
+        );
+    }
+
+    #[test]
+    fn mirror_behind_upstream() {
+        let meta: HashMap<String, String> = [(
+            UPSTREAM_IMAGE_META.to_string(),
+            "ghcr.io/user/app".to_string(),
+        )]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            Some("ghcr.io/user/app".to_string()),
+            upstream_from_meta(Some(&meta))
+        );
+
+        let filter = CandidateFilter::default();
+        let status = MirrorStatus::compare(
+            &["1.0.0", "1.1.0"],
+            &["1.0.0", "1.1.0", "1.2.0", "latest"],
+            &filter,
+        );
+
+        assert_eq!(Some(semantic(1, 1, 0)), status.mirror_newest);
+        assert_eq!(Some(semantic(1, 2, 0)), status.upstream_newest);
+        assert!(status.lagging());
+
+        let status = MirrorStatus::compare(&["1.0.0", "1.2.0"], &["1.2.0"], &filter);
+        assert!(!status.lagging());
+
+        // `1.2` is the same as `1.2.0` when missing Parts count as zero
+        let filter = CandidateFilter::default().with_ordering(VersionOrdering::MissingAsZero);
+        let status = MirrorStatus::compare(&["1.0", "1.2"], &["1.2.0"], &filter);
+        assert!(!status.lagging());
+        let status = MirrorStatus::compare(&["1.2"], &["1.2.1"], &filter);
+        assert!(status.lagging());
+    }
+
+    #[test]
+    fn digest_pinned_with_track_tag() {
+        let image =
+            crate::docker::Image::parse("user/app@sha256:abcdef".to_string()).expect("Valid Image");
+        let meta: HashMap<String, String> = [(TRACK_TAG_META.to_string(), "1.x".to_string())]
+            .into_iter()
+            .collect();
+        let filter = CandidateFilter::from_meta(Some(&meta));
+
+        let deployed = Deployed::Digest(image.digest.expect("Image has a Digest"));
+        let result = evaluate(&deployed, &["1.2.0", "1.4.1", "2.0.0"], &filter);
+
+        assert_eq!(
+            Some(UpdatedVersion::DigestPinned {
+                digest: "sha256:abcdef".to_string(),
+                newest: "1.4.1".to_string(),
+            }),
+            result
+        );
+    }
+}
@@ -0,0 +1,567 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    docker::{Version, VersionOrdering},
+    metrics::{Metrics, UpdatedVersion},
+    select::{CandidateCounts, MirrorStatus},
+};
+
+/// Identifies a Task across Checks
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TaskKey {
+    pub namespace: String,
+    pub job: String,
+    pub group: String,
+    pub task: String,
+}
+
+/// The State that is kept between Checks
+#[derive(Debug, Default)]
+pub struct CycleState {
+    /// The Position in the list of Tasks at which the next Check continues
+    cursor: usize,
+    /// The latest Result for every Task
+    results: BTreeMap<TaskKey, UpdatedVersion>,
+    /// The Digest the deployed Tag of every Task resolved to
+    digests: BTreeMap<TaskKey, DigestRecord>,
+    /// How the Mirror of every mirrored Task compares to its Upstream
+    mirrors: BTreeMap<TaskKey, MirrorStatus>,
+    /// How many Tags of the Image of every Task were left as Candidates by the Filter
+    candidates: BTreeMap<TaskKey, CandidateCounts>,
+    /// The Versions newer than the deployed one of every Task, oldest first
+    newer: BTreeMap<TaskKey, Vec<String>>,
+    /// The parsed Versions of every Task that is out of date
+    compared: BTreeMap<TaskKey, ComparedVersions>,
+    /// The newest Version available for every Repository, by its Name like `ghcr.io/user/app`
+    registry_newest: BTreeMap<String, NewestRecord>,
+    /// For how many consecutive Checks a Task was observed to have flipped between being out of
+    /// date and not, without its Result being updated yet
+    flips: BTreeMap<TaskKey, usize>,
+    /// The Digest `latest` resolved to when every Task running the plain `latest` Tag was first
+    /// seen with the current Version of its Job
+    latest: BTreeMap<TaskKey, LatestBaseline>,
+    /// The Owner and Version the Metrics of every Task were last set to, so unchanged Tasks are
+    /// not set again
+    published: BTreeMap<TaskKey, (String, UpdatedVersion)>,
+}
+
+/// Computes a stable Hash over the monitored Tasks and their Images, independent of their
+/// order, which only changes when the monitored Tasks change
+pub fn fingerprint<'a, I>(tasks: I) -> String
+where
+    I: IntoIterator<Item = (&'a TaskKey, &'a str)>,
+{
+    let tasks: BTreeSet<_> = tasks.into_iter().collect();
+
+    // FNV-1a, as the Hashers of the standard library are not guaranteed to be stable
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for (key, image) in tasks {
+        for field in [&key.namespace, &key.job, &key.group, &key.task, image] {
+            for byte in field.bytes().chain(std::iter::once(0)) {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+    }
+
+    format!("{hash:016x}")
+}
+
+/// The Digest a deployed Tag resolved to
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigestRecord {
+    pub tag: String,
+    pub digest: String,
+    /// Whether the Tag pointed to a different Digest in the previous Check
+    pub changed: bool,
+}
+
+/// The Digest `latest` resolved to when a Task was first seen with a Version of its Job, which is
+/// assumed to be the Digest the Task runs, as Nomad does not know it
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatestBaseline {
+    pub job_version: u64,
+    pub digest: String,
+}
+
+/// The deployed Version of a Task and the newest one it was compared against, as parsed
+/// instead of how they are displayed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparedVersions {
+    pub current: Version,
+    pub newest: Version,
+}
+
+/// The newest Version available for a Repository
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewestRecord {
+    pub version: Version,
+    /// Whether a newer Version was available in the previous Check, like when a Tag was
+    /// deleted upstream
+    pub regressed: bool,
+}
+
+impl CycleState {
+    /// Selects the indices of the Tasks that should be processed in this Check, continuing
+    /// where the last Check stopped, and advances the cursor
+    pub fn next_window(&mut self, total: usize, max: Option<usize>) -> BTreeSet<usize> {
+        match max {
+            Some(max) if max < total => {
+                let start = self.cursor % total;
+                self.cursor = (start + max) % total;
+
+                (start..start + max).map(|i| i % total).collect()
+            }
+            _ => {
+                self.cursor = 0;
+                (0..total).collect()
+            }
+        }
+    }
+
+    /// Stores the Results of the processed Tasks and forgets all Tasks that no longer exist
+    pub fn merge(
+        &mut self,
+        existing: &BTreeSet<TaskKey>,
+        processed: Vec<(TaskKey, Option<UpdatedVersion>)>,
+    ) {
+        self.results.retain(|key, _| existing.contains(key));
+        self.digests.retain(|key, _| existing.contains(key));
+        self.mirrors.retain(|key, _| existing.contains(key));
+        self.candidates.retain(|key, _| existing.contains(key));
+        self.newer.retain(|key, _| existing.contains(key));
+        self.compared.retain(|key, _| existing.contains(key));
+        self.flips.retain(|key, _| existing.contains(key));
+        self.latest.retain(|key, _| existing.contains(key));
+
+        for (key, result) in processed {
+            match result {
+                Some(r) => {
+                    self.results.insert(key, r);
+                }
+                None => {
+                    self.results.remove(&key);
+                }
+            };
+        }
+    }
+
+    /// Delays Results that flip a Task between being out of date and not until they were
+    /// observed in `threshold` consecutive Checks, returning the previous Result until then
+    pub fn debounce(
+        &mut self,
+        processed: Vec<(TaskKey, Option<UpdatedVersion>)>,
+        threshold: usize,
+    ) -> Vec<(TaskKey, Option<UpdatedVersion>)> {
+        processed
+            .into_iter()
+            .map(|(key, result)| {
+                let is_out = |r: &UpdatedVersion| matches!(r, UpdatedVersion::OutOfDate { .. });
+
+                let previous = match (self.results.get(&key), result.as_ref()) {
+                    (Some(previous), Some(current)) if is_out(previous) != is_out(current) => {
+                        previous
+                    }
+                    _ => {
+                        self.flips.remove(&key);
+                        return (key, result);
+                    }
+                };
+
+                let flips = self.flips.entry(key.clone()).or_default();
+                *flips += 1;
+                if *flips >= threshold {
+                    self.flips.remove(&key);
+                    return (key, result);
+                }
+
+                let previous = previous.clone();
+                (key, Some(previous))
+            })
+            .collect()
+    }
+
+    /// Stores the Digest the deployed Tag of the Task resolved to and returns whether the same
+    /// Tag resolved to a different Digest before
+    pub fn record_digest(&mut self, key: TaskKey, tag: String, digest: String) -> bool {
+        let changed = matches!(
+            self.digests.get(&key),
+            Some(previous) if previous.tag == tag && previous.digest != digest
+        );
+
+        self.digests.insert(
+            key,
+            DigestRecord {
+                tag,
+                digest,
+                changed,
+            },
+        );
+
+        changed
+    }
+
+    pub fn digests(&self) -> &BTreeMap<TaskKey, DigestRecord> {
+        &self.digests
+    }
+
+    /// Compares the Digest `latest` currently resolves to against the one it resolved to when
+    /// the Task was first seen with the same Version of its Job
+    pub fn compare_latest(
+        &mut self,
+        key: TaskKey,
+        job_version: u64,
+        digest: String,
+    ) -> UpdatedVersion {
+        let baseline = self.latest.entry(key).or_insert_with(|| LatestBaseline {
+            job_version,
+            digest: digest.clone(),
+        });
+        // Changing the Job redeploys it, which pulls `latest` again
+        if baseline.job_version != job_version {
+            *baseline = LatestBaseline {
+                job_version,
+                digest: digest.clone(),
+            };
+        }
+
+        if baseline.digest == digest {
+            UpdatedVersion::UpToDate {
+                version: "latest".to_string(),
+            }
+        } else {
+            UpdatedVersion::OutOfDate {
+                current: format!("latest@{}", baseline.digest),
+                newest: format!("latest@{digest}"),
+                newer: 1,
+            }
+        }
+    }
+
+    /// Stores how the Mirror of the Task compares to its Upstream, forgetting it if the Task is
+    /// not mirrored (anymore)
+    pub fn record_mirror(&mut self, key: TaskKey, status: Option<MirrorStatus>) {
+        match status {
+            Some(s) => {
+                self.mirrors.insert(key, s);
+            }
+            None => {
+                self.mirrors.remove(&key);
+            }
+        };
+    }
+
+    pub fn mirrors(&self) -> &BTreeMap<TaskKey, MirrorStatus> {
+        &self.mirrors
+    }
+
+    /// Stores how many Tags of the Image of the Task were left as Candidates, forgetting it if
+    /// the Tags could not be loaded
+    pub fn record_candidates(&mut self, key: TaskKey, counts: Option<CandidateCounts>) {
+        match counts {
+            Some(c) => {
+                self.candidates.insert(key, c);
+            }
+            None => {
+                self.candidates.remove(&key);
+            }
+        };
+    }
+
+    pub fn candidates(&self) -> &BTreeMap<TaskKey, CandidateCounts> {
+        &self.candidates
+    }
+
+    /// Stores the Versions newer than the deployed one of the Task
+    pub fn record_newer(&mut self, key: TaskKey, newer: Vec<String>) {
+        self.newer.insert(key, newer);
+    }
+
+    pub fn newer(&self) -> &BTreeMap<TaskKey, Vec<String>> {
+        &self.newer
+    }
+
+    /// Stores the parsed Versions the Task was compared with, forgetting them if the Task is
+    /// not out of date
+    pub fn record_compared(&mut self, key: TaskKey, compared: Option<ComparedVersions>) {
+        match compared {
+            Some(c) => {
+                self.compared.insert(key, c);
+            }
+            None => {
+                self.compared.remove(&key);
+            }
+        };
+    }
+
+    pub fn compared(&self) -> &BTreeMap<TaskKey, ComparedVersions> {
+        &self.compared
+    }
+
+    /// Stores the newest Version available for the Repository and returns whether it is older
+    /// than the one available in the previous Check, which is only the case for Versions of
+    /// the same Scheme
+    pub fn record_registry_newest(
+        &mut self,
+        repository: String,
+        version: Version,
+        ordering: VersionOrdering,
+    ) -> bool {
+        let regressed = matches!(
+            self.registry_newest.get(&repository),
+            Some(previous) if previous.version.is_calver() == version.is_calver()
+                && ordering.compare(&version, &previous.version) == std::cmp::Ordering::Less
+        );
+
+        self.registry_newest
+            .insert(repository, NewestRecord { version, regressed });
+
+        regressed
+    }
+
+    /// Forgets the newest Versions of all Repositories that are no longer deployed
+    pub fn retain_repositories(&mut self, existing: &BTreeSet<String>) {
+        self.registry_newest
+            .retain(|repository, _| existing.contains(repository));
+    }
+
+    pub fn registry_newest(&self) -> &BTreeMap<String, NewestRecord> {
+        &self.registry_newest
+    }
+
+    /// The latest Result for every Task
+    pub fn results(&self) -> &BTreeMap<TaskKey, UpdatedVersion> {
+        &self.results
+    }
+
+    /// Sets the Metrics of every Task to its latest Result, labeled with the Owner of its Job
+    /// from `owners`, which is keyed by the Task as Jobs are only unique within a Namespace.
+    /// Only Tasks whose Owner or Result changed since they were last published are set again,
+    /// and the Metrics of Tasks without a Result are removed
+    pub fn publish(&mut self, metrics: &Metrics, owners: &BTreeMap<TaskKey, String>) {
+        let results = &self.results;
+        self.published.retain(|key, (owner, version)| {
+            if results.contains_key(key) {
+                return true;
+            }
+
+            metrics.remove(key, owner, version);
+            false
+        });
+
+        for (key, version) in results {
+            let owner = owners.get(key).cloned().unwrap_or_default();
+            match self.published.get(key) {
+                Some((p_owner, p_version)) if *p_owner == owner && p_version == version => {
+                    continue;
+                }
+                Some((p_owner, p_version)) => metrics.remove(key, p_owner, p_version),
+                None => {}
+            };
+
+            metrics.set(key, &owner, version.clone());
+            self.published.insert(key.clone(), (owner, version.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(task: &str) -> TaskKey {
+        TaskKey {
+            namespace: "default".to_string(),
+            job: "job".to_string(),
+            group: "group".to_string(),
+            task: task.to_string(),
+        }
+    }
+
+    #[test]
+    fn registry_newest_of_same_scheme() {
+        let version = |raw: &str| crate::docker::RawTag::new(raw).parse_version().unwrap();
+        let mut state = CycleState::default();
+        let mut record = |raw: &str| {
+            state.record_registry_newest("app".to_string(), version(raw), Default::default())
+        };
+
+        assert!(!record("2024.01"));
+        assert!(!record("1.2.0"), "Schemes are not compared");
+        assert!(!record("1.3.0"));
+        assert!(record("1.2.0"));
+    }
+
+    #[test]
+    fn unchanged_task_not_published_again() {
+        let reg = prometheus::Registry::new();
+        let metrics = Metrics::new(&reg).unwrap();
+        let owners = BTreeMap::new();
+        let version_info = || {
+            reg.gather()
+                .into_iter()
+                .find(|f| f.get_name() == "task_version_info")
+                .map(|f| f.get_metric().to_vec())
+                .unwrap_or_default()
+        };
+
+        let mut state = CycleState::default();
+        let existing = BTreeSet::from([key("task")]);
+        let out_of_date = UpdatedVersion::OutOfDate {
+            current: "1.0.0".to_string(),
+            newest: "1.1.0".to_string(),
+            newer: 1,
+        };
+        state.merge(&existing, vec![(key("task"), Some(out_of_date))]);
+        state.publish(&metrics, &owners);
+        state.publish(&metrics, &owners);
+        assert_eq!(1, version_info().len());
+
+        // A changed Version replaces the previous Series instead of adding another one
+        let up_to_date = UpdatedVersion::UpToDate {
+            version: "1.1.0".to_string(),
+        };
+        state.merge(&existing, vec![(key("task"), Some(up_to_date))]);
+        state.publish(&metrics, &owners);
+        let versions = version_info();
+        assert_eq!(1, versions.len());
+        assert!(versions[0]
+            .get_label()
+            .iter()
+            .all(|l| l.get_name() != "current" || l.get_value() == "1.1.0"));
+
+        state.merge(&BTreeSet::new(), Vec::new());
+        state.publish(&metrics, &owners);
+        assert!(version_info().is_empty());
+    }
+
+    #[test]
+    fn unlimited_window() {
+        let mut state = CycleState::default();
+
+        assert_eq!(BTreeSet::from([0, 1, 2, 3, 4]), state.next_window(5, None));
+        assert_eq!(
+            BTreeSet::from([0, 1, 2, 3, 4]),
+            state.next_window(5, Some(10))
+        );
+        assert_eq!(BTreeSet::new(), state.next_window(0, Some(2)));
+    }
+
+    #[test]
+    fn capped_window_round_robin() {
+        let mut state = CycleState::default();
+
+        let first = state.next_window(5, Some(2));
+        let second = state.next_window(5, Some(2));
+        let third = state.next_window(5, Some(2));
+
+        assert_eq!(BTreeSet::from([0, 1]), first);
+        assert_eq!(BTreeSet::from([2, 3]), second);
+        assert_eq!(BTreeSet::from([4, 0]), third);
+
+        let covered: BTreeSet<_> = first.into_iter().chain(second).chain(third).collect();
+        assert_eq!(BTreeSet::from([0, 1, 2, 3, 4]), covered);
+    }
+
+    #[test]
+    fn merge_keeps_unprocessed() {
+        let mut state = CycleState::default();
+        let version = |v: &str| UpdatedVersion::UpToDate {
+            version: v.to_string(),
+        };
+
+        state.merge(
+            &BTreeSet::from([key("a"), key("b")]),
+            vec![
+                (key("a"), Some(version("1"))),
+                (key("b"), Some(version("2"))),
+            ],
+        );
+        state.merge(
+            &BTreeSet::from([key("a"), key("c")]),
+            vec![(key("c"), Some(version("3")))],
+        );
+
+        assert_eq!(
+            vec![&key("a"), &key("c")],
+            state.results().keys().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn debounce_flips() {
+        let mut state = CycleState::default();
+        let existing = BTreeSet::from([key("a")]);
+        let up_to_date = UpdatedVersion::UpToDate {
+            version: "1.0.0".to_string(),
+        };
+        let out_of_date = UpdatedVersion::OutOfDate {
+            current: "1.0.0".to_string(),
+            newest: "1.1.0".to_string(),
+            newer: 1,
+        };
+
+        let mut cycle = |result: &UpdatedVersion| {
+            let processed = state.debounce(vec![(key("a"), Some(result.clone()))], 3);
+            state.merge(&existing, processed);
+            state.results().get(&key("a")).cloned()
+        };
+
+        // The first Result is taken as is
+        assert_eq!(Some(up_to_date.clone()), cycle(&up_to_date));
+
+        // A single glitch does not flip the Task and resets the count
+        assert_eq!(Some(up_to_date.clone()), cycle(&out_of_date));
+        assert_eq!(Some(up_to_date.clone()), cycle(&up_to_date));
+
+        assert_eq!(Some(up_to_date.clone()), cycle(&out_of_date));
+        assert_eq!(Some(up_to_date.clone()), cycle(&out_of_date));
+        assert_eq!(Some(out_of_date.clone()), cycle(&out_of_date));
+
+        // Flipping back needs the same number of Checks
+        assert_eq!(Some(out_of_date.clone()), cycle(&up_to_date));
+        assert_eq!(Some(out_of_date.clone()), cycle(&up_to_date));
+        assert_eq!(Some(up_to_date.clone()), cycle(&up_to_date));
+    }
+
+    #[test]
+    fn fingerprint_changes_with_tasks() {
+        let (a, b, c) = (key("a"), key("b"), key("c"));
+        let tasks = vec![(&a, "user/app:1.0.0"), (&b, "user/db:2.0.0")];
+
+        let first = fingerprint(tasks.clone());
+        assert_eq!(first, fingerprint(tasks.iter().rev().cloned()));
+
+        let mut added = tasks.clone();
+        added.push((&c, "user/cache:3.0.0"));
+        assert_ne!(first, fingerprint(added));
+
+        let changed = vec![(&a, "user/app:1.1.0"), (&b, "user/db:2.0.0")];
+        assert_ne!(first, fingerprint(changed));
+    }
+
+    #[test]
+    fn latest_compared_against_baseline() {
+        let mut state = CycleState::default();
+        let up_to_date = UpdatedVersion::UpToDate {
+            version: "latest".to_string(),
+        };
+
+        let mut compare = |job_version: u64, digest: &str| {
+            state.compare_latest(key("task"), job_version, digest.to_string())
+        };
+        assert_eq!(up_to_date, compare(1, "sha256:aaa"));
+        assert_eq!(up_to_date, compare(1, "sha256:aaa"));
+        assert_eq!(
+            UpdatedVersion::OutOfDate {
+                current: "latest@sha256:aaa".to_string(),
+                newest: "latest@sha256:bbb".to_string(),
+                newer: 1,
+            },
+            compare(1, "sha256:bbb")
+        );
+
+        // Redeploying the Job pulls the new Digest
+        assert_eq!(up_to_date, compare(2, "sha256:bbb"));
+    }
+}
@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{metrics::UpdatedVersion, state::TaskKey};
+
+/// The Result of the last Check for a single Task
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskStatus {
+    pub namespace: String,
+    pub job: String,
+    pub group: String,
+    pub task: String,
+    /// Whether the [`state`](Self::state) is [`TaskState::UpToDate`], for consumers that only
+    /// care about that
+    pub up_to_date: bool,
+    pub current: String,
+    pub newest: String,
+    pub state: TaskState,
+}
+
+/// The Status of a single Task together with the Versions it could be updated to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskDetail {
+    #[serde(flatten)]
+    pub status: TaskStatus,
+    /// The available Versions newer than the current one, oldest first
+    pub newer: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    UpToDate,
+    OutOfDate,
+    Floating,
+    DigestPinned,
+    NotTrackable,
+}
+
+/// The Formats in which the Status can be rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusFormat {
+    #[default]
+    Json,
+    Csv,
+    Text,
+}
+
+const CSV_HEADER: [&str; 6] = ["job", "group", "task", "current", "newest", "state"];
+
+impl TaskStatus {
+    pub(crate) fn new(key: &TaskKey, version: &UpdatedVersion) -> Self {
+        let (current, newest, state) = match version {
+            UpdatedVersion::UpToDate { version } => {
+                (version.clone(), version.clone(), TaskState::UpToDate)
+            }
+            UpdatedVersion::OutOfDate {
+                current, newest, ..
+            } => (current.clone(), newest.clone(), TaskState::OutOfDate),
+            UpdatedVersion::Floating { tag } => (tag.clone(), String::new(), TaskState::Floating),
+            UpdatedVersion::DigestPinned { digest, newest } => {
+                (digest.clone(), newest.clone(), TaskState::DigestPinned)
+            }
+            UpdatedVersion::NotTrackable => (String::new(), String::new(), TaskState::NotTrackable),
+        };
+
+        Self {
+            namespace: key.namespace.clone(),
+            job: key.job.clone(),
+            group: key.group.clone(),
+            task: key.task.clone(),
+            up_to_date: state == TaskState::UpToDate,
+            current,
+            newest,
+            state,
+        }
+    }
+
+    fn fields(&self) -> [&str; 6] {
+        [
+            &self.job,
+            &self.group,
+            &self.task,
+            &self.current,
+            &self.newest,
+            self.state.as_str(),
+        ]
+    }
+}
+
+impl TaskState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UpToDate => "up_to_date",
+            Self::OutOfDate => "out_of_date",
+            Self::Floating => "floating",
+            Self::DigestPinned => "digest_pinned",
+            Self::NotTrackable => "not_trackable",
+        }
+    }
+}
+
+impl StatusFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Text => "text/plain; charset=utf-8",
+        }
+    }
+
+    pub fn render(&self, statuses: &[TaskStatus]) -> String {
+        match self {
+            Self::Json => serde_json::to_string(statuses).expect("Status is always serializable"),
+            Self::Csv => render_csv(statuses),
+            Self::Text => render_text(statuses),
+        }
+    }
+}
+
+fn render_csv(statuses: &[TaskStatus]) -> String {
+    let mut result = CSV_HEADER.join(",");
+    result.push('\n');
+
+    for status in statuses {
+        let row = status.fields().map(csv_escape);
+        result.push_str(&row.join(","));
+        result.push('\n');
+    }
+
+    result
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn render_text(statuses: &[TaskStatus]) -> String {
+    let header = CSV_HEADER.map(|h| h.to_uppercase());
+
+    let mut widths = header.clone().map(|h| h.len());
+    for status in statuses {
+        for (width, field) in widths.iter_mut().zip(status.fields()) {
+            *width = core::cmp::max(*width, field.len());
+        }
+    }
+
+    let render_row = |fields: [&str; 6]| {
+        let mut line = fields
+            .iter()
+            .zip(widths.iter())
+            .map(|(field, width)| format!("{field:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        line.truncate(line.trim_end().len());
+        line.push('\n');
+        line
+    };
+
+    let mut result = render_row(header.each_ref().map(|h| h.as_str()));
+    for status in statuses {
+        result.push_str(&render_row(status.fields()));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(job: &str, group: &str, task: &str) -> TaskKey {
+        TaskKey {
+            namespace: "default".to_string(),
+            job: job.to_string(),
+            group: group.to_string(),
+            task: task.to_string(),
+        }
+    }
+
+    fn statuses() -> Vec<TaskStatus> {
+        vec![
+            TaskStatus::new(
+                &key("web", "frontend", "nginx"),
+                &UpdatedVersion::OutOfDate {
+                    current: "1.24.0".to_string(),
+                    newest: "1.25.3".to_string(),
+                    newer: 4,
+                },
+            ),
+            TaskStatus::new(
+                &key("db", "storage", "postgres"),
+                &UpdatedVersion::UpToDate {
+                    version: "16.1.0".to_string(),
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn render_json() {
+        let rendered = StatusFormat::Json.render(&statuses());
+
+        let value: serde_json::Value = serde_json::from_str(&rendered).expect("Valid JSON");
+        let entries = value.as_array().expect("Top-Level Array");
+        assert_eq!(2, entries.len());
+        assert_eq!(
+            serde_json::json!({
+                "namespace": "default",
+                "job": "web",
+                "group": "frontend",
+                "task": "nginx",
+                "up_to_date": false,
+                "current": "1.24.0",
+                "newest": "1.25.3",
+                "state": "out_of_date",
+            }),
+            entries[0]
+        );
+        assert_eq!("up_to_date", entries[1]["state"]);
+        assert_eq!(true, entries[1]["up_to_date"]);
+    }
+
+    #[test]
+    fn render_csv() {
+        let rendered = StatusFormat::Csv.render(&statuses());
+
+        assert_eq!(
+            "job,group,task,current,newest,state\n\
+             web,frontend,nginx,1.24.0,1.25.3,out_of_date\n\
+             db,storage,postgres,16.1.0,16.1.0,up_to_date\n",
+            rendered
+        );
+    }
+
+    #[test]
+    fn render_csv_escapes_fields() {
+        let mut statuses = statuses();
+        statuses[0].job = "web,\"prod\"".to_string();
+
+        let rendered = StatusFormat::Csv.render(&statuses);
+
+        assert_eq!(
+            Some("\"web,\"\"prod\"\"\",frontend,nginx,1.24.0,1.25.3,out_of_date"),
+            rendered.lines().nth(1)
+        );
+    }
+
+    #[test]
+    fn render_csv_empty() {
+        assert_eq!(
+            "job,group,task,current,newest,state\n",
+            StatusFormat::Csv.render(&[])
+        );
+    }
+
+    #[test]
+    fn render_text() {
+        let rendered = StatusFormat::Text.render(&statuses());
+
+        assert_eq!(
+            "JOB  GROUP     TASK      CURRENT  NEWEST  STATE\n\
+             web  frontend  nginx     1.24.0   1.25.3  out_of_date\n\
+             db   storage   postgres  16.1.0   16.1.0  up_to_date\n",
+            rendered
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::{sync::Mutex, time::Duration};
+
+use serde::Serialize;
+
+/// How many Notifications can be queued before new ones are dropped
+const QUEUE_LEN: usize = 128;
+
+/// Where Notifications about Tasks becoming out of date are posted to
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    /// The URL the Notifications are POSTed to as JSON
+    pub url: reqwest::Url,
+    /// How long pending Notifications are still sent when shutting down
+    pub drain_timeout: Duration,
+}
+
+/// Sent when a Task becomes out of date
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Notification {
+    pub job: String,
+    pub group: String,
+    pub task: String,
+    pub current: String,
+    pub newest: String,
+}
+
+/// Posts Notifications to the Webhook in the Background, using a bounded Queue
+#[derive(Debug)]
+pub struct Notifier {
+    client: reqwest::Client,
+    conf: WebhookConfig,
+    sender: Mutex<Option<tokio::sync::mpsc::Sender<Notification>>>,
+    /// The Receiver of the Queue until the Worker is started
+    receiver: Mutex<Option<tokio::sync::mpsc::Receiver<Notification>>>,
+    worker: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+impl Notifier {
+    /// Creates the Notifier, Notifications are queued until the Worker is
+    /// [started](Self::start)
+    pub fn new(client: reqwest::Client, conf: WebhookConfig) -> Self {
+        let (sender, receiver) = tokio::sync::mpsc::channel::<Notification>(QUEUE_LEN);
+
+        Self {
+            client,
+            conf,
+            sender: Mutex::new(Some(sender)),
+            receiver: Mutex::new(Some(receiver)),
+            worker: Mutex::new(None),
+        }
+    }
+
+    /// Starts the Background-Worker sending the Notifications, which requires a Tokio Runtime.
+    /// Does nothing if it was already started
+    pub fn start(&self) {
+        let mut receiver = match self.receiver.lock().unwrap().take() {
+            Some(r) => r,
+            None => return,
+        };
+
+        let client = self.client.clone();
+        let url = self.conf.url.clone();
+        let worker = tokio::spawn(async move {
+            while let Some(notification) = receiver.recv().await {
+                if let Err(e) = post(&client, &url, &notification).await {
+                    tracing::error!("Sending Webhook Notification: {}", e);
+                }
+            }
+        });
+
+        *self.worker.lock().unwrap() = Some(worker);
+    }
+
+    /// Queues the Notification, dropping it if the Queue is full or the Notifier was drained
+    pub fn notify(&self, notification: Notification) {
+        let sender = self.sender.lock().unwrap();
+        let sender = match sender.as_ref() {
+            Some(s) => s,
+            None => {
+                tracing::warn!("Dropping Notification after shutdown: {:?}", notification);
+                return;
+            }
+        };
+
+        if let Err(e) = sender.try_send(notification) {
+            tracing::warn!("Dropping Notification: {}", e);
+        }
+    }
+
+    /// Stops accepting new Notifications and waits for the queued ones to be sent, returns
+    /// whether all of them were sent before the drain timeout
+    pub async fn drain(&self) -> bool {
+        drop(self.sender.lock().unwrap().take());
+        // Notifications queued before the Worker was started are still sent
+        self.start();
+
+        let worker = match self.worker.lock().unwrap().take() {
+            Some(w) => w,
+            None => return true,
+        };
+
+        match tokio::time::timeout(self.conf.drain_timeout, worker).await {
+            Ok(_) => true,
+            Err(_) => {
+                tracing::warn!(
+                    "Pending Notifications were not sent within {:?}",
+                    self.conf.drain_timeout
+                );
+                false
+            }
+        }
+    }
+}
+
+async fn post(
+    client: &reqwest::Client,
+    url: &reqwest::Url,
+    notification: &Notification,
+) -> Result<(), reqwest::Error> {
+    let body = serde_json::to_vec(notification).expect("Notification is always serializable");
+
+    client
+        .post(url.clone())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn drain_flushes_queue() {
+        use wiremock::{
+            matchers::{method, path},
+            Mock, MockServer, ResponseTemplate,
+        };
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(100)))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let notifier = Notifier::new(
+            reqwest::Client::new(),
+            WebhookConfig {
+                url: reqwest::Url::parse(&format!("{}/hook", server.uri())).unwrap(),
+                drain_timeout: Duration::from_secs(5),
+            },
+        );
+        notifier.start();
+        for task in ["a", "b", "c"] {
+            notifier.notify(Notification {
+                job: "job".to_string(),
+                group: "group".to_string(),
+                task: task.to_string(),
+                current: "1.0.0".to_string(),
+                newest: "1.1.0".to_string(),
+            });
+        }
+
+        assert!(notifier.drain().await);
+        server.verify().await;
+
+        // Notifications after the drain are dropped instead of being queued
+        notifier.notify(Notification {
+            job: "job".to_string(),
+            group: "group".to_string(),
+            task: "d".to_string(),
+            current: "1.0.0".to_string(),
+            newest: "1.1.0".to_string(),
+        });
+    }
+}